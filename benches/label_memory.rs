@@ -0,0 +1,59 @@
+//! Measures the peak heap memory used to cluster a dataset, to keep track of the copies made while clustering.
+//!
+//! Run with `cargo bench --bench label_memory`. The points are generated so that most cells are sparse, which is the
+//! case where the labelling of the core points reads the neighbour cells the most.
+
+use appr_dbscan::dbscan::approximate_dbscan_labels;
+use appr_dbscan::generate::uniform;
+use appr_dbscan::utils::{vector_input_to_array_input, DBSCANParams, Point};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Wraps the system allocator to keep track of the bytes allocated and of their peak
+struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAllocator = PeakAllocator;
+
+/// Points spread uniformly in a square, from a fixed seed so that each run clusters the same points
+fn uniform_points(count: usize, side: f64) -> Vec<Point<2>> {
+    vector_input_to_array_input(uniform(count, 2, side, 0).points)
+}
+
+fn main() {
+    for &count in &[100_000, 1_000_000] {
+        let points = uniform_points(count, (count as f64).sqrt() * 0.1);
+        let params = DBSCANParams{cardinality: count, dimensionality: 2, epsilon: 0.2, rho: 0.1, min_pts: 10};
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        PEAK.store(before, Ordering::Relaxed);
+        let start = Instant::now();
+        let labels = approximate_dbscan_labels(&points, &params);
+        let elapsed = start.elapsed();
+        let peak = PEAK.load(Ordering::Relaxed) - before;
+        let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+        println!(
+            "{} points, {} clusters: peak {:.1} MiB over the input ({:.1} bytes per point) in {:.2?}",
+            count, clusters_count, peak as f64 / (1024.0 * 1024.0), peak as f64 / count as f64, elapsed
+        );
+    }
+}
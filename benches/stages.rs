@@ -0,0 +1,143 @@
+//! Times each stage of the approximate DBSCAN algorithm, and the whole run, on the bundled datasets and on synthetic
+//! clusters of dimensionality 2 to 7 made by `generate::blobs`.
+//!
+//! Run with `cargo bench --features internals --bench stages`. The synthetic datasets have 10^3, 10^4 and 10^5 points,
+//! and also 10^6 and 10^7 points when the `APPR_DBSCAN_BENCH_LARGE` environment variable is set. A subset of the
+//! benchmarks can be selected as usual with criterion, for example `cargo bench --features internals --bench stages -- blobs_3d`.
+//!
+//! The `sparse_cells` benchmarks compare `label_points` with `label_points_scanning_neighbours` as the points around
+//! the sparse cells grow, with `min_pts` set so that the cells near the centers of the clusters are still sparse.
+
+use appr_dbscan::data_io::read_vector_points_from_file;
+use appr_dbscan::dbscan::approximate_dbscan_labels;
+use appr_dbscan::generate::blobs;
+use appr_dbscan::internals::*;
+use appr_dbscan::utils::{vector_input_to_array_input, DBSCANParams, Point};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::time::Duration;
+
+/// The number of gaussian clusters of unit variance of the synthetic datasets
+const BLOBS_COUNT: usize = 10;
+/// The expected number of points at distance at most `epsilon` from the center of a synthetic cluster
+const CENTER_NEIGHBOURS: f64 = 50.0;
+/// The value of `min_pts` used on the synthetic datasets
+const SYNTHETIC_MIN_PTS: usize = 20;
+/// The number of points of the datasets used to compare the ways of labelling the sparse cells
+const SPARSE_CELLS_COUNT: usize = 100_000;
+
+/// Times each stage on the cells left by the previous ones, so that a slower stage shows up on its own
+fn bench_stages<const D: usize>(c: &mut Criterion, name: &str, points: &[Point<D>], params: &DBSCANParams) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10).warm_up_time(Duration::from_secs(1)).measurement_time(Duration::from_secs(3));
+    group.throughput(Throughput::Elements(points.len() as u64));
+
+    group.bench_function("find_cells", |b| b.iter(|| find_cells(points, params)));
+    let mut cells = find_cells(points, params);
+    group.bench_function("populate_neighbours", |b| b.iter_batched(
+        || cells.clone(),
+        |mut cells| { populate_neighbours(&mut cells); cells },
+        BatchSize::LargeInput
+    ));
+    populate_neighbours(&mut cells);
+    group.bench_function("label_points", |b| b.iter_batched(
+        || cells.clone(),
+        |mut cells| { label_points(&mut cells, points, params); cells },
+        BatchSize::LargeInput
+    ));
+    label_points(&mut cells, points, params);
+    group.bench_function("compute_adjacency_lists", |b| b.iter(|| compute_adjacency_lists(&cells, points, params)));
+    let sets = compute_adjacency_lists(&cells, points, params);
+    let clusters = find_connected_components(&mut cells, points, sets);
+    group.bench_function("assign_border_noise_points", |b| b.iter_batched(
+        || clusters.clone(),
+        |mut clusters| { assign_border_noise_points(&cells, points, params, &mut clusters); clusters },
+        BatchSize::LargeInput
+    ));
+    group.bench_function("total", |b| b.iter(|| approximate_dbscan_labels(points, params)));
+    group.finish();
+}
+
+fn bench_dataset<const D: usize>(c: &mut Criterion, file_name: &str, epsilon: f64, min_pts: usize) {
+    let points : Vec<Point<D>> = vector_input_to_array_input(read_vector_points_from_file(&file_name).unwrap());
+    let params = DBSCANParams{cardinality: points.len(), dimensionality: D as u32, epsilon, rho: 0.1, min_pts};
+    let name = file_name.trim_start_matches("./datasets/").trim_end_matches(".txt");
+    bench_stages(c, name, &points, &params);
+}
+
+fn datasets(c: &mut Criterion) {
+    bench_dataset::<2>(c, "./datasets/out20000.txt", 0.3, 30);
+    bench_dataset::<2>(c, "./datasets/out_test_1.txt", 0.3, 30);
+    bench_dataset::<3>(c, "./datasets/out_test_2.txt", 0.5, 30);
+    bench_dataset::<3>(c, "./datasets/out_3.txt", 1.25, 15);
+}
+
+/// The volume of the ball of radius `1` in `d` dimensions
+fn unit_ball_volume(d: usize) -> f64 {
+    match d {
+        0 => 1.0,
+        1 => 2.0,
+        _ => 2.0 * std::f64::consts::PI / d as f64 * unit_ball_volume(d - 2)
+    }
+}
+
+/// The radius that contains `neighbours` points on average around the center of one of the clusters of `blobs`,
+/// so that the work per point stays comparable as the number of points and the dimensionality grow
+fn blobs_epsilon(dimensionality: usize, count: usize, neighbours: f64) -> f64 {
+    let center_density = (count / BLOBS_COUNT) as f64 / (2.0 * std::f64::consts::PI).powf(dimensionality as f64 / 2.0);
+    (neighbours / (center_density * unit_ball_volume(dimensionality))).powf(1.0 / dimensionality as f64)
+}
+
+fn bench_blobs<const D: usize>(c: &mut Criterion, sizes: &[usize]) {
+    for &count in sizes {
+        let points : Vec<Point<D>> = vector_input_to_array_input(blobs(count, D, BLOBS_COUNT, 1.0, D as u64).points);
+        let params = DBSCANParams{cardinality: count, dimensionality: D as u32, epsilon: blobs_epsilon(D, count, CENTER_NEIGHBOURS), rho: 0.1, min_pts: SYNTHETIC_MIN_PTS};
+        bench_stages(c, &format!("blobs_{}d_{}", D, count), &points, &params);
+    }
+}
+
+fn synthetic(c: &mut Criterion) {
+    let mut sizes = vec![1_000, 10_000, 100_000];
+    if std::env::var_os("APPR_DBSCAN_BENCH_LARGE").is_some() {
+        sizes.extend([1_000_000, 10_000_000]);
+    }
+    bench_blobs::<2>(c, &sizes);
+    bench_blobs::<3>(c, &sizes);
+    bench_blobs::<4>(c, &sizes);
+    bench_blobs::<5>(c, &sizes);
+    bench_blobs::<6>(c, &sizes);
+    bench_blobs::<7>(c, &sizes);
+}
+
+/// Times the labelling of the cells with and without the kd-trees of the neighbour cells. A cell of side
+/// `epsilon / sqrt(2)` holds about a sixth of the points in range of its center, so with `min_pts` at half of them the
+/// central cells are sparse while their neighbours hold up to about `neighbours / 6` points each
+fn bench_sparse_cells<const D: usize>(c: &mut Criterion, neighbours: &[usize]) {
+    let points : Vec<Point<D>> = vector_input_to_array_input(blobs(SPARSE_CELLS_COUNT, D, BLOBS_COUNT, 1.0, D as u64).points);
+    for &neighbours in neighbours {
+        let params = DBSCANParams{cardinality: SPARSE_CELLS_COUNT, dimensionality: D as u32, epsilon: blobs_epsilon(D, SPARSE_CELLS_COUNT, neighbours as f64), rho: 0.1, min_pts: neighbours / 2};
+        let mut group = c.benchmark_group(format!("sparse_cells_{}d_{}", D, neighbours));
+        group.sample_size(10).warm_up_time(Duration::from_secs(1)).measurement_time(Duration::from_secs(3));
+        group.throughput(Throughput::Elements(points.len() as u64));
+        let mut cells = find_cells(&points[..], &params);
+        populate_neighbours(&mut cells);
+        group.bench_function("label_points", |b| b.iter_batched(
+            || cells.clone(),
+            |mut cells| { label_points(&mut cells, &points[..], &params); cells },
+            BatchSize::LargeInput
+        ));
+        group.bench_function("label_points_scanning_neighbours", |b| b.iter_batched(
+            || cells.clone(),
+            |mut cells| { label_points_scanning_neighbours(&mut cells, &points[..], &params); cells },
+            BatchSize::LargeInput
+        ));
+        group.finish();
+    }
+}
+
+fn sparse_cells(c: &mut Criterion) {
+    bench_sparse_cells::<2>(c, &[100, 1_000, 10_000]);
+    bench_sparse_cells::<3>(c, &[100, 1_000, 10_000]);
+}
+
+criterion_group!(benches, datasets, synthetic, sparse_cells);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+/* Clusters a few points and prints their labels.
+ * Build the library with `cargo build --release -p appr_dbscan_c`, then from the repository root:
+ * cc capi/examples/example.c -Icapi/include -Ltarget/release -lappr_dbscan_c -o example */
+#include <stdio.h>
+#include "appr_dbscan.h"
+
+int main(void) {
+    const double points[] = {0.0, 0.0, 0.0, 0.1, 0.1, 0.0, 0.1, 0.1, 5.0, 5.0};
+    int64_t labels[5];
+    size_t clusters_count = 0;
+    int32_t status = appr_dbscan_label_points(points, 5, 2, 0.5, 0.1, 3, labels, &clusters_count);
+    if (status != APPR_DBSCAN_OK) {
+        fprintf(stderr, "clustering failed: %s\n", appr_dbscan_status_message(status));
+        return 1;
+    }
+    printf("%zu clusters found\n", clusters_count);
+    for (size_t i = 0; i < 5; i++) {
+        printf("point %zu: %lld\n", i, (long long)labels[i]);
+    }
+    return 0;
+}
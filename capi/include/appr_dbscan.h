@@ -0,0 +1,73 @@
+#ifndef APPR_DBSCAN_H
+#define APPR_DBSCAN_H
+
+/* This file is generated by cbindgen from capi/src/lib.rs, do not edit it by hand. Run `APPR_DBSCAN_UPDATE_HEADER=1 cargo test -p appr_dbscan_c` to update it. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call completed successfully
+#define APPR_DBSCAN_OK 0
+
+// A required pointer argument is null
+#define APPR_DBSCAN_NULL_POINTER 1
+
+// The dimensionality of the points is not between 1 and `APPR_DBSCAN_MAX_DIMENSIONALITY`
+#define APPR_DBSCAN_INVALID_DIMENSIONALITY 2
+
+// `epsilon` or `rho` is not a positive number, or `min_pts` is 0
+#define APPR_DBSCAN_INVALID_PARAMETER 3
+
+// A coordinate of a point is infinite or NaN
+#define APPR_DBSCAN_NON_FINITE_COORDINATE 4
+
+// `count * dimensionality` does not fit in the address space
+#define APPR_DBSCAN_TOO_MANY_POINTS 5
+
+// An unexpected error happened inside the library
+#define APPR_DBSCAN_INTERNAL_ERROR 6
+
+// The label given to noise points
+#define APPR_DBSCAN_NOISE -1
+
+// The highest dimensionality supported for the points
+#define APPR_DBSCAN_MAX_DIMENSIONALITY 7
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Clusters `count` points of `dimensionality` coordinates each with the approximate DBSCAN algorithm.
+//
+// `points` holds the coordinates in row-major order, so the point at index `i` starts at `points[i * dimensionality]`.
+// The label of the point at index `i` is written in `labels[i]`: `APPR_DBSCAN_NOISE` for noise points and the index of
+// the cluster, starting from 0, for the others. If `clusters_count` is not null the number of clusters found is written in it.
+// The buffers are not read or written if `count` is 0, and `labels` is left untouched if an error is returned.
+//
+// # Safety
+//
+// `points` must be valid for reading `count * dimensionality` doubles and `labels` must be valid for writing `count`
+// values. `clusters_count` must be either null or valid for writing.
+int32_t appr_dbscan_label_points(const double *points,
+                                 size_t count,
+                                 size_t dimensionality,
+                                 double epsilon,
+                                 double rho,
+                                 size_t min_pts,
+                                 int64_t *labels,
+                                 size_t *clusters_count);
+
+// Returns a static, null terminated description of `status`
+const char *appr_dbscan_status_message(int32_t status);
+
+// Returns the version of the library as a static, null terminated string
+const char *appr_dbscan_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* APPR_DBSCAN_H */
@@ -0,0 +1,129 @@
+//! C API for the approximate DBSCAN algorithm. The header `include/appr_dbscan.h` is generated from this file.
+//!
+//! All functions report errors through their return value, which is one of the `APPR_DBSCAN_*` status codes,
+//! and never unwind into the caller.
+
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+use appr_dbscan::do_appr_dbscan_labels;
+use appr_dbscan::utils::{DBSCANLabels, StridedPoints};
+
+/// The call completed successfully
+pub const APPR_DBSCAN_OK: i32 = 0;
+/// A required pointer argument is null
+pub const APPR_DBSCAN_NULL_POINTER: i32 = 1;
+/// The dimensionality of the points is not between 1 and `APPR_DBSCAN_MAX_DIMENSIONALITY`
+pub const APPR_DBSCAN_INVALID_DIMENSIONALITY: i32 = 2;
+/// `epsilon` or `rho` is not a positive number, or `min_pts` is 0
+pub const APPR_DBSCAN_INVALID_PARAMETER: i32 = 3;
+/// A coordinate of a point is infinite or NaN
+pub const APPR_DBSCAN_NON_FINITE_COORDINATE: i32 = 4;
+/// `count * dimensionality` does not fit in the address space
+pub const APPR_DBSCAN_TOO_MANY_POINTS: i32 = 5;
+/// An unexpected error happened inside the library
+pub const APPR_DBSCAN_INTERNAL_ERROR: i32 = 6;
+
+/// The label given to noise points
+pub const APPR_DBSCAN_NOISE: i64 = -1;
+/// The highest dimensionality supported for the points
+pub const APPR_DBSCAN_MAX_DIMENSIONALITY: usize = 7;
+
+/// Clusters `count` points of `dimensionality` coordinates each with the approximate DBSCAN algorithm.
+///
+/// `points` holds the coordinates in row-major order, so the point at index `i` starts at `points[i * dimensionality]`.
+/// The label of the point at index `i` is written in `labels[i]`: `APPR_DBSCAN_NOISE` for noise points and the index of
+/// the cluster, starting from 0, for the others. If `clusters_count` is not null the number of clusters found is written in it.
+/// The buffers are not read or written if `count` is 0, and `labels` is left untouched if an error is returned.
+///
+/// # Safety
+///
+/// `points` must be valid for reading `count * dimensionality` doubles and `labels` must be valid for writing `count`
+/// values. `clusters_count` must be either null or valid for writing.
+#[no_mangle]
+pub unsafe extern "C" fn appr_dbscan_label_points(
+    points: *const f64,
+    count: usize,
+    dimensionality: usize,
+    epsilon: f64,
+    rho: f64,
+    min_pts: usize,
+    labels: *mut i64,
+    clusters_count: *mut usize
+) -> i32 {
+    if dimensionality == 0 || dimensionality > APPR_DBSCAN_MAX_DIMENSIONALITY {
+        return APPR_DBSCAN_INVALID_DIMENSIONALITY;
+    }
+    if epsilon.is_nan() || epsilon <= 0.0 || rho.is_nan() || rho <= 0.0 || min_pts == 0 {
+        return APPR_DBSCAN_INVALID_PARAMETER;
+    }
+    if count == 0 {
+        if !clusters_count.is_null() {
+            *clusters_count = 0;
+        }
+        return APPR_DBSCAN_OK;
+    }
+    if points.is_null() || labels.is_null() {
+        return APPR_DBSCAN_NULL_POINTER;
+    }
+    let len = match count.checked_mul(dimensionality) {
+        Some(len) if len <= isize::MAX as usize / std::mem::size_of::<f64>() => len,
+        _ => return APPR_DBSCAN_TOO_MANY_POINTS
+    };
+    let data = slice::from_raw_parts(points, len);
+    if data.iter().any(|c| !c.is_finite()) {
+        return APPR_DBSCAN_NON_FINITE_COORDINATE;
+    }
+    let result = catch_unwind(AssertUnwindSafe(|| label_points(data, dimensionality, epsilon, rho, min_pts)));
+    let point_labels = match result {
+        Ok(point_labels) => point_labels,
+        Err(_) => return APPR_DBSCAN_INTERNAL_ERROR
+    };
+    let labels = slice::from_raw_parts_mut(labels, count);
+    let mut found_clusters = 0;
+    for (label, point_label) in labels.iter_mut().zip(point_labels.iter()) {
+        // cluster 0 holds the noise points
+        *label = point_label.cluster_id as i64 - 1;
+        found_clusters = found_clusters.max(point_label.cluster_id);
+    }
+    if !clusters_count.is_null() {
+        *clusters_count = found_clusters;
+    }
+    APPR_DBSCAN_OK
+}
+
+/// Returns a static, null terminated description of `status`
+#[no_mangle]
+pub extern "C" fn appr_dbscan_status_message(status: i32) -> *const c_char {
+    let message: &'static [u8] = match status {
+        APPR_DBSCAN_OK => b"success\0",
+        APPR_DBSCAN_NULL_POINTER => b"a required pointer is null\0",
+        APPR_DBSCAN_INVALID_DIMENSIONALITY => b"the dimensionality must be between 1 and 7\0",
+        APPR_DBSCAN_INVALID_PARAMETER => b"epsilon and rho must be positive and min_pts must be at least 1\0",
+        APPR_DBSCAN_NON_FINITE_COORDINATE => b"a coordinate is infinite or NaN\0",
+        APPR_DBSCAN_TOO_MANY_POINTS => b"too many points\0",
+        APPR_DBSCAN_INTERNAL_ERROR => b"internal error\0",
+        _ => b"unknown status\0"
+    };
+    message.as_ptr() as *const c_char
+}
+
+/// Returns the version of the library as a static, null terminated string
+#[no_mangle]
+pub extern "C" fn appr_dbscan_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Runs the algorithm on the row-major coordinates in `data` without copying them
+fn label_points(data: &[f64], dimensionality: usize, epsilon: f64, rho: f64, min_pts: usize) -> DBSCANLabels {
+    match dimensionality {
+        1 => do_appr_dbscan_labels(&StridedPoints::<f64, 1>::new(data, 1), epsilon, rho, min_pts),
+        2 => do_appr_dbscan_labels(&StridedPoints::<f64, 2>::new(data, 2), epsilon, rho, min_pts),
+        3 => do_appr_dbscan_labels(&StridedPoints::<f64, 3>::new(data, 3), epsilon, rho, min_pts),
+        4 => do_appr_dbscan_labels(&StridedPoints::<f64, 4>::new(data, 4), epsilon, rho, min_pts),
+        5 => do_appr_dbscan_labels(&StridedPoints::<f64, 5>::new(data, 5), epsilon, rho, min_pts),
+        6 => do_appr_dbscan_labels(&StridedPoints::<f64, 6>::new(data, 6), epsilon, rho, min_pts),
+        7 => do_appr_dbscan_labels(&StridedPoints::<f64, 7>::new(data, 7), epsilon, rho, min_pts),
+        _ => unreachable!()
+    }
+}
@@ -0,0 +1,151 @@
+use std::ffi::CStr;
+use std::path::Path;
+use std::ptr;
+use appr_dbscan_c::*;
+use appr_dbscan::evaluation::adjusted_rand_index;
+use appr_dbscan::generate::blobs;
+
+/// The row-major coordinates of 21 points in two dimensions: the even points of the first 20 are close to `(0, 0)`,
+/// the odd ones close to `(10, 10)`, and the last one, `(-20, 0)`, is far from both groups
+fn two_groups_buffer() -> Vec<f64> {
+    let mut flat = Vec::new();
+    for i in 0..10 {
+        let offset = i as f64 * 0.01;
+        flat.extend([offset, offset, 10.0 + offset, 10.0]);
+    }
+    flat.extend([-20.0, 0.0]);
+    flat
+}
+
+#[test]
+fn label_points_test() {
+    let flat = two_groups_buffer();
+    let mut labels = vec![100_i64; 21];
+    let mut clusters_count = 0;
+    let status = unsafe {
+        appr_dbscan_label_points(flat.as_ptr(), 21, 2, 0.5, 0.1, 5, labels.as_mut_ptr(), &mut clusters_count)
+    };
+    assert_eq!(status, APPR_DBSCAN_OK);
+    assert_eq!(clusters_count, 2);
+    assert_eq!(labels[20], APPR_DBSCAN_NOISE);
+    assert_ne!(labels[0], labels[1]);
+    for i in 0..10 {
+        assert_eq!(labels[2 * i], labels[0]);
+        assert_eq!(labels[2 * i + 1], labels[1]);
+    }
+    // the clusters are numbered from `0`
+    let mut clusters = labels[..20].to_vec();
+    clusters.sort_unstable();
+    clusters.dedup();
+    assert_eq!(clusters, vec![0, 1]);
+}
+
+#[test]
+fn generated_blobs_test() {
+    let dataset = blobs(300, 3, 3, 0.2, 1);
+    let flat : Vec<f64> = dataset.points.iter().flatten().copied().collect();
+    let count = dataset.points.len();
+    let mut labels = vec![100_i64; count];
+    let mut clusters_count = 0;
+    let status = unsafe {
+        appr_dbscan_label_points(flat.as_ptr(), count, 3, 0.3, 0.1, 5, labels.as_mut_ptr(), &mut clusters_count)
+    };
+    assert_eq!(status, APPR_DBSCAN_OK);
+    assert_eq!(clusters_count, 3);
+    let ari = adjusted_rand_index(&labels, &dataset.labels);
+    assert!(ari > 0.95, "{}", ari);
+}
+
+#[test]
+fn row_major_layout_test() {
+    let flat = two_groups_buffer();
+    let mut labels = vec![100_i64; 21];
+    let status = unsafe {
+        appr_dbscan_label_points(flat.as_ptr(), 21, 2, 0.5, 0.1, 5, labels.as_mut_ptr(), ptr::null_mut())
+    };
+    assert_eq!(status, APPR_DBSCAN_OK);
+    // the same buffer read as 42 points of dimensionality 1: the coordinates of the points close to `(0, 0)`, and the
+    // `0` of the last point, make one cluster, the ones of the points close to `(10, 10)` another, and `-20` is noise
+    let mut flat_labels = vec![100_i64; 42];
+    let mut clusters_count = 0;
+    let status = unsafe {
+        appr_dbscan_label_points(flat.as_ptr(), 42, 1, 0.5, 0.1, 5, flat_labels.as_mut_ptr(), &mut clusters_count)
+    };
+    assert_eq!(status, APPR_DBSCAN_OK);
+    assert_eq!(clusters_count, 2);
+    assert_ne!(flat_labels[0], flat_labels[2]);
+    for i in 0..10 {
+        assert_eq!(flat_labels[4 * i..4 * i + 4], [flat_labels[0], flat_labels[0], flat_labels[2], flat_labels[2]]);
+    }
+    assert_eq!(flat_labels[40], APPR_DBSCAN_NOISE);
+    assert_eq!(flat_labels[41], flat_labels[0]);
+    // only the first `count` labels are written
+    let mut short_labels = vec![100_i64; 22];
+    let status = unsafe {
+        appr_dbscan_label_points(flat.as_ptr(), 21, 2, 0.5, 0.1, 5, short_labels.as_mut_ptr(), ptr::null_mut())
+    };
+    assert_eq!(status, APPR_DBSCAN_OK);
+    assert_eq!(short_labels[..21], labels[..]);
+    assert_eq!(short_labels[21], 100);
+}
+
+#[test]
+fn errors_test() {
+    let flat = two_groups_buffer();
+    let count = 21;
+    let mut labels = vec![100_i64; count];
+    let mut clusters_count = 100;
+    let clusters_count_ptr : *mut usize = &mut clusters_count;
+    let call = |points: *const f64, count, dimensionality, epsilon, rho, min_pts, labels: *mut i64| unsafe {
+        appr_dbscan_label_points(points, count, dimensionality, epsilon, rho, min_pts, labels, clusters_count_ptr)
+    };
+    assert_eq!(call(flat.as_ptr(), count, 0, 0.5, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_DIMENSIONALITY);
+    assert_eq!(call(flat.as_ptr(), 2, APPR_DBSCAN_MAX_DIMENSIONALITY + 1, 0.5, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_DIMENSIONALITY);
+    assert_eq!(call(flat.as_ptr(), count, 2, -1.0, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_PARAMETER);
+    assert_eq!(call(flat.as_ptr(), count, 2, f64::NAN, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_PARAMETER);
+    assert_eq!(call(flat.as_ptr(), count, 2, 0.5, 0.0, 5, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_PARAMETER);
+    assert_eq!(call(flat.as_ptr(), count, 2, 0.5, 0.1, 0, labels.as_mut_ptr()), APPR_DBSCAN_INVALID_PARAMETER);
+    assert_eq!(call(ptr::null(), count, 2, 0.5, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_NULL_POINTER);
+    assert_eq!(call(flat.as_ptr(), count, 2, 0.5, 0.1, 5, ptr::null_mut()), APPR_DBSCAN_NULL_POINTER);
+    assert_eq!(call(flat.as_ptr(), usize::MAX, 2, 0.5, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_TOO_MANY_POINTS);
+    // a single non finite coordinate anywhere in the buffer is rejected
+    let mut bad = flat.clone();
+    *bad.last_mut().unwrap() = f64::INFINITY;
+    assert_eq!(call(bad.as_ptr(), count, 2, 0.5, 0.1, 5, labels.as_mut_ptr()), APPR_DBSCAN_NON_FINITE_COORDINATE);
+    // neither the labels nor the number of clusters are written when an error is returned
+    assert!(labels.iter().all(|l| *l == 100));
+    assert_eq!(clusters_count, 100);
+    assert_eq!(call(ptr::null(), 0, 2, 0.5, 0.1, 5, ptr::null_mut()), APPR_DBSCAN_OK);
+    assert_eq!(clusters_count, 0);
+}
+
+#[test]
+fn messages_test() {
+    for status in APPR_DBSCAN_OK..=APPR_DBSCAN_INTERNAL_ERROR {
+        let message = unsafe { CStr::from_ptr(appr_dbscan_status_message(status)) };
+        assert_ne!(message.to_str().unwrap(), "unknown status");
+    }
+    let message = unsafe { CStr::from_ptr(appr_dbscan_status_message(-3)) };
+    assert_eq!(message.to_str().unwrap(), "unknown status");
+    let version = unsafe { CStr::from_ptr(appr_dbscan_version()) };
+    assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn header_test() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(Path::new(crate_dir).join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(Path::new(crate_dir).join("src/lib.rs"))
+        .generate()
+        .unwrap()
+        .write(&mut generated);
+    let header_path = Path::new(crate_dir).join("include/appr_dbscan.h");
+    if std::env::var_os("APPR_DBSCAN_UPDATE_HEADER").is_some() {
+        std::fs::write(&header_path, &generated).unwrap();
+    }
+    let header = std::fs::read(&header_path).unwrap();
+    assert!(header == generated, "include/appr_dbscan.h is out of date, run `APPR_DBSCAN_UPDATE_HEADER=1 cargo test -p appr_dbscan_c`");
+}
@@ -0,0 +1,168 @@
+//! Python bindings for the approximate DBSCAN algorithm, with an interface similar to the one of `sklearn.cluster.DBSCAN`.
+//!
+//! The points can be given as any sequence of sequences of numbers, so NumPy arrays, lists of lists and lists of tuples
+//! are all accepted. Two dimensional arrays of `float64` or `float32` that expose the buffer protocol, such as NumPy arrays
+//! and `memoryview`s, are read directly from their memory with any strides, transposed and sliced views included. Every
+//! other input is read point by point through the sequence protocol, which is much slower on large arrays. In both cases
+//! the coordinates are copied, as `float64`, into memory owned by the bindings before the clustering starts, so the input
+//! can be changed or released while the clustering runs. The labels follow the scikit-learn convention: noise points are labeled with `-1` and the clusters
+//! are numbered from `0`.
+
+use pyo3::prelude::*;
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::{PyAttributeError, PyValueError};
+use appr_dbscan::do_appr_dbscan_auto_dimensionality_labels_points;
+use appr_dbscan::utils::{DBSCANLabels, PointRole, VectorPoint};
+
+/// The label given to noise points, as in scikit-learn
+pub const NOISE_LABEL: i64 = -1;
+
+/// The highest dimensionality supported by the approximate DBSCAN algorithm
+const MAX_DIMENSIONALITY: usize = 7;
+
+/// Approximate DBSCAN clustering of points with up to 7 coordinates.
+///
+/// Parameters
+/// ----------
+/// eps : float
+///     The radius of the neighbourhood of each point.
+/// rho : float
+///     The approximation factor. Points at distance between `eps` and `eps * (1 + rho)` may or may not be
+///     considered neighbours. The smaller it is the more precise and slow the clustering.
+/// min_samples : int
+///     The number of points, the point itself included, that must be in the neighbourhood of a core point.
+#[pyclass(name = "ApproxDBSCAN", module = "appr_dbscan")]
+pub struct ApproxDBSCAN {
+    #[pyo3(get, set)]
+    pub eps: f64,
+    #[pyo3(get, set)]
+    pub rho: f64,
+    #[pyo3(get, set)]
+    pub min_samples: usize,
+    /// The labels found by the last call to `fit`
+    labels: Option<DBSCANLabels>
+}
+
+#[pymethods]
+impl ApproxDBSCAN {
+    #[new]
+    #[pyo3(signature = (eps = 0.5, rho = 0.1, min_samples = 5))]
+    pub fn new(eps: f64, rho: f64, min_samples: usize) -> PyResult<ApproxDBSCAN> {
+        let model = ApproxDBSCAN{eps, rho, min_samples, labels: None};
+        model.check_params()?;
+        Ok(model)
+    }
+
+    /// Clusters the points in `x`, one point for each row. `y` is ignored and accepted only for compatibility
+    /// with scikit-learn pipelines. Returns the fitted model.
+    #[pyo3(signature = (x, y = None))]
+    pub fn fit<'py>(mut slf: PyRefMut<'py, Self>, py: Python<'py>, x: &Bound<'py, PyAny>, y: Option<&Bound<'py, PyAny>>) -> PyResult<PyRefMut<'py, Self>> {
+        let _ = y;
+        let points = extract_points(x)?;
+        slf.check_params()?;
+        let (eps, rho, min_samples) = (slf.eps, slf.rho, slf.min_samples);
+        let labels = py.allow_threads(|| {
+            do_appr_dbscan_auto_dimensionality_labels_points(points, eps, rho, min_samples).0
+        });
+        slf.labels = Some(labels);
+        Ok(slf)
+    }
+
+    /// Clusters the points in `x` and returns the label of each point
+    #[pyo3(signature = (x, y = None))]
+    pub fn fit_predict<'py>(slf: PyRefMut<'py, Self>, py: Python<'py>, x: &Bound<'py, PyAny>, y: Option<&Bound<'py, PyAny>>) -> PyResult<Vec<i64>> {
+        let slf = ApproxDBSCAN::fit(slf, py, x, y)?;
+        slf.labels_()
+    }
+
+    /// The label of each point given to the last call to `fit`: `-1` for noise points and the index of
+    /// the cluster, starting from `0`, for the others
+    #[getter]
+    pub fn labels_(&self) -> PyResult<Vec<i64>> {
+        let labels = self.fitted_labels()?;
+        Ok(labels.iter().map(|l| l.cluster_id as i64 - 1).collect())
+    }
+
+    /// The indexes of the core points found by the last call to `fit`
+    #[getter]
+    pub fn core_sample_indices_(&self) -> PyResult<Vec<usize>> {
+        let labels = self.fitted_labels()?;
+        Ok(labels.iter().enumerate().filter(|(_, l)| l.role == PointRole::Core).map(|(i, _)| i).collect())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ApproxDBSCAN(eps={}, rho={}, min_samples={})", self.eps, self.rho, self.min_samples)
+    }
+}
+
+impl ApproxDBSCAN {
+    fn check_params(&self) -> PyResult<()> {
+        if self.eps.is_nan() || self.eps <= 0.0 {
+            return Err(PyValueError::new_err(format!("eps must be positive, got {}", self.eps)));
+        }
+        if self.rho.is_nan() || self.rho <= 0.0 {
+            return Err(PyValueError::new_err(format!("rho must be positive, got {}", self.rho)));
+        }
+        if self.min_samples == 0 {
+            return Err(PyValueError::new_err("min_samples must be at least 1"));
+        }
+        Ok(())
+    }
+
+    fn fitted_labels(&self) -> PyResult<&DBSCANLabels> {
+        self.labels.as_ref().ok_or_else(|| PyAttributeError::new_err("this ApproxDBSCAN instance is not fitted yet, call 'fit' first"))
+    }
+}
+
+/// Reads the points from a two dimensional buffer of floats or from a sequence of sequences of numbers, checking that
+/// they can be clustered. The coordinates are always copied.
+fn extract_points(x: &Bound<'_, PyAny>) -> PyResult<Vec<VectorPoint>> {
+    let points = match buffer_points::<f64>(x) {
+        Some(points) => points?,
+        None => match buffer_points::<f32>(x) {
+            Some(points) => points?,
+            None => x.extract()
+                .map_err(|e| PyValueError::new_err(format!("expected a two dimensional array of numbers: {}", e)))?
+        }
+    };
+    let dimensionality = points.first().map_or(0, |p| p.len());
+    if !points.is_empty() && !(1..=MAX_DIMENSIONALITY).contains(&dimensionality) {
+        return Err(PyValueError::new_err(format!("points must have between 1 and {} coordinates, got {}", MAX_DIMENSIONALITY, dimensionality)));
+    }
+    for (i, point) in points.iter().enumerate() {
+        if point.len() != dimensionality {
+            return Err(PyValueError::new_err(format!("expected all points to have {} coordinates, but point {} has {}", dimensionality, i, point.len())));
+        }
+        if point.iter().any(|c| !c.is_finite()) {
+            return Err(PyValueError::new_err(format!("point {} has a coordinate that is not finite", i)));
+        }
+    }
+    Ok(points)
+}
+
+/// Copies the points from `x` if it exposes a buffer of elements of type `T`, or returns `None` to let the caller try
+/// another way of reading them. `PyBuffer::to_vec` follows the strides, so the rows and the columns can be anywhere in memory.
+fn buffer_points<T: Element + Into<f64>>(x: &Bound<'_, PyAny>) -> Option<PyResult<Vec<VectorPoint>>> {
+    let buffer = PyBuffer::<T>::get(x).ok()?;
+    if buffer.dimensions() != 2 {
+        return Some(Err(PyValueError::new_err(format!("expected a two dimensional array of numbers, got {} dimensions", buffer.dimensions()))));
+    }
+    let columns = buffer.shape()[1];
+    let coordinates = match buffer.to_vec(x.py()) {
+        Ok(coordinates) => coordinates,
+        Err(e) => return Some(Err(e))
+    };
+    if columns == 0 {
+        return Some(Ok(vec![Vec::new(); buffer.shape()[0]]));
+    }
+    Some(Ok(coordinates.chunks_exact(columns).map(|row| row.iter().map(|c| (*c).into()).collect()).collect()))
+}
+
+/// Approximate DBSCAN clustering
+#[pymodule]
+#[pyo3(name = "appr_dbscan")]
+pub fn appr_dbscan_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ApproxDBSCAN>()?;
+    m.add("NOISE_LABEL", NOISE_LABEL)?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::ffi::CString;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use appr_dbscan_py::appr_dbscan_module;
+
+/// Runs `code` with the bindings module imported as `appr_dbscan`
+fn run_python(code: &str) {
+    Python::with_gil(|py| {
+        let module = pyo3::wrap_pymodule!(appr_dbscan_module)(py);
+        let globals = PyDict::new(py);
+        globals.set_item("appr_dbscan", module).unwrap();
+        let code = CString::new(code).unwrap();
+        if let Err(e) = py.run(&code, Some(&globals), None) {
+            e.print(py);
+            panic!("python code failed");
+        }
+    });
+}
+
+#[test]
+fn fit_predict_test() {
+    run_python(r#"
+points = [[0.01 * i, 0.01 * i] for i in range(10)] + [(10.0 + 0.01 * i, 10.0) for i in range(10)] + [[-20.0, 0.0]]
+model = appr_dbscan.ApproxDBSCAN(eps=0.5, rho=0.1, min_samples=5)
+labels = model.fit_predict(points)
+assert len(labels) == 21
+assert labels[20] == appr_dbscan.NOISE_LABEL == -1
+assert sorted(set(labels)) == [-1, 0, 1]
+assert len(set(labels[:10])) == 1 and len(set(labels[10:20])) == 1
+assert model.labels_ == labels
+assert len(model.core_sample_indices_) == 20
+assert model.fit(points) is model
+assert repr(model) == "ApproxDBSCAN(eps=0.5, rho=0.1, min_samples=5)"
+"#);
+}
+
+#[test]
+fn defaults_and_params_test() {
+    run_python(r#"
+model = appr_dbscan.ApproxDBSCAN()
+assert (model.eps, model.rho, model.min_samples) == (0.5, 0.1, 5)
+model.eps = 2.0
+assert model.fit_predict([[0.0], [1.0], [2.0], [3.0], [4.0], [100.0]]) == [0, 0, 0, 0, 0, -1]
+assert model.fit_predict([]) == []
+"#);
+}
+
+#[test]
+fn errors_test() {
+    run_python(r#"
+def raises(error, f):
+    try:
+        f()
+    except error:
+        return True
+    return False
+
+model = appr_dbscan.ApproxDBSCAN()
+assert raises(AttributeError, lambda: model.labels_)
+assert raises(ValueError, lambda: appr_dbscan.ApproxDBSCAN(eps=0.0))
+assert raises(ValueError, lambda: appr_dbscan.ApproxDBSCAN(rho=-1.0))
+assert raises(ValueError, lambda: appr_dbscan.ApproxDBSCAN(min_samples=0))
+assert raises(ValueError, lambda: model.fit([[0.0, 1.0], [1.0]]))
+assert raises(ValueError, lambda: model.fit([[0.0] * 8]))
+assert raises(ValueError, lambda: model.fit([[float("nan"), 0.0]]))
+assert raises(ValueError, lambda: model.fit("points"))
+"#);
+}
+
+#[test]
+fn buffer_input_test() {
+    run_python(r#"
+import array
+points = [[0.01 * i, 0.01 * i, 0.0] for i in range(10)] + [[10.0 + 0.01 * i, 10.0, 0.5] for i in range(10)] + [[-20.0, 0.0, 0.0]]
+model = appr_dbscan.ApproxDBSCAN(eps=0.5, rho=0.1, min_samples=5)
+expected = model.fit_predict(points)
+
+def view(type_code, rows):
+    flat = array.array(type_code, [c for point in rows for c in point])
+    return memoryview(flat).cast("B").cast(type_code, [len(rows), 3])
+
+assert model.fit_predict(view("d", points)) == expected
+assert model.fit_predict(view("f", points)) == expected
+# every other row of a buffer with twice the points, so the rows are not contiguous
+doubled = [p for point in points for p in (point, [1000.0, 1000.0, 1000.0])]
+strided = view("d", doubled)[::2]
+assert not strided.c_contiguous
+assert model.fit_predict(strided) == expected
+assert model.fit_predict(view("d", points)[:0]) == []
+
+def raises(error, f):
+    try:
+        f()
+    except error:
+        return True
+    return False
+
+assert raises(ValueError, lambda: model.fit(memoryview(array.array("d", [0.0, 1.0]))))
+assert raises(ValueError, lambda: model.fit(view("d", [[0.0, float("inf"), 0.0]])))
+assert raises(ValueError, lambda: model.fit(memoryview(array.array("d", [0.0] * 8)).cast("B").cast("d", [1, 8])))
+"#);
+}
+
+#[test]
+fn numpy_input_test() {
+    // NumPy is not a dependency of the bindings, so the test checks nothing when it is not installed
+    run_python(r#"
+try:
+    import numpy as np
+except ImportError:
+    np = None
+
+if np is not None:
+    points = np.array([[0.01 * i, 0.01 * i, 0.0] for i in range(10)] + [[10.0 + 0.01 * i, 10.0, 0.5] for i in range(10)] + [[-20.0, 0.0, 0.0]])
+    model = appr_dbscan.ApproxDBSCAN(eps=0.5, rho=0.1, min_samples=5)
+    expected = model.fit_predict(points.tolist())
+    assert model.fit_predict(points) == expected
+    assert model.fit_predict(points.astype(np.float32)) == expected
+    assert model.fit_predict(np.asfortranarray(points)) == expected
+    assert model.fit_predict(np.ascontiguousarray(points.T).T) == expected
+    assert model.fit_predict(np.repeat(points, 2, axis=0)[::2]) == expected
+    assert model.fit_predict(np.hstack([np.zeros((21, 1)), points])[:, 1:]) == expected
+    assert model.fit_predict(points.astype(np.int64) * 0) == [0] * 21
+"#);
+}
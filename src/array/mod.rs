@@ -0,0 +1,106 @@
+//! Clustering of points stored in `ndarray` arrays, with one point per row. The labels follow the convention used by 
+//! linfa: noise points are labeled with `None` and the clusters are numbered from `0`. With the `linfa` feature 
+//! `ApproxDbscan` can be used as a `linfa::traits::Transformer` inside linfa pipelines.
+
+use ndarray::{Array1, ArrayBase, Data, Ix2};
+use crate::do_appr_dbscan_labels;
+use crate::cluster::NOISE_CLUSTER_INDEX;
+use crate::utils::{DBSCANLabels, Float};
+
+/// The parameters of the approximate DBSCAN algorithm, used to cluster arrays of points
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ApproxDbscan {
+    /// The radius for the DBSCAN algorithm
+    pub epsilon: f64,
+    /// The approximation factor. The smaller it is the more precise the result
+    pub rho: f64,
+    /// The minimum number of nearby points required to declare an area as 'dense'
+    pub min_pts: usize
+}
+
+impl ApproxDbscan {
+    pub fn new(epsilon: f64, rho: f64, min_pts: usize) -> ApproxDbscan {
+        ApproxDbscan{epsilon, rho, min_pts}
+    }
+
+    /// Clusters the rows of `points`. See `do_appr_dbscan_array`
+    pub fn predict<F: Float, S: Data<Elem = F>>(&self, points: &ArrayBase<S, Ix2>) -> Array1<Option<usize>> {
+        do_appr_dbscan_array(points, self.epsilon, self.rho, self.min_pts)
+    }
+}
+
+/// Function that labels each row of `points` with the cluster found for it by the approximate DBSCAN algorithm. 
+/// The points are not copied. 
+/// 
+/// # Arguments
+/// 
+/// * `points`: the array of points, with one point per row. Arrays with up to 7 columns are supported.
+/// * `epsilon`: the radius for the DBSCAN algorithm. 
+/// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
+/// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
+/// 
+/// # Return value
+/// 
+/// An array with the label of each row of `points`: `None` for noise points and `Some(i)` for the points in the `i`-th 
+/// cluster, where clusters are numbered from `0`.
+/// 
+/// # Example
+/// ``` rust
+/// extern crate appr_dbscan;
+/// use appr_dbscan::array::do_appr_dbscan_array;
+/// use ndarray::arr2;
+/// 
+/// let points = arr2(&[[0.0,0.0],[0.0,0.1],[0.1,0.0],[5.0,5.0]]);
+/// let labels = do_appr_dbscan_array(&points, 0.3, 0.1, 3);
+/// assert_eq!(labels[0], Some(0));
+/// assert_eq!(labels[3], None);
+/// ```
+/// 
+pub fn do_appr_dbscan_array<F: Float, S: Data<Elem = F>>(points: &ArrayBase<S, Ix2>, epsilon: f64, rho: f64, min_pts: usize) -> Array1<Option<usize>> {
+    if points.nrows() == 0 {
+        return Array1::from(Vec::new());
+    }
+    let view = points.view();
+    let labels = match points.ncols() {
+        0 => {panic!("There has been an error while reading the data: 0 dimensionality point found");},
+        1 => do_appr_dbscan_labels::<F, 1, _>(&view, epsilon, rho, min_pts),
+        2 => do_appr_dbscan_labels::<F, 2, _>(&view, epsilon, rho, min_pts),
+        3 => do_appr_dbscan_labels::<F, 3, _>(&view, epsilon, rho, min_pts),
+        4 => do_appr_dbscan_labels::<F, 4, _>(&view, epsilon, rho, min_pts),
+        5 => do_appr_dbscan_labels::<F, 5, _>(&view, epsilon, rho, min_pts),
+        6 => do_appr_dbscan_labels::<F, 6, _>(&view, epsilon, rho, min_pts),
+        7 => do_appr_dbscan_labels::<F, 7, _>(&view, epsilon, rho, min_pts),
+        _ => {panic!("Dimensionalities over 7 are not supported")}
+    };
+    labels_to_array(&labels)
+}
+
+/// Translates the labels found by the approximate DBSCAN algorithm into an array of optional cluster indexes,
+/// with `None` for noise points and clusters numbered from `0`
+pub fn labels_to_array(labels: &DBSCANLabels) -> Array1<Option<usize>> {
+    labels.iter().map(|l| {
+        if l.cluster_id == NOISE_CLUSTER_INDEX {
+            None
+        } else {
+            Some(l.cluster_id - 1)
+        }
+    }).collect()
+}
+
+#[cfg(feature = "linfa")]
+impl <F: Float, S: Data<Elem = F>> linfa::traits::Transformer<&ArrayBase<S, Ix2>, Array1<Option<usize>>> for ApproxDbscan {
+    fn transform(&self, points: &ArrayBase<S, Ix2>) -> Array1<Option<usize>> {
+        self.predict(points)
+    }
+}
+
+#[cfg(feature = "linfa")]
+impl <F: Float, S: Data<Elem = F>, T> linfa::traits::Transformer<linfa::DatasetBase<ArrayBase<S, Ix2>, T>, linfa::DatasetBase<ArrayBase<S, Ix2>, Array1<Option<usize>>>> for ApproxDbscan {
+    fn transform(&self, dataset: linfa::DatasetBase<ArrayBase<S, Ix2>, T>) -> linfa::DatasetBase<ArrayBase<S, Ix2>, Array1<Option<usize>>> {
+        let labels = self.predict(&dataset.records);
+        dataset.with_targets(labels)
+    }
+}
+
+#[cfg(test)]
+mod tests;
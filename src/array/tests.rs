@@ -0,0 +1,87 @@
+use super::*;
+use crate::evaluation::adjusted_rand_index;
+use crate::generate::blobs;
+use ndarray::{arr2, s, Array2};
+
+/// Two groups of ten points each, far apart from each other, and an isolated point as the last row
+fn two_groups() -> Array2<f64> {
+    let mut points = Vec::new();
+    for i in 0..10 {
+        let offset = i as f64 * 0.01;
+        points.push([offset, offset, 0.0]);
+        points.push([10.0 + offset, 10.0, offset]);
+    }
+    points.push([-20.0, 0.0, 0.0]);
+    Array2::from(points)
+}
+
+/// The points of `blobs` as the rows of an array, together with the cluster each of them was drawn from
+fn generated_blobs() -> (Array2<f64>, Vec<i64>) {
+    let dataset = blobs(600, 3, 3, 0.2, 1);
+    let rows = dataset.points.len();
+    (Array2::from_shape_vec((rows, 3), dataset.points.into_iter().flatten().collect()).unwrap(), dataset.labels)
+}
+
+#[test]
+fn array_labels_test() {
+    let points = two_groups();
+    let labels = do_appr_dbscan_array(&points, 0.5, 0.1, 5);
+    assert_eq!(labels.len(), 21);
+    assert_eq!(labels[20], None);
+    assert!(labels[0].is_some());
+    assert!(labels[1].is_some());
+    assert_ne!(labels[0], labels[1]);
+    for i in 0..10 {
+        assert_eq!(labels[2 * i], labels[0]);
+        assert_eq!(labels[2 * i + 1], labels[1]);
+    }
+    let mut clusters : Vec<usize> = labels.iter().flatten().copied().collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    assert_eq!(clusters, vec![0, 1]);
+}
+
+#[test]
+fn array_blobs_test() {
+    let (points, expected) = generated_blobs();
+    let labels = do_appr_dbscan_array(&points, 0.3, 0.1, 5);
+    let ari = adjusted_rand_index(labels.as_slice().unwrap(), &expected);
+    assert!(ari > 0.95, "{}", ari);
+}
+
+#[test]
+fn array_strided_views_test() {
+    let (points, _) = generated_blobs();
+    let labels = do_appr_dbscan_array(&points, 0.3, 0.1, 5);
+    // column major layout
+    let transposed = Array2::from_shape_vec((3, points.nrows()), points.t().iter().copied().collect()).unwrap();
+    assert_eq!(ApproxDbscan::new(0.3, 0.1, 5).predict(&transposed.t()), labels);
+    // rows that are part of a wider array
+    let mut wider = Array2::from_elem((points.nrows(), 5), f64::NAN);
+    wider.slice_mut(s![.., 1..4]).assign(&points);
+    assert_eq!(do_appr_dbscan_array(&wider.slice(s![.., 1..4]), 0.3, 0.1, 5), labels);
+}
+
+#[test]
+fn array_f32_test() {
+    let (points, _) = generated_blobs();
+    let labels = do_appr_dbscan_array(&points, 0.3, 0.1, 5);
+    let f32_labels = do_appr_dbscan_array(&points.mapv(|x| x as f32).view(), 0.3, 0.1, 5);
+    let ari = adjusted_rand_index(labels.as_slice().unwrap(), f32_labels.as_slice().unwrap());
+    assert!(ari > 0.99, "{}", ari);
+    let empty : Array2<f32> = Array2::zeros((0, 2));
+    assert!(do_appr_dbscan_array(&empty, 0.5, 0.1, 5).is_empty());
+    assert_eq!(do_appr_dbscan_array(&arr2(&[[0.0]]), 0.5, 0.1, 5)[0], None);
+}
+
+#[cfg(feature = "linfa")]
+#[test]
+fn linfa_transformer_test() {
+    use linfa::traits::Transformer;
+    use linfa::DatasetBase;
+    let model = ApproxDbscan::new(0.5, 0.1, 5);
+    let points = two_groups();
+    let labels : Array1<Option<usize>> = model.transform(&points);
+    let dataset = model.transform(DatasetBase::from(points));
+    assert_eq!(dataset.targets, labels);
+}
@@ -1,7 +1,10 @@
 use crate::utils::*;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::collections::HashMap;
 use rstar::{RTree, RTreeParams, RStarInsertionStrategy};
 use crate::tree_structure::TreeStructure;
+use crate::progress::Monitor;
 
 /// Parameters euristhically found to male the r-tree construction and query faster
 struct LargeNodeParameters<const D:usize>;
@@ -14,54 +17,58 @@ impl <const D:usize> RTreeParams for LargeNodeParameters<D> {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A point in a D dimensional euclidean space that memorizes its
-/// status: 'core' or 'non core'
-pub struct StatusPoint<const D: usize> {
-    pub point: Point<D>,
+/// status: 'core' or 'non core'. The coordinates of the point are not copied, 
+/// they are read from the input of the algorithm through the index of the point
+pub struct StatusPoint {
+    /// The position of the point in the input of the algorithm
+    pub index: usize,
     pub is_core: bool
 }
 
-impl <const D: usize> StatusPoint<D> {
-    fn new(point: Point<D>) -> StatusPoint<D> {
+impl StatusPoint {
+    fn new(index: usize) -> StatusPoint {
         StatusPoint {
-            point: point,
+            index,
             is_core: false
         }
     }
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Informations regarding the cell used in various stages of the approximate DBSCAN
 /// algorithm if it is a core cell
 pub struct CoreCellInfo <const D: usize>{
     /// The root of the approximate range counting tree built on the core points of the cell
     pub root: TreeStructure<D>,
     /// The index of the cluster where the cell belongs
-    pub i_cluster: usize,
-    /// The index of the element of union-find structure that references this cell 
-    pub uf_index: usize
+    pub i_cluster: usize
 }
 
 impl <const D: usize> CoreCellInfo<D>{
     fn new() -> CoreCellInfo<D> {
         CoreCellInfo{
             root: TreeStructure::new_empty(),
-            i_cluster: 0,
-            uf_index: 0
+            i_cluster: 0
         }
     }
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A cell from a grid that partitions the D dimensional euclidean space.
 pub struct Cell <const D: usize>{
     /// The index of the intervals of the D dimensional axes where this cell lies
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_array"))]
     pub index: CellIndex<D>,
     /// The points from the dataset that lie inside this cell
-    pub points: Vec<StatusPoint<D>>,
-    /// The list of all the cells that might contain poinst at distance at most 
-    /// 'epsilon' from a point in this cell
-    pub neighbour_cell_indexes: Vec<CellIndex<D>>,
+    pub points: Vec<StatusPoint>,
+    /// The ids in the `CellTable` of all the cells that might contain poinst at distance at most 
+    /// 'epsilon' from a point in this cell. It is not serialized since it can be found again with `populate_neighbours`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub neighbour_cell_ids: Vec<CellId>,
     /// Keeps track of wether this cell is a core cell or not
     pub is_core: bool,
     /// The additional informations that need to be stored if this cell is indeed a core cell
@@ -71,55 +78,298 @@ pub struct Cell <const D: usize>{
 impl <const D: usize> Cell<D> {
     fn new(index_arr: &CellIndex<D>) -> Cell<D>{
         Cell {
-            index: index_arr.clone(),
+            index: *index_arr,
             points: Vec::new(),
             //TODO::size
-            neighbour_cell_indexes: Vec::new(),
+            neighbour_cell_ids: Vec::new(),
             is_core: false,
             core_info: CoreCellInfo::new()
         }
-        //get_neighbours(index_arr, &mut cell.neighbour_cell_indexes);
     }
 }
 
-/// A structure that memorizes all non empty cells by their index's hash
-pub type CellTable <const D: usize> = HashMap<CellIndex<D>, Cell<D>>;
+/// The position of a cell in its `CellTable`
+pub type CellId = u32;
+
+#[derive(Clone, Default)]
+/// A structure that memorizes all non empty cells. The cells are kept in a vector, so that each one can be referred to
+/// by its position, its id, and are also found by their index's hash. Once all the cells are found they are sorted
+/// along the Z-order curve, so that cells close in space have close ids
+pub struct CellTable <const D: usize> {
+    cells: Vec<Cell<D>>,
+    ids: HashMap<CellIndex<D>, CellId>
+}
+
+impl <const D: usize> CellTable<D> {
+    pub fn with_capacity(capacity: usize) -> CellTable<D> {
+        CellTable{cells: Vec::with_capacity(capacity), ids: HashMap::with_capacity(capacity)}
+    }
+
+    /// The number of non empty cells
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// The number of core cells
+    pub fn core_cells_count(&self) -> usize {
+        self.cells.iter().filter(|c| c.is_core).count()
+    }
+
+    /// The id of the cell with index `index`, if it is not empty
+    pub fn id(&self, index: &CellIndex<D>) -> Option<CellId> {
+        self.ids.get(index).copied()
+    }
+
+    /// The cell with index `index`, if it is not empty
+    pub fn get(&self, index: &CellIndex<D>) -> Option<&Cell<D>> {
+        self.id(index).map(|id| &self[id])
+    }
+
+    /// All the cells, in the order of their ids
+    pub fn values(&self) -> std::slice::Iter<'_, Cell<D>> {
+        self.cells.iter()
+    }
+
+    /// All the cells together with their ids, in the order of their ids
+    pub fn iter(&self) -> impl Iterator<Item = (CellId, &Cell<D>)> {
+        self.cells.iter().enumerate().map(|(id, c)| (id as CellId, c))
+    }
+
+    /// All the cells, in the order of their ids
+    pub fn values_mut(&mut self) -> std::slice::IterMut<'_, Cell<D>> {
+        self.cells.iter_mut()
+    }
+
+    /// Builds a table from cells with different indexes, giving them ids in the order of `cells`. Fails with the
+    /// repeated index if two cells have the same one, as it can happen with corrupted serialized data.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_cells(cells: Vec<Cell<D>>) -> Result<CellTable<D>, CellIndex<D>> {
+        let mut ids = HashMap::with_capacity(cells.len());
+        for (id, cell) in cells.iter().enumerate() {
+            let id = CellId::try_from(id).expect("the number of non empty cells does not fit in a cell id");
+            if ids.insert(cell.index, id).is_some() {
+                return Err(cell.index);
+            }
+        }
+        Ok(CellTable{cells, ids})
+    }
+
+    /// The cell with index `index`, that is added empty if it was not already in the table
+    fn get_or_insert(&mut self, index: &CellIndex<D>) -> &mut Cell<D> {
+        let cells = &mut self.cells;
+        let id = *self.ids.entry(*index).or_insert_with(|| {
+            let id = CellId::try_from(cells.len()).expect("the number of non empty cells does not fit in a cell id");
+            cells.push(Cell::new(index));
+            id
+        });
+        &mut cells[id as usize]
+    }
+
+    /// Sorts the cells along the Z-order curve and gives them new ids in that order. The neighbours of the cells
+    /// must be found after sorting them.
+    fn sort_by_morton_order(&mut self) {
+        self.cells.sort_unstable_by(|a, b| morton_cmp(&a.index, &b.index));
+        for (id, cell) in self.cells.iter().enumerate() {
+            *self.ids.get_mut(&cell.index).unwrap() = id as CellId;
+        }
+    }
+}
+
+impl <const D: usize> std::ops::Index<CellId> for CellTable<D> {
+    type Output = Cell<D>;
+
+    fn index(&self, id: CellId) -> &Cell<D> {
+        &self.cells[id as usize]
+    }
+}
+
+impl <const D: usize> std::ops::IndexMut<CellId> for CellTable<D> {
+    fn index_mut(&mut self, id: CellId) -> &mut Cell<D> {
+        &mut self.cells[id as usize]
+    }
+}
+
+/// Compares two cell indexes by their position along the Z-order (Morton) curve, without computing the curve codes:
+/// the order is decided by the axis where the two indexes differ in the most significant bit
+fn morton_cmp<const D: usize>(a: &CellIndex<D>, b: &CellIndex<D>) -> Ordering {
+    // flipping the sign bit keeps the order of the indexes as unsigned integers
+    let to_unsigned = |i: i64| (i as u64) ^ (1 << 63);
+    let mut axis = 0;
+    let mut max_xor = 0_u64;
+    for i in 0..D {
+        let xor = to_unsigned(a[i]) ^ to_unsigned(b[i]);
+        // the most significant bit of `max_xor` is lower than the one of `xor`
+        if max_xor < xor && max_xor < (max_xor ^ xor) {
+            axis = i;
+            max_xor = xor;
+        }
+    }
+    to_unsigned(a[axis]).cmp(&to_unsigned(b[axis]))
+}
 
 /// Divides the D dimensional euclidean space in a grid of cells with side length `epsilon\sqrt(D)` and memorizes 
-/// the non empty ones in a `CellTable`
-pub fn find_cells<const D: usize>(points: Vec<Point<D>>, params: &DBSCANParams) -> CellTable<D> {
+/// the non empty ones in a `CellTable`, sorted along the Z-order curve. The cells only store the indexes of the points 
+/// in `points`. `monitor` is advanced for each point
+pub(crate) fn find_cells<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> CellTable<D> {
     let mut table : CellTable<D> = CellTable::with_capacity(params.cardinality);
-    for p_i in 0..params.cardinality {
-        let curr_point = points[p_i];
-        let index_arr = get_base_cell_index(&curr_point, params);
-        let cell = table.entry(index_arr.clone())
-                    .or_insert(Cell::new(&index_arr));
-        cell.points.push(StatusPoint::new(curr_point));
+    for p_i in 0..points.len().min(params.cardinality) {
+        if !monitor.step() {
+            break;
+        }
+        let index_arr = get_base_cell_index(&points.point(p_i), params);
+        table.get_or_insert(&index_arr).points.push(StatusPoint::new(p_i));
     }
+    table.sort_by_morton_order();
     table
 }
 
-/// Builds an r-tree on all the non empty cells and executes a query on each one to find the indexes of all their possible neighbour cells
-pub fn populate_neighbours<const D: usize>(table: &mut CellTable<D>){
-    let rtree: RTree<CellIndexPoint<D>, LargeNodeParameters<D>> = RTree::bulk_load_with_params(table.keys().map(|k| CellIndexPoint{index: *k}).collect());
-    /*let mut cell_counter = 0;
-    let mut neighbour_counter = 0;
-    let mut points_counter = 0;*/
-    for (key, cell) in table.iter_mut() {
+/// The largest difference along one axis between the indexes of two neighbour cells: neighbour cells have
+/// `index_distance_sq < 4 * D`
+pub(crate) fn neighbour_reach<const D: usize>() -> i64 {
+    let mut reach = 0;
+    while ((reach + 1) * (reach + 1)) < (4 * D) as i64 {
+        reach += 1;
+    }
+    reach
+}
+
+/// The number of offsets between the index of a cell and the indexes of its possible neighbours, the offset
+/// of the cell itself included. They are counted by the sum of the squares of their components without listing them
+fn stencil_len<const D: usize>() -> usize {
+    let reach = neighbour_reach::<D>();
+    // `counts[s]` is the number of offsets on the axes seen so far whose components have squares that sum to `s`
+    let mut counts = vec![0_usize; 4 * D];
+    counts[0] = 1;
+    for _ in 0..D {
+        let mut next = vec![0_usize; 4 * D];
+        for (sum, &count) in counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+            for component in -reach..=reach {
+                let new_sum = sum + (component * component) as usize;
+                if new_sum < 4 * D {
+                    next[new_sum] += count;
+                }
+            }
+        }
+        counts = next;
+    }
+    counts.iter().sum()
+}
+
+/// All the offsets between the index of a cell and the indexes of its possible neighbours, the offset of the cell
+/// itself included: the offsets with `index_distance_sq < 4 * D` from the origin
+fn stencil_offsets<const D: usize>() -> Vec<CellIndex<D>> {
+    let reach = neighbour_reach::<D>();
+    let mut offsets = Vec::with_capacity(stencil_len::<D>());
+    let mut offset = [-reach; D];
+    loop {
+        if index_distance_sq(&offset, &[0; D]) < 4 * D {
+            offsets.push(offset);
+        }
+        // next offset, as in counting in base `2 * reach + 1`
+        let mut axis = 0;
+        while axis < D && offset[axis] == reach {
+            offset[axis] = -reach;
+            axis += 1;
+        }
+        if axis == D {
+            return offsets;
+        }
+        offset[axis] += 1;
+    }
+}
+
+/// Stencils longer than this are never used, since probing so many cells for each cell is always slower than an r-tree
+const MAX_STENCIL_LEN: usize = 1 << 14;
+/// The number of cells sampled to estimate how many neighbours each cell has
+const DENSITY_SAMPLE_LEN: usize = 64;
+/// How many hash table probes take the same time of visiting one node or one neighbour in the r-tree, heuristically found
+const RTREE_VISIT_COST: f64 = 8.0;
+
+/// Finds the ids of all the possible neighbour cells of each cell in `table`. When the neighbours are few compared to 
+/// the cells that may be neighbours, as in high dimensionalities or with sparse cells, an r-tree is built on all the 
+/// non empty cells and queried for each one. Otherwise the table is probed directly with the fixed offsets of the 
+/// neighbours, see `stencil_is_faster`. `monitor` is advanced for each cell
+pub(crate) fn populate_neighbours<const D: usize>(table: &mut CellTable<D>, monitor: &mut Monitor){
+    // r-trees need at least two dimensions
+    if D >= 2 && stencil_len::<D>() > MAX_STENCIL_LEN {
+        populate_neighbours_rtree(table, monitor);
+        return;
+    }
+    let offsets = stencil_offsets::<D>();
+    if D < 2 || stencil_is_faster(table, &offsets) {
+        populate_neighbours_stencil(table, &offsets, monitor);
+    } else {
+        populate_neighbours_rtree(table, monitor);
+    }
+}
+
+/// Tells if the neighbours of the cells in `table` are found faster by probing the table with each of the `offsets`
+/// than by querying an r-tree. The stencil probes the same number of cells for every cell, while an r-tree query visits
+/// a number of nodes that grows with the logarithm of the number of cells and with the number of neighbours found.
+/// The number of neighbours is estimated on a sample of the cells.
+fn stencil_is_faster<const D: usize>(table: &CellTable<D>, offsets: &[CellIndex<D>]) -> bool {
+    if table.is_empty() {
+        return true;
+    }
+    let step = (table.len() / DENSITY_SAMPLE_LEN).max(1);
+    let sample : Vec<&Cell<D>> = table.values().step_by(step).take(DENSITY_SAMPLE_LEN).collect();
+    let neighbours : usize = sample.iter().map(|c| stencil_neighbours(table, &c.index, offsets).count()).sum();
+    let average_neighbours = neighbours as f64 / sample.len() as f64;
+    let rtree_cost = RTREE_VISIT_COST * ((table.len() as f64).log2() + average_neighbours);
+    (offsets.len() as f64) <= rtree_cost
+}
+
+/// The offsets to probe `table` with to find the possible neighbours of a cell that is not in `table`, see
+/// `stencil_neighbours`. `None` when there are more offsets than cells, and checking every cell is faster
+pub(crate) fn lookup_offsets<const D: usize>(table: &CellTable<D>) -> Option<Vec<CellIndex<D>>> {
+    if stencil_len::<D>() <= table.len().min(MAX_STENCIL_LEN) {
+        Some(stencil_offsets::<D>())
+    } else {
+        None
+    }
+}
+
+/// The ids of the non empty cells whose index is `index` moved by one of the `offsets`
+pub(crate) fn stencil_neighbours<'a, const D: usize>(table: &'a CellTable<D>, index: &'a CellIndex<D>, offsets: &'a [CellIndex<D>]) -> impl Iterator<Item = CellId> + 'a {
+    offsets.iter().filter_map(move |offset| {
+        let mut n_index = *index;
+        for axis in 0..D {
+            n_index[axis] += offset[axis];
+        }
+        table.id(&n_index)
+    })
+}
+
+/// Finds the neighbours of each cell by looking up in `table` the index of the cell moved by each of the `offsets`
+fn populate_neighbours_stencil<const D: usize>(table: &mut CellTable<D>, offsets: &[CellIndex<D>], monitor: &mut Monitor){
+    for id in 0..table.len() {
+        if !monitor.step() {
+            return;
+        }
+        let index = table.cells[id].index;
+        let neighbours : Vec<CellId> = stencil_neighbours(table, &index, offsets).collect();
+        table.cells[id].neighbour_cell_ids = neighbours;
+    }
+}
+
+/// Finds the neighbours of each cell with a query on an r-tree built on the indexes of all the cells
+fn populate_neighbours_rtree<const D: usize>(table: &mut CellTable<D>, monitor: &mut Monitor){
+    let rtree: RTree<CellIndexPoint<D>, LargeNodeParameters<D>> = RTree::bulk_load_with_params(table.values().map(|c| CellIndexPoint{index: c.index}).collect());
+    for id in 0..table.len() {
+        if !monitor.step() {
+            return;
+        }
+        let index = table.cells[id].index;
         //devo filtrare perche' li voglio minori e non minori_uguali
-        let neighbours : Vec<CellIndex<D>>= rtree.locate_within_distance(CellIndexPoint{index: key.clone()}, (4 * D) as i64).filter(
-            |x| index_distance_sq(&x.index, key) < 4*D
-        ).map(|x| x.index).collect();
-        /*cell_counter += 1;
-        neighbour_counter +=neighbours.len();
-        points_counter += cell.points.len();*/
-        cell.neighbour_cell_indexes = neighbours;
-    }
-    //println!("Average number of neighbours: {}",neighbour_counter/cell_counter);
-    //println!("Average number of points per cell: {}",points_counter/cell_counter);
-    /*for (key, cell) in table.iter_mut() {
-        get_neighbours(&cell.index, &mut cell.neighbour_cell_indexes);
-    }*/
+        let neighbours : Vec<CellId> = rtree.locate_within_distance(CellIndexPoint{index}, (4 * D) as i64).filter(
+            |x| index_distance_sq(&x.index, &index) < 4*D
+        ).map(|x| table.ids[&x.index]).collect();
+        table.cells[id].neighbour_cell_ids = neighbours;
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,6 @@
 use super::*;
+use crate::generate::uniform;
+use crate::progress::Monitor;
 
 #[test]
 fn find_cells_test(){
@@ -12,9 +14,100 @@ fn find_cells_test(){
     let l = params.epsilon / (params.dimensionality as f64).sqrt();
     let q = [l,-l];
     let q2 = [-l,l];
-    let mut points = Vec::with_capacity(2);
-    points.push(q.clone());
-    points.push(q2.clone());
-    let base_table = find_cells(points, &params);
+    let points = vec![q, q2];
+    let base_table = find_cells(&points, &params, &mut Monitor::unobserved());
     assert_eq!(base_table.len(), 2);   
-}
\ No newline at end of file
+}
+#[test]
+fn stencil_test() {
+    fn check<const D: usize>(expected_len: usize) {
+        let offsets = stencil_offsets::<D>();
+        assert_eq!(offsets.len(), expected_len);
+        assert_eq!(stencil_len::<D>(), expected_len);
+        assert!(offsets.contains(&[0; D]));
+        assert!(offsets.iter().all(|o| index_distance_sq(o, &[0; D]) < 4 * D));
+    }
+    check::<1>(3);
+    check::<2>(21);
+    check::<3>(171);
+    check::<4>(1257);
+    assert_eq!(stencil_len::<5>(), 9153);
+    assert_eq!(stencil_len::<6>(), 68733);
+    assert!(stencil_len::<6>() > MAX_STENCIL_LEN);
+    assert_eq!(neighbour_reach::<1>(), 1);
+    assert_eq!(neighbour_reach::<4>(), 3);
+}
+
+#[test]
+fn neighbour_strategies_test() {
+    fn check<const D: usize>(spread: f64) {
+        let params = DBSCANParams {
+            cardinality: 2000,
+            dimensionality: D as u32,
+            epsilon: 1.0,
+            rho: 0.1,
+            min_pts: 0
+        };
+        let points : Vec<Point<D>> = vector_input_to_array_input(uniform(params.cardinality, D, spread, 7).points);
+        let mut stencil_table = find_cells(&points, &params, &mut Monitor::unobserved());
+        let mut rtree_table = stencil_table.clone();
+        populate_neighbours_stencil(&mut stencil_table, &stencil_offsets::<D>(), &mut Monitor::unobserved());
+        populate_neighbours_rtree(&mut rtree_table, &mut Monitor::unobserved());
+        for (stencil_cell, rtree_cell) in stencil_table.values().zip(rtree_table.values()) {
+            let mut stencil_ids = stencil_cell.neighbour_cell_ids.clone();
+            let mut rtree_ids = rtree_cell.neighbour_cell_ids.clone();
+            stencil_ids.sort_unstable();
+            rtree_ids.sort_unstable();
+            assert_eq!(stencil_ids, rtree_ids);
+            assert!(stencil_ids.iter().all(|&id| index_distance_sq(&stencil_table[id].index, &stencil_cell.index) < 4 * D));
+        }
+    }
+    check::<2>(10.0);
+    check::<2>(200.0);
+    check::<3>(10.0);
+    check::<4>(5.0);
+}
+
+#[test]
+fn populate_neighbours_test() {
+    let params = DBSCANParams {
+        cardinality: 3,
+        dimensionality: 1,
+        epsilon: 1.0,
+        rho: 0.1,
+        min_pts: 0
+    };
+    let points = vec![[0.0], [1.0], [5.0]];
+    let mut table = find_cells(&points, &params, &mut Monitor::unobserved());
+    populate_neighbours(&mut table, &mut Monitor::unobserved());
+    assert_eq!(table.len(), 3);
+    let id = table.id(&[0]).unwrap();
+    assert_eq!(table[id].neighbour_cell_ids.len(), 2);
+    assert_eq!(table.get(&[5]).unwrap().neighbour_cell_ids, vec![table.id(&[5]).unwrap()]);
+}
+
+#[test]
+fn morton_order_test() {
+    use std::cmp::Ordering::*;
+    // the first axis is the most significant one
+    assert_eq!(morton_cmp(&[0, 0], &[0, 1]), Less);
+    assert_eq!(morton_cmp(&[0, 1], &[1, 0]), Less);
+    assert_eq!(morton_cmp(&[1, 1], &[0, 2]), Less);
+    assert_eq!(morton_cmp(&[-1, 0], &[0, 0]), Less);
+    assert_eq!(morton_cmp(&[3, -5, 2], &[3, -5, 2]), Equal);
+    let params = DBSCANParams {
+        cardinality: 16,
+        dimensionality: 2,
+        epsilon: 2_f64.sqrt(),
+        rho: 0.1,
+        min_pts: 0
+    };
+    // one point in each cell of a 4x4 square of cells, given row by row
+    let points : Vec<Point<2>> = (0..16).map(|i| [(i % 4) as f64, (i / 4) as f64]).collect();
+    let table = find_cells(&points, &params, &mut Monitor::unobserved());
+    let order : Vec<usize> = table.values().map(|c| c.points[0].index).collect();
+    assert_eq!(order, vec![0, 4, 1, 5, 8, 12, 9, 13, 2, 6, 3, 7, 10, 14, 11, 15]);
+    for (id, cell) in table.iter() {
+        assert_eq!(table.id(&cell.index), Some(id));
+    }
+}
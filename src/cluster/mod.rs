@@ -1,76 +1,116 @@
-use crate::cell::{CellTable};
+use crate::cell::{CellId, CellTable};
 //use crate::core_cell::core_points_in_range;
 use crate::utils::*;
+use crate::progress::Monitor;
 use partitions::PartitionVec;
 
 /// The result of the approximate DBSCAN algorithm always has an element at this index
 /// that corresponds to the set of noise points found
 pub const NOISE_CLUSTER_INDEX:usize = 0;
 
+/// Explores the union-find structure `part_vec`, that has an element for each cell at the position of its id, and gives the same cluster 
+/// index to all the core cells in the same set and different cluster indexes to core cells from different sets. The cluster indexes start 
+/// from `1`, since `NOISE_CLUSTER_INDEX` is reserved for the noise points. This function supposes that all possible union operations on 
+/// `part_vec` were already done. Returns the number of clusters found.
+pub fn assign_cluster_indexes<const D: usize>(cells: &mut CellTable<D>, part_vec: PartitionVec<CellId>) -> usize {
+    let mut clusters_count: usize = 0;
+    for set in part_vec.all_sets(){
+        let mut set = set.map(|(_, id)| *id).peekable();
+        // the non core cells are never joined to other cells, so they are alone in their sets
+        if !set.peek().is_some_and(|id| cells[*id].is_core) {
+            continue;
+        }
+        clusters_count += 1;
+        for id in set {
+            cells[id].core_info.i_cluster = clusters_count;
+        }
+    }
+    clusters_count
+}
+
 /// Explores the union-find structure `part_vec` and puts all core points in the same set in the same clusters and core points from 
 /// different sets in different clusters. This function supposes that all possible union operations on `part_vec` were already done.
 /// The result of this function is a collection of cluster constructed as described above, and its first element is the (now still empty)
-/// set of noise points
-pub fn find_connected_components<const D: usize>(cells: &mut CellTable<D>, part_vec: PartitionVec<CellIndex<D>>) -> DBSCANResult<D>{
-    let mut res : DBSCANResult<D> = Vec::new();
-    let noise_cluster : Cluster<D> = Vec::new();
+/// set of noise points. The coordinates of the points are copied from `points`.
+pub fn find_connected_components<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, part_vec: PartitionVec<CellId>) -> DBSCANResult<D, F>{
+    let clusters_count = assign_cluster_indexes(cells, part_vec);
     //the noise cluster will be at index 0
-    res.push(noise_cluster);
-    let mut current_cluster_i: usize = 1;
-    for set in part_vec.all_sets(){
-        let mut new_cluster : Cluster<D> = Vec::new();
-        for (_,key) in set {
-            let curr_core_cell = cells.get_mut(key).unwrap();
-            curr_core_cell.core_info.i_cluster = current_cluster_i;
-            for s_point in &curr_core_cell.points {
-                if s_point.is_core {
-                    new_cluster.push(s_point.point.clone());
-                }
+    let mut res : DBSCANResult<D, F> = vec![Vec::new(); clusters_count + 1];
+    for cell in cells.values().filter(|c| c.is_core) {
+        for s_point in &cell.points {
+            if s_point.is_core {
+                res[cell.core_info.i_cluster].push(points.point(s_point.index));
             }
         }
-        res.push(new_cluster);
-        current_cluster_i += 1;
     }
     res
 }
 
 /// Loops through all non core points of the dataset and puts them in the cluster\clusters they belong to. If no such cluster is found then
-/// the point is added to the noise points set.
-pub fn assign_border_noise_points<const D: usize>(cells: &CellTable<D>, clusters: &mut DBSCANResult<D>, params: &DBSCANParams) {
+/// the point is added to the noise points set. `monitor` is advanced for each cell.
+pub(crate) fn assign_border_noise_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, points: &S, clusters: &mut DBSCANResult<D, F>, params: &DBSCANParams, monitor: &mut Monitor) {
     for cell in cells.values() {
+        if !monitor.step() {
+            return;
+        }
         for s_point in &cell.points {
             if !s_point.is_core {
-                assign_border_noise_point(&s_point.point, &cell.neighbour_cell_indexes, clusters, cells, params);
+                let point = points.point(s_point.index);
+                let clusters_in = find_point_clusters(&point, &cell.neighbour_cell_ids, cells, params, monitor);
+                if clusters_in.is_empty() {
+                    clusters[NOISE_CLUSTER_INDEX].push(point);
+                }
+                for i_cluster in clusters_in {
+                    clusters[i_cluster].push(point);
+                }
             }
         }
     }
 }
 
-/// Evaluates if a single point belongs to one or more clusters or if it is a noise points and adds the point to the cluster/s or set where it belongs.
-fn assign_border_noise_point<const D: usize>(point: &Point<D>,neighbours: &Vec<CellIndex<D>>, clusters: &mut DBSCANResult<D>, cells: &CellTable<D>, params: &DBSCANParams) {
-    let mut clusters_in : Vec<usize> = Vec::new();
-    for n_index in neighbours {
-        match cells.get(n_index) {
-            Some(curr_cell) => {
-                if curr_cell.is_core{
-                    if !clusters_in.contains(&curr_cell.core_info.i_cluster) {
-                        if curr_cell.core_info.root.approximate_range_counting_root(&point,params) != 0 {    
-                            clusters[curr_cell.core_info.i_cluster].push(point.clone());
-                            clusters_in.push(curr_cell.core_info.i_cluster);
-                        }
-                        /*if core_points_in_range(&point, curr_cell, params.epsilon) > 0 {
-                            clusters[curr_cell.core_info.i_cluster].push(point.clone());
-                            clusters_in.push(curr_cell.core_info.i_cluster);
-                        }*/
-                    }
+/// Labels all the points of the dataset. Core points take the cluster index of their cell, while non core points are labeled 
+/// as border points of the first cluster they are found to belong to, or as noise points if no such cluster is found.
+/// This function supposes that the cluster indexes were already assigned to the core cells. There is a label for each point
+/// put in the cells by `find_cells`, so the first `min(points.len(), params.cardinality)` ones. `monitor` is advanced for each cell.
+pub(crate) fn label_all_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> DBSCANLabels {
+    let noise_label = PointLabel{cluster_id: NOISE_CLUSTER_INDEX, role: PointRole::Noise};
+    let mut labels : DBSCANLabels = vec![noise_label; points.len().min(params.cardinality)];
+    for cell in cells.values() {
+        if !monitor.step() {
+            break;
+        }
+        for s_point in &cell.points {
+            if s_point.is_core {
+                labels[s_point.index] = PointLabel{cluster_id: cell.core_info.i_cluster, role: PointRole::Core};
+            } else {
+                let clusters_in = find_point_clusters(&points.point(s_point.index), &cell.neighbour_cell_ids, cells, params, monitor);
+                if let Some(i_cluster) = clusters_in.first() {
+                    labels[s_point.index] = PointLabel{cluster_id: *i_cluster, role: PointRole::Border};
                 }
-            },
-            _ => {},
+            }
         }
     }
-    if clusters_in.is_empty() {
-        clusters[NOISE_CLUSTER_INDEX].push(point.clone());
+    labels
+}
+
+/// Evaluates if a single point belongs to one or more clusters or if it is a noise point. Returns the indexes of 
+/// all the clusters where the point belongs, that will be empty if the point is a noise point. `neighbours` are the ids of the 
+/// cells to check in `cells`. The queries made on the trees of the core cells are counted in `monitor`.
+pub(crate) fn find_point_clusters<F: Float, const D: usize>(point: &Point<D, F>,neighbours: &[CellId], cells: &CellTable<D>, params: &DBSCANParams, monitor: &mut Monitor) -> Vec<usize> {
+    let mut clusters_in : Vec<usize> = Vec::new();
+    for &n_id in neighbours {
+        let curr_cell = &cells[n_id];
+        if curr_cell.is_core && !clusters_in.contains(&curr_cell.core_info.i_cluster) {
+            monitor.count_range_query();
+            if curr_cell.core_info.root.approximate_range_nonempty(point, params) {
+                clusters_in.push(curr_cell.core_info.i_cluster);
+            }
+        }
+        /*if core_points_in_range(&point, curr_cell, params.epsilon) > 0 {
+            clusters_in.push(curr_cell.core_info.i_cluster);
+        }*/
     }
+    clusters_in
 }
 
 #[cfg(test)]
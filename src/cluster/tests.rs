@@ -1,4 +1,5 @@
 use super::*;
+use crate::progress::Monitor;
 use crate::cell::find_cells;
 use crate::core_cell::{label_points,compute_adjacency_lists};
 
@@ -16,16 +17,12 @@ fn clustering_test() {
     let p2 = [2.0*l,2.0*l];
     let p3 = [2.0*l,2.0*l];
     let p4 = [-5.0*l,-5.0*l];
-    let mut points = Vec::with_capacity(4);
-    points.push(p1);
-    points.push(p2);
-    points.push(p3);
-    points.push(p4);
-    let mut base_table = find_cells(points, &params);
-    let mut p_v = label_points(&mut base_table, &params);
-    compute_adjacency_lists(&mut base_table, &params, &mut p_v);
-    let mut result = find_connected_components(&mut base_table, p_v);
-    assign_border_noise_points(&base_table, &mut result, &params);
+    let points = vec![p1, p2, p3, p4];
+    let mut base_table = find_cells(&points, &params, &mut Monitor::unobserved());
+    label_points(&mut base_table, &points, &params, &mut Monitor::unobserved());
+    let (p_v, _) = compute_adjacency_lists(&base_table, &points, &params, &mut Monitor::unobserved());
+    let mut result = find_connected_components(&mut base_table, &points, p_v);
+    assign_border_noise_points(&base_table, &points, &mut result, &params, &mut Monitor::unobserved());
     assert_eq!(result.len(), 2);
     assert_eq!(result[NOISE_CLUSTER_INDEX].len(), 1);
     assert_eq!(result[1].len(), 3);
@@ -1,19 +1,40 @@
 use crate::cell::*;
 use crate::utils::*;
 use crate::tree_structure::TreeStructure;
+use crate::point_index::{PointIndex, LEAF_POINTS};
+use crate::progress::Monitor;
 use partitions::PartitionVec;
+use std::collections::HashMap;
 
 
-/// Counts the points in `cell` that are at distance at most `epsilon` from `point`.
-/// The distance used is the euclidean one.
-pub fn points_in_range<const D: usize>(point: &Point<D>, cell: &Cell<D>, epsilon: f64) -> usize{
-    let mut cnt : usize = 0;
-    for s_point in &cell.points {
-        if euclidean_distance(point, &s_point.point) <= epsilon {
-            cnt += 1;
+/// The coordinates of the points of a cell, copied from the input one axis at a time (structure of arrays) so that the
+/// distances of many points from the same point can be computed in batches. The buffers are reused from cell to cell
+pub(crate) struct CellCoordinates<F: Float, const D: usize> {
+    axes: [Vec<F>; D]
+}
+
+impl <F: Float, const D: usize> CellCoordinates<F, D> {
+    pub fn new() -> CellCoordinates<F, D> {
+        CellCoordinates{axes: std::array::from_fn(|_| Vec::new())}
+    }
+
+    /// Replaces the coordinates with the ones of the points in `cell`, read from `points`
+    pub fn fill<S: PointSource<F, D> + ?Sized>(&mut self, cell: &Cell<D>, points: &S) {
+        for axis in self.axes.iter_mut() {
+            axis.clear();
+        }
+        for s_point in &cell.points {
+            let point = points.point(s_point.index);
+            for (axis, coordinate) in self.axes.iter_mut().zip(point.iter()) {
+                axis.push(*coordinate);
+            }
         }
     }
-    cnt
+
+    /// Counts the points that are at distance at most `sqrt(epsilon_sq)` from `point`. The distance used is the euclidean one.
+    pub fn points_in_range(&self, point: &Point<D, F>, epsilon_sq: F) -> usize {
+        count_within_sq(&self.axes, 0..self.axes[0].len(), point, epsilon_sq)
+    }
 }
 
 /*pub fn core_points_in_range<const D: usize>(point: &Point<D>, cell: &Cell<D>, epsilon: f64) -> usize{
@@ -26,14 +47,43 @@ pub fn points_in_range<const D: usize>(point: &Point<D>, cell: &Cell<D>, epsilon
     cnt
 }*/
 
-/// Equality function for type CellIndex<D>
-fn is_same_index<const D: usize>(i1: &CellIndex<D>, i2: &CellIndex<D>) -> bool {
-    for i in 0..D {
-        if i1[i] != i2[i] {
-            return false;
+/// The neighbour cells with at least this many points are counted through a `PointIndex`. The index of a smaller cell
+/// would be a single leaf, whose points are all measured, so their points are read directly without building it
+pub(crate) const INDEX_MIN_POINTS: usize = LEAF_POINTS + 1;
+
+/// The points of the neighbour cells read by `find_sparse_cell_core_points`. The cells with at least `index_min_points`
+/// points are read through a `PointIndex`, that is built the first time the cell is needed and dropped after the last
+/// sparse cell next to it is labelled, so only the indexes of the cells around the sparse cells being labelled are
+/// kept at the same time. The other cells are copied in a buffer that is reused from cell to cell
+struct NeighbourPoints<F: Float, const D: usize> {
+    index_min_points: usize,
+    indexes: HashMap<CellId, PointIndex<F, D>>,
+    /// The id of the last sparse cell, in the order of the ids, that reads each indexed cell
+    last_reader: HashMap<CellId, CellId>,
+    coordinates: CellCoordinates<F, D>
+}
+
+impl <F: Float, const D: usize> NeighbourPoints<F, D> {
+    fn new(cells: &CellTable<D>, params: &DBSCANParams, index_min_points: usize) -> NeighbourPoints<F, D> {
+        let mut last_reader = HashMap::new();
+        for (id, cell) in cells.iter().filter(|(_, c)| c.points.len() < params.min_pts) {
+            for &n_id in cell.neighbour_cell_ids.iter().filter(|&&n_id| n_id != id) {
+                if cells[n_id].points.len() >= index_min_points {
+                    last_reader.insert(n_id, id);
+                }
+            }
+        }
+        NeighbourPoints{index_min_points, indexes: HashMap::new(), last_reader, coordinates: CellCoordinates::new()}
+    }
+
+    /// Drops the indexes that are not needed after the sparse cell `id`, whose neighbours are `neighbour_cell_ids`
+    fn release(&mut self, id: CellId, neighbour_cell_ids: &[CellId]) {
+        for n_id in neighbour_cell_ids {
+            if self.last_reader.get(n_id) == Some(&id) {
+                self.indexes.remove(n_id);
+            }
         }
     }
-    true
 }
 
 /// Function that decides which points from each cell are core points and which cells are core cells.
@@ -41,92 +91,146 @@ fn is_same_index<const D: usize>(i1: &CellIndex<D>, i2: &CellIndex<D>) -> bool {
 /// # Arguments:
 /// 
 /// * `cells`: The non empty cells obtained from partitioning the `D` dimensional euclidean space
+/// * `points`: The points whose indexes are stored in `cells`
 /// * `params`: the DBSCAN algorithm parameters
+/// * `monitor`: advanced for each cell, the labelling stops early if the run is cancelled
 /// 
 /// # Return
 /// 
-/// A union-find structure that contains all and only the core cells found from `cells`. Each cell in `cells` that has been 
-/// labeled as a core cell will keep a reference to its index inside the union-find structure
-pub fn label_points<const D: usize>(cells: &mut CellTable<D>, params: &DBSCANParams) -> PartitionVec<CellIndex<D>> {
-    //The union find structure will contain the core cell that are found, that are for sure at most in the same number 
-    //as the non core cells
-    let mut part_vec : PartitionVec<CellIndex<D>> = PartitionVec::with_capacity(cells.len());
-    let cells_cloned = cells.clone();
+/// The number of core cells found
+pub(crate) fn label_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> usize {
+    label_points_with_index(cells, points, params, INDEX_MIN_POINTS, monitor)
+}
+
+/// Same as `label_points`, with the neighbour cells of at least `index_min_points` points read through a `PointIndex`
+/// to find the core points of the sparse cells. With `usize::MAX` every point of the neighbour cells is measured
+pub(crate) fn label_points_with_index<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, params: &DBSCANParams, index_min_points: usize, monitor: &mut Monitor) -> usize {
+    let mut core_cells_count = 0;
+    // The sparse cells need to read the points of their neighbours, so their core points are found first without
+    // modifying the cells and are then labeled together with the dense cells. The monitor is advanced for the sparse
+    // cells in the first pass and for the dense ones in the second
+    let mut is_core_point = vec![false; points.len()];
+    let mut neighbour_points = NeighbourPoints::new(cells, params, index_min_points);
+    for (id, cell) in cells.iter().filter(|(_, c)| c.points.len() < params.min_pts) {
+        if !monitor.step() {
+            return core_cells_count;
+        }
+        find_sparse_cell_core_points(cells, id, cell, points, params, &mut neighbour_points, &mut is_core_point);
+        neighbour_points.release(id, &cell.neighbour_cell_ids);
+    }
     for cell in cells.values_mut() {
         if cell.points.len() >= params.min_pts {
-            label_dense_cell(cell, params, &mut part_vec);
+            if !monitor.step() {
+                break;
+            }
+            label_dense_cell(cell, points, params);
         } else {
-            label_sparse_cell(&cells_cloned, cell, params, &mut part_vec)
+            label_sparse_cell(cell, points, params, &is_core_point)
+        }
+        if cell.is_core {
+            core_cells_count += 1;
         }
     }
-    part_vec
+    core_cells_count
 }
 
 /// Function to label as a core cell the cells that have at least 'MinPts' points inside. Sets also the status of
-/// all the points in `cell` to 'core'. The cell is then added to the union-find structure `uf_str` and its index inside
-/// the structure is memorized in the cell. An approximate range counting structure is then built on the core points and 
+/// all the points in `cell` to 'core'. An approximate range counting structure is then built on the core points and 
 /// memorized in the cell
-fn label_dense_cell<const D: usize>(cell: &mut Cell<D>, params: & DBSCANParams, uf_str: &mut PartitionVec<CellIndex<D>>){
+fn label_dense_cell<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cell: &mut Cell<D>, points: &S, params: & DBSCANParams){
     cell.is_core = true;
-    cell.core_info.uf_index = uf_str.len();
-    let points : Vec<Point<D>> = cell.points.iter().map(|x| x.point).collect();
-    cell.core_info.root = TreeStructure::build_structure(points, params);
-    uf_str.push(cell.index);
-    for mut s_point in &mut cell.points {
+    cell.core_info.root = TreeStructure::build_structure(cell.points.iter().map(|x| points.point(x.index)), params);
+    for s_point in &mut cell.points {
         s_point.is_core = true;
     }
 }
 
-/// Function to decide if a cell with less than 'MinPts' points inside is a core cell. If it is a core cell
-/// then all the core points inside are labeled as such and the cell is added to the union-find structure 'uf_str' and its index
-/// inside the structure is memorized in the cell. An approximate range counting structure is then built on the core points and 
-/// memorized in the cell
-fn label_sparse_cell<const D: usize>(cells_c: &CellTable<D>,curr_cell: &mut Cell<D>, params: &DBSCANParams, uf_str: &mut PartitionVec<CellIndex<D>>){
-    let len = curr_cell.points.len();
-    let mut points : Vec<Point<D>> = Vec::with_capacity(curr_cell.points.len());
-    for mut s_point in &mut curr_cell.points {
-        let mut tot_pts = len;
-        for n_index in &curr_cell.neighbour_cell_indexes {
-            if !is_same_index(&curr_cell.index, n_index) {
-                // By using the r-tree fo populate the neighbours indexes I can be sure to get
-                // `Some(neighbour)` from the `get` call.
-                let neighbour = cells_c.get(n_index).unwrap();
-                tot_pts += points_in_range(&s_point.point, neighbour, params.epsilon);
+/// Function to find the core points of a cell with less than 'MinPts' points inside, by counting the points close to
+/// each of them in the neighbour cells. Each neighbour cell is read once for all the points that are not yet known to be
+/// core points, and the counting of a point stops as soon as it reaches `min_pts`. The large neighbour cells are read
+/// through their `PointIndex`, so the cost of a point depends on the points of the neighbour near the border of its ball
+/// instead of on all of them. `curr_id` is the id of `curr_cell` in `cells`. The core points found are marked in
+/// `is_core_point`, indexed by the position of the points in `points`
+fn find_sparse_cell_core_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, curr_id: CellId, curr_cell: &Cell<D>, points: &S, params: &DBSCANParams, neighbour_points: &mut NeighbourPoints<F, D>, is_core_point: &mut [bool]){
+    let epsilon = F::from_f64(params.epsilon);
+    let epsilon_sq = epsilon * epsilon;
+    let curr_points : Vec<Point<D, F>> = curr_cell.points.iter().map(|p| points.point(p.index)).collect();
+    let mut tot_pts = vec![curr_points.len(); curr_points.len()];
+    for &n_id in &curr_cell.neighbour_cell_ids {
+        if tot_pts.iter().all(|&t| t >= params.min_pts) {
+            break;
+        }
+        if n_id == curr_id {
+            continue;
+        }
+        let n_cell = &cells[n_id];
+        if n_cell.points.len() >= neighbour_points.index_min_points {
+            let index = neighbour_points.indexes.entry(n_id)
+                .or_insert_with(|| PointIndex::build(n_cell.points.iter().map(|p| points.point(p.index))));
+            for (point, tot) in curr_points.iter().zip(tot_pts.iter_mut()) {
+                if *tot < params.min_pts {
+                    *tot += index.count_within_sq(point, epsilon_sq, params.min_pts - *tot);
+                }
             }
-            if tot_pts >= params.min_pts {
-                break;
+        } else {
+            neighbour_points.coordinates.fill(n_cell, points);
+            for (point, tot) in curr_points.iter().zip(tot_pts.iter_mut()) {
+                if *tot < params.min_pts {
+                    *tot += neighbour_points.coordinates.points_in_range(point, epsilon_sq);
+                }
             }
         }
-        if tot_pts >= params.min_pts {
+    }
+    for (s_point, tot) in curr_cell.points.iter().zip(tot_pts) {
+        if tot >= params.min_pts {
+            is_core_point[s_point.index] = true;
+        }
+    }
+}
+
+/// Function to label the core points of a cell with less than 'MinPts' points inside, as found by `find_sparse_cell_core_points`.
+/// If there is at least one of them then the cell is a core cell, and an approximate range counting structure is then built on 
+/// the core points and memorized in the cell
+fn label_sparse_cell<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(curr_cell: &mut Cell<D>, points: &S, params: &DBSCANParams, is_core_point: &[bool]){
+    for s_point in &mut curr_cell.points {
+        if is_core_point[s_point.index] {
             s_point.is_core = true;
             curr_cell.is_core = true;
-            points.push(s_point.point.clone());
         }
     }
     if curr_cell.is_core {
-        curr_cell.core_info.uf_index = uf_str.len();
-        uf_str.push(curr_cell.index.clone());      
-        curr_cell.core_info.root = TreeStructure::build_structure(points, params);
+        let core_points = curr_cell.points.iter().filter(|x| x.is_core).map(|x| points.point(x.index));
+        curr_cell.core_info.root = TreeStructure::build_structure(core_points, params);
     }
 }
 
 
-/// Function that makes all the possible 'union' operations on the union-find structure `part_vec` on cells that have core points close enough
-/// to create an arc between them. At the end of this function `part_vec` has as many sets inside as the number of approximate clusters and all 
-/// cells in the same set contain all and only the core points that belong to the same cluster.
-pub fn compute_adjacency_lists<const D: usize>(cells:  &mut CellTable<D>, params: &DBSCANParams, part_vec: &mut PartitionVec<CellIndex<D>>){
-    for cell in cells.values().filter(|c| c.is_core) {
-        for n_index in &cell.neighbour_cell_indexes {
-            // By using the r-tree fo populate the neighbours indexes I can be sure to get
-            // `Some(neighbour)` from the `get` call.
-            let neighbour = cells.get(n_index).unwrap();
+/// Function that builds a union-find structure with an element for each cell, at the position of its id, and makes all the possible 'union' 
+/// operations on cells that have core points close enough to create an arc between them. At the end of this function the union-find structure 
+/// has as many sets of core cells inside as the number of approximate clusters and all cells in the same set contain all and only the core points 
+/// that belong to the same cluster. The non core cells are left alone in their sets. `monitor` is advanced for each cell.
+/// Returns the union-find structure and the number of 'union' operations that merged two different sets.
+pub(crate) fn compute_adjacency_lists<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> (PartitionVec<CellId>, usize) {
+    let mut part_vec : PartitionVec<CellId> = cells.iter().map(|(id, _)| id).collect();
+    let mut merges = 0;
+    for (id, cell) in cells.iter() {
+        if !monitor.step() {
+            break;
+        }
+        if !cell.is_core {
+            continue;
+        }
+        for &n_id in &cell.neighbour_cell_ids {
+            let neighbour = &cells[n_id];
             if neighbour.is_core {
-                if part_vec.same_set(cell.core_info.uf_index, neighbour.core_info.uf_index){
+                if part_vec.same_set(id as usize, n_id as usize){
                     continue;
                 }
                 for point in cell.points.iter().filter(|p| p.is_core) {
-                    if neighbour.core_info.root.approximate_range_counting_root(&point.point, params) != 0 {
-                        part_vec.union(cell.core_info.uf_index, neighbour.core_info.uf_index);
+                    monitor.count_range_query();
+                    if neighbour.core_info.root.approximate_range_nonempty(&points.point(point.index), params) {
+                        part_vec.union(id as usize, n_id as usize);
+                        merges += 1;
                         break;
                     }  
                 }
@@ -134,6 +238,7 @@ pub fn compute_adjacency_lists<const D: usize>(cells:  &mut CellTable<D>, params
             
         }
     }
+    (part_vec, merges)
 }
 
 
@@ -1,4 +1,5 @@
 use super::*;
+use crate::progress::Monitor;
 
 #[test]
 fn label_points_test() {
@@ -14,18 +15,15 @@ fn label_points_test() {
     let p2 = [2.0*l,2.0*l];
     let p3 = [2.0*l,2.0*l];
     let p4 = [l,l];
-    let mut points = Vec::with_capacity(4);
-    points.push(p1);
-    points.push(p2);
-    points.push(p3);
-    points.push(p4);
-    let mut base_table = find_cells(points, &params);
-    populate_neighbours(&mut base_table);
-    let mut p_v = label_points(&mut base_table, &params);
-    compute_adjacency_lists(&mut base_table, &params, &mut p_v);
+    let points = vec![p1, p2, p3, p4];
+    let mut base_table = find_cells(&points, &params, &mut Monitor::unobserved());
+    populate_neighbours(&mut base_table, &mut Monitor::unobserved());
+    let core_cells_count = label_points(&mut base_table, &points, &params, &mut Monitor::unobserved());
+    let (p_v, merges) = compute_adjacency_lists(&base_table, &points, &params, &mut Monitor::unobserved());
     assert_eq!(base_table.len(), 2);  
-    let core_cells_count = base_table.values().filter(|x| x.is_core).count();
     assert_eq!(core_cells_count, 2);
+    assert_eq!(base_table.core_cells_count(), 2);
+    assert_eq!(merges, 1);
     assert_eq!(p_v.all_sets().count(),1);
     for set in p_v.all_sets() {
         assert_eq!(set.count(), 2);
@@ -34,20 +32,50 @@ fn label_points_test() {
     let p2 = [2.0*l,2.0*l];
     let p3 = [2.0*l,2.0*l];
     let p4 = [-5.0*l,-5.0*l];
-    let mut points = Vec::with_capacity(4);
-    points.push(p1);
-    points.push(p2);
-    points.push(p3);
-    points.push(p4);
-    let mut base_table = find_cells(points, &params);
-    populate_neighbours(&mut base_table);
-    let p_v = label_points(&mut base_table, &params);
+    let points = vec![p1, p2, p3, p4];
+    let mut base_table = find_cells(&points, &params, &mut Monitor::unobserved());
+    populate_neighbours(&mut base_table, &mut Monitor::unobserved());
+    assert_eq!(label_points(&mut base_table, &points, &params, &mut Monitor::unobserved()), 1);
+    let (p_v, merges) = compute_adjacency_lists(&base_table, &points, &params, &mut Monitor::unobserved());
     assert_eq!(base_table.len(), 2);   
     assert_eq!(base_table.values().filter(|x| x.is_core).count(), 1);
-    assert_eq!(p_v.all_sets().count(),1);
+    assert_eq!(merges, 0);
+    // the non core cell is alone in its set
+    assert_eq!(p_v.all_sets().count(),2);
     for set in p_v.all_sets() {
         assert_eq!(set.count(), 1);
     }
 
 
-}
\ No newline at end of file
+}
+/// Checks that the core points found are exactly the points with at least `min_pts` points at distance at most `epsilon`,
+/// both when the neighbour cells of at least `index_min_points` points are read through an index and when none are
+fn check_core_points<const D: usize>(points: Vec<Point<D>>, epsilon: f64, min_pts: usize, index_min_points: usize) -> Result<(), proptest::test_runner::TestCaseError> {
+    let params = DBSCANParams{cardinality: points.len(), dimensionality: D as u32, epsilon, rho: 0.1, min_pts};
+    let mut table = find_cells(&points, &params, &mut Monitor::unobserved());
+    populate_neighbours(&mut table, &mut Monitor::unobserved());
+    for index_min_points in [index_min_points, usize::MAX] {
+        let mut table = table.clone();
+        label_points_with_index(&mut table, &points, &params, index_min_points, &mut Monitor::unobserved());
+        for cell in table.values() {
+            for s_point in &cell.points {
+                let point = &points[s_point.index];
+                let in_range = points.iter().filter(|q| euclidean_distance(point, q) <= epsilon).count();
+                proptest::prop_assert_eq!(s_point.is_core, in_range >= min_pts);
+            }
+        }
+    }
+    Ok(())
+}
+
+proptest::proptest! {
+    #[test]
+    fn core_points_2d_prop(points in proptest::collection::vec(proptest::array::uniform2(-3.0..3.0), 1..150), epsilon in 0.2..1.5, min_pts in 2_usize..12, index_min_points in 1_usize..40) {
+        check_core_points(points, epsilon, min_pts, index_min_points)?;
+    }
+
+    #[test]
+    fn core_points_4d_prop(points in proptest::collection::vec(proptest::array::uniform4(-2.0..2.0), 1..150), epsilon in 0.5..2.0, min_pts in 2_usize..12, index_min_points in 1_usize..40) {
+        check_core_points(points, epsilon, min_pts, index_min_points)?;
+    }
+}
@@ -1,10 +1,12 @@
 use std::fs::File;
-use std::io::{Write};
+use std::io::{Write, BufWriter};
 use std::io::{self, BufRead};
 use std::path::{Path};
-use crate::utils::{DBSCANParams, Point, DBSCANResult, VectorDBSCANResult, array_res_to_vector_res};
+use std::str::FromStr;
+use crate::utils::{DBSCANParams, Point, DBSCANResult, VectorDBSCANResult, VectorPoint, DBSCANLabels, PointRole};
 
-const PALETTE_ARR : [[u8; 3];64] = [
+/// High contrast RGB colors used to draw the different clusters. Noise points use the color at index `0`.
+pub(crate) const PALETTE_ARR : [[u8; 3];64] = [
     [0, 0, 0],
     [1, 0, 103],
     [213, 255, 0],
@@ -148,8 +150,7 @@ where P: AsRef<Path>, {
                 if line.trim().is_empty() {
                     continue;
                 }
-                let mut p_i = 0;
-                for val in line.split_whitespace() {
+                for (p_i, val) in line.split_whitespace().enumerate() {
                     match val.parse() {
                         Ok(converted) => {
                             if p_i >= D {
@@ -161,7 +162,6 @@ where P: AsRef<Path>, {
                             println!("An error occourred while reading a point: check your input file. {:?}",e);
                         }
                     }
-                    p_i += 1;
                 }
                 points.push(point);
             }
@@ -174,96 +174,485 @@ where P: AsRef<Path>, {
     points
 }
 
+/// Writes `points` to `file_name` in the format read by `read_vector_points_from_file` and `read_points_from_file`: 
+/// one point for each row, with the coordinates separated by a space. The coordinates are written with as many digits as
+/// needed to read back the same values.
+pub fn write_points<P, Q>(file_name: &P, points: &[Q]) -> io::Result<()>
+where P: AsRef<Path>, Q: AsRef<[f64]>, {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    for point in points {
+        let mut coords = point.as_ref().iter();
+        if let Some(first) = coords.next() {
+            write!(writer, "{}", first)?;
+        }
+        for coord in coords {
+            write!(writer, " {}", coord)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Reads all the points from a data file without prior knowledge of their dimensionality. 
+/// Points must be one for each row and their coordinates must be separated by whitespace, empty lines are skipped.
+/// Contrary to `read_points_from_file` this function does not panic and returns an error of kind `InvalidData` if a 
+/// coordinate can not be parsed or if the points do not all have the same number of components.
+pub fn read_vector_points_from_file<P>(file_name: &P) -> io::Result<Vec<VectorPoint>>
+where P: AsRef<Path>, {
+    let file = File::open(file_name)?;
+    let mut points : Vec<VectorPoint> = Vec::new();
+    for (line_i, line_res) in io::BufReader::new(file).lines().enumerate() {
+        let line = line_res?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut point : VectorPoint = Vec::with_capacity(points.first().map_or(0, |p| p.len()));
+        for val in line.split_whitespace() {
+            match val.parse() {
+                Ok(converted) => point.push(converted),
+                Err(e) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: could not parse {:?}: {}", line_i + 1, val, e)));
+                }
+            }
+        }
+        if let Some(first) = points.first() {
+            if first.len() != point.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: expected {} components but {} were found", line_i + 1, first.len(), point.len())));
+            }
+        }
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// Reads the points of a data file one line at a time, without keeping them in memory. It is meant for files that are
+/// too large to be read with `read_points_from_file`, for example to cluster them with `out_of_core::cluster_out_of_core`.
+/// Empty lines are skipped. The iterator yields an error of kind `InvalidData` if a coordinate can not be parsed or if a
+/// point does not have `D` components.
+pub fn stream_points_from_file<P, const D: usize>(file_name: &P) -> io::Result<impl Iterator<Item = io::Result<Point<D>>>>
+where P: AsRef<Path>, {
+    let lines = io::BufReader::new(File::open(file_name)?).lines();
+    Ok(lines.enumerate().filter_map(|(line_i, line_res)| {
+        let line = match line_res {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e))
+        };
+        if line.trim().is_empty() {
+            return None;
+        }
+        Some(parse_point(&line).map_err(|message| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_i + 1, message))))
+    }))
+}
+
+/// Parses a line with exactly `D` whitespace separated coordinates
+fn parse_point<const D: usize>(line: &str) -> Result<Point<D>, String> {
+    let mut point : Point<D> = [0.0; D];
+    let mut components = 0;
+    for val in line.split_whitespace() {
+        if components < D {
+            point[components] = val.parse().map_err(|e| format!("could not parse {:?}: {}", val, e))?;
+        }
+        components += 1;
+    }
+    if components != D {
+        return Err(format!("expected {} components but {} were found", D, components));
+    }
+    Ok(point)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The formats in which labeled points can be written by `write_labels`
+pub enum OutputFormat {
+    /// Comma separated values: one row per point with its coordinates followed by `cluster_id` and `role`
+    Csv,
+    /// Same as `Csv` but with tab separated values
+    Tsv,
+    /// One JSON object per line per point, with the fields `point`, `cluster_id` and `role`
+    JsonLines,
+    /// A single JSON object that summarizes the clusters found, without the single points
+    Summary
+}
+
+impl OutputFormat {
+    /// Guesses the output format from the extension of `file_name`: `.csv`, `.tsv`, `.jsonl` and `.json`
+    /// are mapped respectively to `Csv`, `Tsv`, `JsonLines` and `Summary`.
+    pub fn from_extension<P>(file_name: &P) -> Option<OutputFormat>
+    where P: AsRef<Path>, {
+        let extension = file_name.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        extension.parse().ok()
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "jsonl" | "json-lines" => Ok(OutputFormat::JsonLines),
+            "json" | "summary" => Ok(OutputFormat::Summary),
+            _ => Err(format!("unknown output format {:?}, expected one of csv, tsv, jsonl, summary", s))
+        }
+    }
+}
+
+/// Writes the points in `points` along with their labels in `labels` to `file_name` using the format `format`.
+/// The label at index `i` must refer to the `i`-th point. Points can be represented either as arrays or as vectors.
+/// 
+/// # Parameters
+/// * `file_name`: the path to the file that will be written;
+/// * `points`: the points given in input to the approximate DBSCAN algorithm, in the same order;
+/// * `labels`: the labels found by the approximate DBSCAN algorithm for `points`;
+/// * `format`: the format of the output file, see `OutputFormat`.
+pub fn write_labels<P, Q>(file_name: &P, points: &[Q], labels: &DBSCANLabels, format: OutputFormat) -> io::Result<()>
+where P: AsRef<Path>, Q: AsRef<[f64]>, {
+    if points.len() != labels.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} points were given but there are {} labels", points.len(), labels.len())));
+    }
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    match format {
+        OutputFormat::Csv => write_labels_delimited(&mut writer, points, labels, ',')?,
+        OutputFormat::Tsv => write_labels_delimited(&mut writer, points, labels, '\t')?,
+        OutputFormat::JsonLines => write_labels_json_lines(&mut writer, points, labels)?,
+        OutputFormat::Summary => write_clusters_summary(&mut writer, points, labels)?
+    }
+    writer.flush()
+}
+
+fn write_labels_delimited<W: Write, Q: AsRef<[f64]>>(writer: &mut W, points: &[Q], labels: &DBSCANLabels, delimiter: char) -> io::Result<()> {
+    let dimensionality = points.first().map_or(0, |p| p.as_ref().len());
+    for i in 0..dimensionality {
+        write!(writer, "x{}{}", i, delimiter)?;
+    }
+    writeln!(writer, "cluster_id{}role", delimiter)?;
+    for (point, label) in points.iter().zip(labels.iter()) {
+        for coord in point.as_ref() {
+            write!(writer, "{}{}", coord, delimiter)?;
+        }
+        writeln!(writer, "{}{}{}", label.cluster_id, delimiter, label.role.as_str())?;
+    }
+    Ok(())
+}
+
+fn write_labels_json_lines<W: Write, Q: AsRef<[f64]>>(writer: &mut W, points: &[Q], labels: &DBSCANLabels) -> io::Result<()> {
+    for (point, label) in points.iter().zip(labels.iter()) {
+        write!(writer, "{{\"point\":")?;
+        write_json_array(writer, point.as_ref())?;
+        writeln!(writer, ",\"cluster_id\":{},\"role\":\"{}\"}}", label.cluster_id, label.role.as_str())?;
+    }
+    Ok(())
+}
+
+/// Writes a single JSON object with the total number of points, the number of noise points and, for each cluster,
+/// its index, its size, the number of its core and border points and its centroid.
+fn write_clusters_summary<W: Write, Q: AsRef<[f64]>>(writer: &mut W, points: &[Q], labels: &DBSCANLabels) -> io::Result<()> {
+    let dimensionality = points.first().map_or(0, |p| p.as_ref().len());
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+    // for each cluster (noise included at index 0): core count, border count and coordinates sum
+    let mut core_counts = vec![0_usize; clusters_count + 1];
+    let mut border_counts = vec![0_usize; clusters_count + 1];
+    let mut sums = vec![vec![0.0_f64; dimensionality]; clusters_count + 1];
+    for (point, label) in points.iter().zip(labels.iter()) {
+        match label.role {
+            PointRole::Core => core_counts[label.cluster_id] += 1,
+            PointRole::Border => border_counts[label.cluster_id] += 1,
+            PointRole::Noise => {}
+        }
+        for (sum, coord) in sums[label.cluster_id].iter_mut().zip(point.as_ref()) {
+            *sum += coord;
+        }
+    }
+    let noise_count = labels.iter().filter(|l| l.role == PointRole::Noise).count();
+    write!(writer, "{{\"points\":{},\"dimensionality\":{},\"noise\":{},\"clusters\":[", points.len(), dimensionality, noise_count)?;
+    for i_cluster in 1..=clusters_count {
+        let size = core_counts[i_cluster] + border_counts[i_cluster];
+        let centroid : Vec<f64> = sums[i_cluster].iter().map(|s| s / size as f64).collect();
+        if i_cluster > 1 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "{{\"id\":{},\"size\":{},\"core\":{},\"border\":{},\"centroid\":", i_cluster, size, core_counts[i_cluster], border_counts[i_cluster])?;
+        write_json_array(writer, &centroid)?;
+        write!(writer, "}}")?;
+    }
+    writeln!(writer, "]}}")
+}
+
+/// JSON has no representation for non finite numbers, so they are written as `null`
+fn write_json_array<W: Write>(writer: &mut W, values: &[f64]) -> io::Result<()> {
+    write!(writer, "[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        if value.is_finite() {
+            write!(writer, "{}", value)?;
+        } else {
+            write!(writer, "null")?;
+        }
+    }
+    write!(writer, "]")
+}
+
+/// Configuration of the BMP writers `write_to_bmp` and `write_to_bmp_vec`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BmpWriterConfig {
+    /// The index of the coordinate that holds the row of the pixel, with row `0` being the top one
+    pub row_index: usize,
+    /// The index of the coordinate that holds the column of the pixel
+    pub column_index: usize,
+    /// The color of the pixels that are not in the result
+    pub background: [u8; 3]
+}
+
+impl Default for BmpWriterConfig {
+    /// Rows and columns are the first two coordinates, as in the points built by `segmentation::image_to_points`,
+    /// and the background is white
+    fn default() -> BmpWriterConfig {
+        BmpWriterConfig {
+            row_index: 0,
+            column_index: 1,
+            background: [255, 255, 255]
+        }
+    }
+}
+
 /// Same as `write_to_bmp_vec` but takes in input a DBSCANResult where each point is a fixed length array.
-pub fn write_to_bmp<P, const D: usize>(file_name: &P,res: &DBSCANResult<D>)
+pub fn write_to_bmp<P, const D: usize>(file_name: &P, res: &DBSCANResult<D>, config: &BmpWriterConfig) -> io::Result<()>
 where P: AsRef<Path>, {
-    write_to_bmp_vec(file_name, &array_res_to_vector_res(res.clone()), D);
+    write_bmp(file_name, &result_to_image(res, config)?)
 }
 
 
 /// Writes the clusterized result to a bmp image using high contrast colors for the different clusters. 
-/// This assumes that the clusterized data comes from a 24 bit BMP image and writes `file_name` as a 24 bit BMP image.
-/// The clusterized points must have 5 coordinates and they must be in this order to have a valid result:
-/// row column B G R
+/// Each point is a pixel of the image, whose row and column are read from the coordinates at indexes `config.row_index`
+/// and `config.column_index` and rounded to the nearest integer. The image is as big as needed to contain the pixel
+/// with the highest row and column, and all the pixels without a point are colored with `config.background`.
+/// Noise points are colored black. If a pixel appears more than once, the cluster with the lowest index wins.
 /// 
 /// # Parameters
 /// * `file_name`: the path to the file where the bmp will be written;
-/// * res: the result of the approximate DBSCAN algorithm where each point is represented as a vector. If this is not your
+/// * `res`: the result of the approximate DBSCAN algorithm where each point is represented as a vector. If this is not your
 ///   type of result check `write_to_bmp`;
-/// * dimensionality: the number of components of the points in res.
-///
-pub fn write_to_bmp_vec<P>(file_name: &P,res: &VectorDBSCANResult, dimensionality: usize)
+/// * `config`: where to find the pixels coordinates in each point and the background color.
+/// 
+/// # Errors
+/// 
+/// Returns an error of kind `InvalidInput` if a point does not have the coordinates at `config.row_index` or `config.column_index`
+/// or if they are negative, and any error that occours while writing the file.
+pub fn write_to_bmp_vec<P>(file_name: &P, res: &VectorDBSCANResult, config: &BmpWriterConfig) -> io::Result<()>
 where P: AsRef<Path>, {
-    if dimensionality != 5 {
-        println!("Points are required to have 5 dimensionalities to be printed to bmp");
-        return;
-    }
-    let mut gp_file = match File::create(file_name) {
-        Err(why) => panic!("couldn't create {}:", why),
-        Ok(file) => file,
-    };
-    let height = res.iter().map(|x| x.iter().map(|x| x[0] as i64).max().unwrap_or(0)).max().unwrap() + 1;
-    let width = res.iter().map(|x| x.iter().map(|x| x[1] as i64).max().unwrap_or(0)).max().unwrap() + 1;
-    println!("w{} h {}",width, height);
-    let tot_pts : usize = (width * height) as usize;
-    let map_size = 3 * tot_pts;
-    let mut tot_size = OFFSET + map_size as u32;
-    let padding = (width % 4) as u32;
-    if padding != 0 {
-        println!("warning: BMP needs padding");
-        tot_size += padding  * height as u32 * 3;
-    }
-
-    // first insert into all_points all noise points adding an additional coordinate to represent their cluster number (0)
-    let mut all_points : Vec<Vec<i64>>= {
-        res[0].iter().map(|x| {let mut y : Vec<i64> = x.iter().map(|x| *x as i64).collect(); y.push(0); y}).collect()
-    };
-    // then append all the points from the oher clusters, adding a coordinate to represent their cluster number
-    for i in 1..res.len() {
-        all_points.append(&mut ({
-                res[i].iter().map(|x| {let mut y: Vec<i64> = x.iter().map(|x| *x as i64).collect(); y.push(i as i64); y}).collect()
+    write_bmp(file_name, &result_to_image(res, config)?)
+}
+
+/// The largest number of pixels of the images drawn from the clusters, about 768 MiB of pixel data
+const MAX_IMAGE_PIXELS: usize = 1 << 28;
+
+/// Builds the image drawn by `write_to_bmp_vec` from the clusters in `res`
+fn result_to_image<Q: AsRef<[f64]>>(res: &[Vec<Q>], config: &BmpWriterConfig) -> io::Result<RgbImage> {
+    let mut pixels : Vec<(usize, usize, usize)> = Vec::with_capacity(res.iter().map(|c| c.len()).sum());
+    for (i_cluster, cluster) in res.iter().enumerate() {
+        for point in cluster {
+            let point = point.as_ref();
+            let (row, column) = match (point.get(config.row_index), point.get(config.column_index)) {
+                (Some(row), Some(column)) => (row.round(), column.round()),
+                _ => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                        "points with {} coordinates have no row at index {} or no column at index {}", point.len(), config.row_index, config.column_index
+                    )));
+                }
+            };
+            // the sizes of a BMP image are stored as `i32`, so larger coordinates could not be written anyway
+            if !(row >= 0.0 && column >= 0.0 && row < i32::MAX as f64 && column < i32::MAX as f64) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid pixel coordinates ({}, {})", row, column)));
             }
-        ));
-    } 
-    all_points.sort_by(|a,b| a[1].cmp(&b[1]));
-    all_points.sort_by(|a,b| a[0].cmp(&b[0]));
-    all_points.dedup_by(|a,b| a[0] == b[0] && a[1] == b[1]);
+            pixels.push((row as usize, column as usize, i_cluster));
+        }
+    }
+    let height = pixels.iter().map(|p| p.0 + 1).max().unwrap_or(0);
+    let width = pixels.iter().map(|p| p.1 + 1).max().unwrap_or(0);
+    if width.checked_mul(height).map_or(true, |size| size > MAX_IMAGE_PIXELS) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "an image of {} by {} pixels is larger than the {} pixels supported", width, height, MAX_IMAGE_PIXELS
+        )));
+    }
+    let mut image = RgbImage::new(width, height, config.background);
+    // clusters are drawn from the last one so that the ones with a lower index are drawn on top 
+    for (row, column, i_cluster) in pixels.into_iter().rev() {
+        image.set_pixel(row, column, PALETTE_ARR[i_cluster % PALETTE_ARR.len()]);
+    }
+    Ok(image)
+}
+
+/// An image with 8 bit RGB pixels stored row by row, starting from the top row
+#[derive(Clone, PartialEq, Debug)]
+pub struct RgbImage {
+    /// The number of pixels in each row
+    pub width: usize,
+    /// The number of rows
+    pub height: usize,
+    /// The `width * height` pixels of the image, stored as `[R, G, B]`
+    pub pixels: Vec<[u8; 3]>
+}
 
+impl RgbImage {
+    /// Creates an image of the given size where all the pixels have color `background`
+    pub fn new(width: usize, height: usize, background: [u8; 3]) -> RgbImage {
+        RgbImage {
+            width,
+            height,
+            pixels: vec![background; width * height]
+        }
+    }
+
+    /// Gets the color of the pixel at row `row` and column `column`. Row `0` is the top one.
+    pub fn get_pixel(&self, row: usize, column: usize) -> [u8; 3] {
+        self.pixels[row * self.width + column]
+    }
+
+    /// Sets the color of the pixel at row `row` and column `column`. Row `0` is the top one.
+    pub fn set_pixel(&mut self, row: usize, column: usize, color: [u8; 3]) {
+        self.pixels[row * self.width + column] = color;
+    }
+}
+
+fn invalid_image(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads an uncompressed 24 or 32 bit BMP image. Both bottom-up and top-down images are supported.
+pub fn read_bmp<P>(file_name: &P) -> io::Result<RgbImage>
+where P: AsRef<Path>, {
+    let data = std::fs::read(file_name)?;
+    let read_u16 = |at: usize| data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let read_u32 = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    if data.len() < OFFSET as usize || data[0..2] != [0x42, 0x4D] {
+        return Err(invalid_image("not a BMP file"));
+    }
+    let pixels_offset = read_u32(10).unwrap() as usize;
+    let width = read_u32(18).unwrap() as i32;
+    let height = read_u32(22).unwrap() as i32;
+    let bits_per_pixel = read_u16(28).unwrap();
+    let compression = read_u32(30).unwrap();
+    if compression != COMPRESSION_METHOD || (bits_per_pixel != 24 && bits_per_pixel != 32) {
+        return Err(invalid_image("only uncompressed 24 and 32 bit BMP images are supported"));
+    }
+    if width <= 0 || height == 0 {
+        return Err(invalid_image("invalid BMP image size"));
+    }
+    let width = width as usize;
+    let top_down = height < 0;
+    let height = height.unsigned_abs() as usize;
+    let bytes_per_pixel = bits_per_pixel as usize / 8;
+    // the header values are not trusted: a crafted size could overflow the end of the pixel data
+    let row_size = width.checked_mul(bytes_per_pixel).map(|row_bytes| row_bytes.div_ceil(4) * 4)
+        .ok_or_else(|| invalid_image("invalid BMP image size"))?;
+    let pixels_end = row_size.checked_mul(height).and_then(|map_size| map_size.checked_add(pixels_offset))
+        .ok_or_else(|| invalid_image("invalid BMP image size"))?;
+    if data.len() < pixels_end {
+        return Err(invalid_image("BMP pixel data is truncated"));
+    }
+    // all the offsets below are smaller than `pixels_end`, so they can not overflow
+    let mut image = RgbImage::new(width, height, [0, 0, 0]);
+    for file_row in 0..height {
+        let row = if top_down { file_row } else { height - 1 - file_row };
+        let row_start = pixels_offset + file_row * row_size;
+        for column in 0..width {
+            let at = row_start + column * bytes_per_pixel;
+            image.set_pixel(row, column, [data[at + 2], data[at + 1], data[at]]);
+        }
+    }
+    Ok(image)
+}
+
+/// Writes `image` to `file_name` as a bottom-up 24 bit BMP image
+pub fn write_bmp<P>(file_name: &P, image: &RgbImage) -> io::Result<()>
+where P: AsRef<Path>, {
+    let row_size = bmp_row_size(image.width, 3);
+    let padding = row_size - 3 * image.width;
+    let map_size = row_size * image.height;
+    let tot_size = OFFSET as usize + map_size;
+    if tot_size > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image too big to be written as BMP"));
+    }
+    let mut gp_file = BufWriter::new(File::create(file_name)?);
     //bitmap header
-    gp_file.write_all(&[0x42,0x4D]).unwrap();
+    gp_file.write_all(&[0x42,0x4D])?;
     //header
-    gp_file.write_all(&(tot_size as u32).to_le_bytes()).unwrap();
-    gp_file.write_all(&[0;4]).unwrap();
-    gp_file.write_all(&(OFFSET).to_le_bytes()).unwrap();
+    gp_file.write_all(&(tot_size as u32).to_le_bytes())?;
+    gp_file.write_all(&[0;4])?;
+    gp_file.write_all(&(OFFSET).to_le_bytes())?;
     //dib header
-    gp_file.write_all(&(HEADER_SIZE).to_le_bytes()).unwrap();
-    gp_file.write_all(&(width as u32).to_le_bytes()).unwrap();
-    gp_file.write_all(&(height as u32).to_le_bytes()).unwrap();
-    gp_file.write_all(&(PLANES).to_le_bytes()).unwrap();
-    gp_file.write_all(&(BITS_PER_PIXEL).to_le_bytes()).unwrap();
-    gp_file.write_all(&(COMPRESSION_METHOD).to_le_bytes()).unwrap();
-    gp_file.write_all(&(map_size as u32).to_le_bytes()).unwrap();
-    gp_file.write_all(&(H_RES).to_le_bytes()).unwrap();
-    gp_file.write_all(&(W_RES).to_le_bytes()).unwrap();
-    gp_file.write_all(&(COLORS_COUNT).to_le_bytes()).unwrap();
-    gp_file.write_all(&(IMPORTANT_COLORS).to_le_bytes()).unwrap();
-
-    let mut col_counter = 0;
-    for point in all_points {
-        // coordinate at index dimensionality is the cluster index
-        let color = PALETTE_ARR[(point[dimensionality] as usize % PALETTE_ARR.len()) as usize];
-        gp_file.write_all(&[color[2], color[1], color[0]]).unwrap();
-        col_counter += 1;
-        if col_counter == width {
-            for _i in 0..padding {
-                gp_file.write_all(&[0]).unwrap();
-            }
-            col_counter = 0;
+    gp_file.write_all(&(HEADER_SIZE).to_le_bytes())?;
+    gp_file.write_all(&(image.width as u32).to_le_bytes())?;
+    gp_file.write_all(&(image.height as u32).to_le_bytes())?;
+    gp_file.write_all(&(PLANES).to_le_bytes())?;
+    gp_file.write_all(&(BITS_PER_PIXEL).to_le_bytes())?;
+    gp_file.write_all(&(COMPRESSION_METHOD).to_le_bytes())?;
+    gp_file.write_all(&(map_size as u32).to_le_bytes())?;
+    gp_file.write_all(&(H_RES).to_le_bytes())?;
+    gp_file.write_all(&(W_RES).to_le_bytes())?;
+    gp_file.write_all(&(COLORS_COUNT).to_le_bytes())?;
+    gp_file.write_all(&(IMPORTANT_COLORS).to_le_bytes())?;
+    // rows are stored from the bottom one to the top one
+    for row in (0..image.height).rev() {
+        for column in 0..image.width {
+            let color = image.get_pixel(row, column);
+            gp_file.write_all(&[color[2], color[1], color[0]])?;
         }
+        gp_file.write_all(&[0, 0, 0][..padding])?;
     }
+    gp_file.flush()
+}
+
+/// Each row of a BMP image must be padded to a multiple of 4 bytes
+fn bmp_row_size(width: usize, bytes_per_pixel: usize) -> usize {
+    (width * bytes_per_pixel).div_ceil(4) * 4
+}
 
+/// Reads an 8 bit PNG image, dropping the alpha channel if present. Requires the `png` feature.
+#[cfg(feature = "png")]
+pub fn read_png<P>(file_name: &P) -> io::Result<RgbImage>
+where P: AsRef<Path>, {
+    let mut decoder = png::Decoder::new(File::open(file_name)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| invalid_image(&e.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| invalid_image(&e.to_string()))?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err(invalid_image("unexpected indexed PNG after expansion"))
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut image = RgbImage::new(width, height, [0, 0, 0]);
+    for row in 0..height {
+        for column in 0..width {
+            let at = row * info.line_size + column * channels;
+            let color = if channels < 3 {
+                [buffer[at]; 3]
+            } else {
+                [buffer[at], buffer[at + 1], buffer[at + 2]]
+            };
+            image.set_pixel(row, column, color);
+        }
+    }
+    Ok(image)
 }
 
+/// Reads a BMP image, or a PNG image if the `png` feature is enabled, choosing the format from the extension of `file_name`
+pub fn read_image<P>(file_name: &P) -> io::Result<RgbImage>
+where P: AsRef<Path>, {
+    let extension = file_name.as_ref().extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("bmp") => read_bmp(file_name),
+        #[cfg(feature = "png")]
+        Some("png") => read_png(file_name),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported image format"))
+    }
+}
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -12,4 +12,168 @@ fn read_file_test(){
     assert_eq!(params.dimensionality,3);
     assert_eq!(params.cardinality, 20000);
 
-}
\ No newline at end of file
+}
+#[test]
+fn read_vector_points_test(){
+    let points = read_vector_points_from_file(&"./datasets/out_test_2.txt").unwrap();
+    assert_eq!(points.len(), 20000);
+    assert!(points.iter().all(|p| p.len() == 3));
+    assert!(read_vector_points_from_file(&"./datasets/not_there.txt").is_err());
+}
+
+#[test]
+fn write_points_test(){
+    let points = vec![vec![1.0, -2.5, 0.1], vec![1e-20, 3.0, 123456.789]];
+    let file_name = std::env::temp_dir().join("appr_dbscan_write_points_test.txt");
+    write_points(&file_name, &points).unwrap();
+    assert_eq!(std::fs::read_to_string(&file_name).unwrap(), "1 -2.5 0.1\n0.00000000000000000001 3 123456.789\n");
+    assert_eq!(read_vector_points_from_file(&file_name).unwrap(), points);
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+fn output_format_test(){
+    assert_eq!("CSV".parse::<OutputFormat>(), Ok(OutputFormat::Csv));
+    assert_eq!("jsonl".parse::<OutputFormat>(), Ok(OutputFormat::JsonLines));
+    assert!("xml".parse::<OutputFormat>().is_err());
+    assert_eq!(OutputFormat::from_extension(&"out/res.tsv"), Some(OutputFormat::Tsv));
+    assert_eq!(OutputFormat::from_extension(&"res.json"), Some(OutputFormat::Summary));
+    assert_eq!(OutputFormat::from_extension(&"res"), None);
+}
+
+fn labeled_points() -> (Vec<Point<2>>, DBSCANLabels) {
+    use crate::utils::PointLabel;
+    let points = vec![[0.0, 1.5], [1.0, 2.0], [-3.0, 0.25]];
+    let labels = vec![
+        PointLabel{cluster_id: 1, role: PointRole::Core},
+        PointLabel{cluster_id: 1, role: PointRole::Border},
+        PointLabel{cluster_id: 0, role: PointRole::Noise},
+    ];
+    (points, labels)
+}
+
+#[test]
+fn write_labels_delimited_test(){
+    let (points, labels) = labeled_points();
+    let mut out : Vec<u8> = Vec::new();
+    write_labels_delimited(&mut out, &points, &labels, ',').unwrap();
+    let expected = "x0,x1,cluster_id,role\n0,1.5,1,core\n1,2,1,border\n-3,0.25,0,noise\n";
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+    let mut out : Vec<u8> = Vec::new();
+    write_labels_delimited(&mut out, &points, &labels, '\t').unwrap();
+    assert!(String::from_utf8(out).unwrap().starts_with("x0\tx1\tcluster_id\trole\n0\t1.5\t1\tcore\n"));
+}
+
+#[test]
+fn write_labels_json_test(){
+    let (points, labels) = labeled_points();
+    let mut out : Vec<u8> = Vec::new();
+    write_labels_json_lines(&mut out, &points, &labels).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.lines().count(), 3);
+    assert_eq!(out.lines().next().unwrap(), "{\"point\":[0,1.5],\"cluster_id\":1,\"role\":\"core\"}");
+    let mut out : Vec<u8> = Vec::new();
+    write_clusters_summary(&mut out, &points, &labels).unwrap();
+    let expected = "{\"points\":3,\"dimensionality\":2,\"noise\":1,\"clusters\":[{\"id\":1,\"size\":2,\"core\":1,\"border\":1,\"centroid\":[0.5,1.75]}]}\n";
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn write_labels_test(){
+    let (points, labels) = labeled_points();
+    let file_name = std::env::temp_dir().join("appr_dbscan_write_labels_test.csv");
+    write_labels(&file_name, &points, &labels, OutputFormat::Csv).unwrap();
+    let written = std::fs::read_to_string(&file_name).unwrap();
+    assert_eq!(written.lines().count(), 4);
+    assert!(write_labels(&file_name, &points[1..], &labels, OutputFormat::Csv).is_err());
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+fn bmp_round_trip_test(){
+    // widths that are not multiples of 4 need padding at the end of each row
+    for width in 1..6 {
+        let mut image = RgbImage::new(width, 3, [10, 20, 30]);
+        image.set_pixel(0, 0, [255, 0, 0]);
+        image.set_pixel(2, width - 1, [0, 0, 255]);
+        let file_name = std::env::temp_dir().join(format!("appr_dbscan_bmp_test_{}.bmp", width));
+        write_bmp(&file_name, &image).unwrap();
+        let file_len = std::fs::metadata(&file_name).unwrap().len() as usize;
+        assert_eq!(file_len, 54 + 3 * (3 * width).div_ceil(4) * 4);
+        assert_eq!(read_image(&file_name).unwrap(), image);
+        std::fs::remove_file(&file_name).unwrap();
+    }
+    assert!(read_bmp(&"./datasets/out_test_1.txt").is_err());
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn read_png_test(){
+    let file_name = std::env::temp_dir().join("appr_dbscan_png_test.png");
+    {
+        let file = File::create(&file_name).unwrap();
+        let mut encoder = png::Encoder::new(file, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 128, 255, 0]).unwrap();
+    }
+    let image = read_image(&file_name).unwrap();
+    assert_eq!(image.width, 2);
+    assert_eq!(image.height, 1);
+    assert_eq!(image.pixels, vec![[255, 0, 0], [0, 128, 255]]);
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+fn read_bmp_crafted_header_test(){
+    // a 32 bit header with the largest sizes and pixel offset, followed by no pixels
+    let mut data = vec![0_u8; 54];
+    data[0..2].copy_from_slice(b"BM");
+    data[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+    data[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+    data[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+    data[28..30].copy_from_slice(&32_u16.to_le_bytes());
+    let file_name = std::env::temp_dir().join("appr_dbscan_crafted_header_test.bmp");
+    std::fs::write(&file_name, &data).unwrap();
+    assert_eq!(read_bmp(&file_name).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    data[22..26].copy_from_slice(&2_i32.to_le_bytes());
+    data[18..22].copy_from_slice(&1_i32.to_le_bytes());
+    std::fs::write(&file_name, &data).unwrap();
+    assert_eq!(read_bmp(&file_name).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+fn write_to_bmp_test(){
+    let config = BmpWriterConfig{row_index: 2, column_index: 0, background: [1, 2, 3]};
+    // sparse pixels in a 3x5 image, with a border point in both clusters 1 and 2
+    let res : DBSCANResult<3> = vec![
+        vec![[0.0, 0.0, 0.0]],
+        vec![[4.0, 0.0, 2.0], [1.0, 7.0, 1.0]],
+        vec![[1.0, 7.0, 1.0], [2.2, 0.0, 1.9]],
+    ];
+    let image = result_to_image(&res, &config).unwrap();
+    assert_eq!(image.width, 5);
+    assert_eq!(image.height, 3);
+    assert_eq!(image.get_pixel(0, 0), PALETTE_ARR[0]);
+    assert_eq!(image.get_pixel(2, 4), PALETTE_ARR[1]);
+    assert_eq!(image.get_pixel(1, 1), PALETTE_ARR[1]);
+    assert_eq!(image.get_pixel(2, 2), PALETTE_ARR[2]);
+    assert_eq!(image.get_pixel(0, 1), [1, 2, 3]);
+    assert_eq!(image.pixels.iter().filter(|p| **p == [1, 2, 3]).count(), 11);
+
+    let file_name = std::env::temp_dir().join("appr_dbscan_write_to_bmp_test.bmp");
+    write_to_bmp(&file_name, &res, &config).unwrap();
+    assert_eq!(read_bmp(&file_name).unwrap(), image);
+    let bad_config = BmpWriterConfig{row_index: 3, ..config};
+    assert!(write_to_bmp(&file_name, &res, &bad_config).is_err());
+    let negative : VectorDBSCANResult = vec![vec![vec![-1.0, 0.0]]];
+    assert!(write_to_bmp_vec(&file_name, &negative, &BmpWriterConfig::default()).is_err());
+    for coordinates in [[f64::INFINITY, 0.0], [f64::NAN, 0.0], [1e300, 0.0], [100000.0, 100000.0]] {
+        let res : VectorDBSCANResult = vec![vec![coordinates.to_vec()]];
+        let error = write_to_bmp_vec(&file_name, &res, &BmpWriterConfig::default()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+    std::fs::remove_file(&file_name).unwrap();
+}
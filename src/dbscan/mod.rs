@@ -1,48 +1,118 @@
-use crate::cluster::{find_connected_components, assign_border_noise_points};
-use crate::cell::{find_cells, populate_neighbours};
+use crate::cluster::{find_connected_components, assign_border_noise_points, assign_cluster_indexes, label_all_points, NOISE_CLUSTER_INDEX};
+use crate::cell::{find_cells, populate_neighbours, CellId, CellTable};
 use crate::core_cell::{label_points,compute_adjacency_lists};
+use crate::progress::{Monitor, Stage, ProgressObserver, CancellationToken, Cancelled};
+use crate::stats::RunStats;
 use crate::utils::*;
-//use std::time::{Instant};
+use partitions::PartitionVec;
 
 /// Function that runs the approximate DBSCAN algorithm on the given set of points with the given parameters.
 /// 
 /// # Arguments
 /// 
-/// * `points` - A vector of `Point` elements to cluster.
+/// * `points` - The points to cluster, with coordinates of any `Float` type. They can be a slice or a vector of `Point` 
+///   elements, a `StridedPoints` view on a flat slice or, with the `ndarray` feature, an `ndarray::ArrayView2`. The points are 
+///   never copied while clustering, only when they are grouped in the result.
 /// * `params` - A reference to a `DBSCANParams` struct that holds the clustering parameters
 /// 
 /// # Return 
 /// 
 /// An element of type `DBSCANResult`, in which the first cluster contains the noise points. The total number of cluster then
 /// is one less than the length of the result returned. 
-pub fn approximate_dbscan<const D: usize>(points: Vec<Point<D>>, params: &DBSCANParams) -> DBSCANResult<D> {
-    //let tot = Instant::now();
-    //let now = Instant::now();
-    let mut base_cells = find_cells(points, params);
-    //println!("Found {} cells in {} ms",base_cells.len(),now.elapsed().as_millis());
-    //let now = Instant::now();
-    populate_neighbours(&mut base_cells);
-    //println!("Neighbours computed in {} ms",now.elapsed().as_millis());
-    //let now = Instant::now();
-    let mut part_vec = label_points(&mut base_cells, params);
-    //println!("Found {} core cells in {} ms",base_cells.values().filter(|x| x.is_core).count(),now.elapsed().as_millis());
-    //let now = Instant::now();
-    compute_adjacency_lists(&mut base_cells, params, &mut part_vec);
-    //println!("Graph built in {} ms",now.elapsed().as_millis());
-    //let now = Instant::now();
-    let mut result = find_connected_components(&mut base_cells, part_vec);
-    //println!("Found {} clusters in {} ms",result.len() - 1,now.elapsed().as_millis());
-    //let now = Instant::now();
-    assign_border_noise_points(&base_cells, &mut result, params);
-    //println!("Found {} noise points in {} ms",result[0].len(),now.elapsed().as_millis());
-    //println!(/*"Completed internal DBSCAN in */"{}"/* milliseconds"*/, tot.elapsed().as_millis());
-    /*println!("----------------------CLUSTERS---------------");
-    for i in 1..result.len(){
-        println!("Cluster #{}: {} points;",i,result[i].len());
-    }
-    println!("Cluster Noise: {} points;",result[0].len());
-    println!("---------------------------------------------");*/
-    result
+pub fn approximate_dbscan<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> DBSCANResult<D, F> {
+    run_approximate_dbscan(points, params, &mut Monitor::unobserved()).expect("an unobserved run can not be cancelled")
+}
+
+/// Same as `approximate_dbscan`, but sends the progress of each stage to `observer` and stops as soon as `token`
+/// is cancelled, returning `Err(Cancelled)`. The token is checked between the cells, see the `progress` module.
+pub fn approximate_dbscan_observed<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, observer: &mut dyn ProgressObserver, token: &CancellationToken) -> Result<DBSCANResult<D, F>, Cancelled> {
+    run_approximate_dbscan(points, params, &mut Monitor::new(observer, token))
+}
+
+/// Same as `approximate_dbscan`, but also returns some statistics about the run, such as the number of cells and the time
+/// taken by each stage. See `stats::RunStats`.
+pub fn approximate_dbscan_with_stats<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> (DBSCANResult<D, F>, RunStats) {
+    let mut monitor = Monitor::unobserved();
+    let result = run_approximate_dbscan(points, params, &mut monitor).expect("an unobserved run can not be cancelled");
+    (result, monitor.into_stats())
+}
+
+fn run_approximate_dbscan<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> Result<DBSCANResult<D, F>, Cancelled> {
+    let (mut base_cells, part_vec) = connect_core_cells(points, params, monitor)?;
+    monitor.start(Stage::FindComponents, base_cells.core_cells_count());
+    let mut result = find_connected_components(&mut base_cells, points, part_vec);
+    monitor.finish(result.len() - 1)?;
+    monitor.start(Stage::AssignBorderPoints, base_cells.len());
+    assign_border_noise_points(&base_cells, points, &mut result, params, monitor);
+    monitor.finish(result[NOISE_CLUSTER_INDEX].len())?;
+    Ok(result)
+}
+
+/// Function that runs the approximate DBSCAN algorithm on the given set of points with the given parameters and 
+/// labels each point with the cluster it belongs to instead of grouping the points in clusters.
+/// 
+/// # Arguments
+/// 
+/// * `points` - The points to cluster, in any of the collections accepted by `approximate_dbscan`. The points are never copied.
+/// * `params` - A reference to a `DBSCANParams` struct that holds the clustering parameters
+/// 
+/// # Return 
+/// 
+/// An element of type `DBSCANLabels` where the label at index `i` refers to the `i`-th point in `points`. Only the first
+/// `params.cardinality` points are clustered, so there is a label for each of the first `min(points.len(), params.cardinality)`
+/// points. The cluster indexes used in the labels are the same that would be used in a `DBSCANResult`, so noise points are labeled with cluster `0`.
+pub fn approximate_dbscan_labels<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> DBSCANLabels {
+    run_approximate_dbscan_labels(points, params, &mut Monitor::unobserved()).expect("an unobserved run can not be cancelled")
+}
+
+/// Same as `approximate_dbscan_labels`, but sends the progress of each stage to `observer` and stops as soon as `token`
+/// is cancelled, returning `Err(Cancelled)`
+pub fn approximate_dbscan_labels_observed<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, observer: &mut dyn ProgressObserver, token: &CancellationToken) -> Result<DBSCANLabels, Cancelled> {
+    run_approximate_dbscan_labels(points, params, &mut Monitor::new(observer, token))
+}
+
+/// Same as `approximate_dbscan_labels`, but also returns some statistics about the run. See `stats::RunStats`.
+pub fn approximate_dbscan_labels_with_stats<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> (DBSCANLabels, RunStats) {
+    let mut monitor = Monitor::unobserved();
+    let labels = run_approximate_dbscan_labels(points, params, &mut monitor).expect("an unobserved run can not be cancelled");
+    (labels, monitor.into_stats())
+}
+
+fn run_approximate_dbscan_labels<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> Result<DBSCANLabels, Cancelled> {
+    let (base_cells, _) = cluster_cells(points, params, monitor)?;
+    monitor.start(Stage::AssignBorderPoints, base_cells.len());
+    let labels = label_all_points(&base_cells, points, params, monitor);
+    monitor.finish(labels.iter().filter(|l| l.role == PointRole::Noise).count())?;
+    Ok(labels)
+}
+
+/// Runs the approximate DBSCAN algorithm up to the assignment of the cluster indexes to the core cells. 
+/// Returns the non empty cells, with the index of their cluster if they are core cells, and the number of clusters found.
+pub(crate) fn cluster_cells<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> Result<(CellTable<D>, usize), Cancelled> {
+    let (mut base_cells, part_vec) = connect_core_cells(points, params, monitor)?;
+    monitor.start(Stage::FindComponents, base_cells.core_cells_count());
+    let clusters_count = assign_cluster_indexes(&mut base_cells, part_vec);
+    monitor.finish(clusters_count)?;
+    Ok((base_cells, clusters_count))
+}
+
+/// Runs the stages of the approximate DBSCAN algorithm that find the core cells and put the connected ones in the 
+/// same set of the returned union-find structure
+fn connect_core_cells<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams, monitor: &mut Monitor) -> Result<(CellTable<D>, PartitionVec<CellId>), Cancelled> {
+    monitor.start(Stage::FindCells, points.len().min(params.cardinality));
+    let mut base_cells = find_cells(points, params, monitor);
+    monitor.finish(base_cells.len())?;
+    monitor.start(Stage::PopulateNeighbours, base_cells.len());
+    populate_neighbours(&mut base_cells, monitor);
+    monitor.finish(base_cells.values().map(|c| c.neighbour_cell_ids.len()).sum())?;
+    monitor.start(Stage::LabelPoints, base_cells.len());
+    let core_cells_count = label_points(&mut base_cells, points, params, monitor);
+    monitor.finish(core_cells_count)?;
+    monitor.set_tree_nodes_count(base_cells.values().filter(|c| c.is_core).map(|c| c.core_info.root.nodes_count()).sum());
+    monitor.start(Stage::ComputeAdjacency, base_cells.len());
+    let (part_vec, merges) = compute_adjacency_lists(&base_cells, points, params, monitor);
+    monitor.finish(merges)?;
+    Ok((base_cells, part_vec))
 }
 
 #[cfg(test)]
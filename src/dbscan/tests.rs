@@ -12,7 +12,7 @@ fn dbscan_test_1() {
     params.epsilon = 0.3;
     params.min_pts = 10;
     params.rho = 0.1;
-    let res = approximate_dbscan(points, &params);
+    let res = approximate_dbscan(&points, &params);
     let exp_noise = 0;
     assert_eq!(res[0].len(), exp_noise);
     let exp_clusters = 5;
@@ -28,9 +28,83 @@ fn dbscan_test_2() {
     params.epsilon = 0.3;
     params.min_pts = 10;
     params.rho = 0.1;
-    let res = approximate_dbscan(points, &params);
+    let res = approximate_dbscan(&points, &params);
     let exp_noise = 0;
     assert_eq!(res[0].len(), exp_noise);
     let exp_clusters = 8;
     assert_eq!(res.len(), exp_clusters + 1);
-}
\ No newline at end of file
+}
+#[test]
+fn dbscan_labels_test() {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    let labels = approximate_dbscan_labels(&points, &params);
+    assert_eq!(labels.len(), params.cardinality);
+    let res = approximate_dbscan(&points, &params);
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap();
+    assert_eq!(clusters_count, res.len() - 1);
+    let noise_count = labels.iter().filter(|l| l.role == PointRole::Noise).count();
+    assert_eq!(noise_count, res[0].len());
+    // cluster indexes depend on the order in which cells are visited, so only the totals are compared
+    let clustered_count : usize = res[1..].iter().map(|c| c.len()).sum();
+    assert_eq!(clustered_count, params.cardinality - noise_count);
+    for i in 1..res.len() {
+        assert!(labels.iter().any(|l| l.cluster_id == i && l.role == PointRole::Core));
+    }
+}
+
+#[test]
+fn dbscan_labels_cardinality_test() {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    let all_labels = approximate_dbscan_labels(&points, &params);
+    // only the first `cardinality` points are clustered
+    params.cardinality = 5000;
+    let labels = approximate_dbscan_labels(&points, &params);
+    assert_eq!(labels.len(), 5000);
+    assert_eq!(labels, approximate_dbscan_labels(&points[..5000], &params));
+    // and no label is added for the points missing
+    params.cardinality = 30000;
+    assert_eq!(approximate_dbscan_labels(&points, &params), all_labels);
+}
+
+#[test]
+fn dbscan_f32_test() {
+    let mut params = params_from_file(&"datasets/out_test_2.txt");
+    let points : Vec<Point<3>> = read_points_from_file(&"datasets/out_test_2.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    let points_f32 : Vec<Point<3, f32>> = points.iter().map(|p| p.map(|x| x as f32)).collect();
+    let res = approximate_dbscan(&points_f32, &params);
+    assert_eq!(res[0].len(), 0);
+    assert_eq!(res.len(), 8 + 1);
+    let labels = approximate_dbscan_labels(&points_f32, &params);
+    assert_eq!(labels.iter().map(|l| l.cluster_id).max().unwrap(), 8);
+}
+
+#[test]
+fn dbscan_strided_test() {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    // each point is followed by a value that is not a coordinate
+    let flat : Vec<f64> = points.iter().flat_map(|p| vec![p[0], p[1], -1.0]).collect();
+    let strided = StridedPoints::<f64, 2>::new(&flat, 3);
+    assert_eq!(strided.len(), params.cardinality);
+    let labels = approximate_dbscan_labels(&strided, &params);
+    let slice_labels = approximate_dbscan_labels(&points[..], &params);
+    for (label, slice_label) in labels.iter().zip(slice_labels.iter()) {
+        assert_eq!(label.role, slice_label.role);
+    }
+    let res = approximate_dbscan(&strided, &params);
+    assert_eq!(res.len(), 5 + 1);
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// For each pair of labels `(a[i], b[i])`, the number of elements that have it. Followed by the number of elements 
+/// with each label in `a` and in `b`
+type Contingency<A, B> = (HashMap<(A, B), usize>, HashMap<A, usize>, HashMap<B, usize>);
+
+/// Counts how many elements have each pair of labels `(a[i], b[i])`, along with the number of elements 
+/// with each label in `a` and in `b`
+fn contingency<A: Eq + Hash + Copy, B: Eq + Hash + Copy>(a: &[A], b: &[B]) -> Contingency<A, B> {
+    let mut pairs = HashMap::new();
+    let mut a_counts = HashMap::new();
+    let mut b_counts = HashMap::new();
+    for (a_label, b_label) in a.iter().zip(b.iter()) {
+        *pairs.entry((*a_label, *b_label)).or_insert(0) += 1;
+        *a_counts.entry(*a_label).or_insert(0) += 1;
+        *b_counts.entry(*b_label).or_insert(0) += 1;
+    }
+    (pairs, a_counts, b_counts)
+}
+
+fn pairs_count(n: usize) -> f64 {
+    (n as f64) * (n as f64 - 1.0) / 2.0
+}
+
+/// Computes the adjusted Rand index between two labelings of the same points. It is `1.0` when the two labelings 
+/// describe the same partition, whatever the actual label values, and close to `0.0` for unrelated labelings.
+/// Noise points are treated as a cluster of their own. Panics if the two labelings have different lengths.
+pub fn adjusted_rand_index<A: Eq + Hash + Copy, B: Eq + Hash + Copy>(a: &[A], b: &[B]) -> f64 {
+    assert_eq!(a.len(), b.len(), "the two labelings must have the same length");
+    let (pairs, a_counts, b_counts) = contingency(a, b);
+    let index : f64 = pairs.values().map(|n| pairs_count(*n)).sum();
+    let a_index : f64 = a_counts.values().map(|n| pairs_count(*n)).sum();
+    let b_index : f64 = b_counts.values().map(|n| pairs_count(*n)).sum();
+    let expected = a_index * b_index / pairs_count(a.len()).max(1.0);
+    let max = (a_index + b_index) / 2.0;
+    if max == expected {
+        return 1.0;
+    }
+    (index - expected) / (max - expected)
+}
+
+fn entropy<L>(counts: &HashMap<L, usize>, n: f64) -> f64 {
+    counts.values().map(|c| { let p = *c as f64 / n; -p * p.ln() }).sum()
+}
+
+/// Computes the mutual information between two labelings of the same points normalized by the arithmetic mean of 
+/// their entropies. It is `1.0` when the two labelings describe the same partition and `0.0` for independent labelings.
+/// Noise points are treated as a cluster of their own. Panics if the two labelings have different lengths.
+pub fn normalized_mutual_information<A: Eq + Hash + Copy, B: Eq + Hash + Copy>(a: &[A], b: &[B]) -> f64 {
+    assert_eq!(a.len(), b.len(), "the two labelings must have the same length");
+    let n = a.len() as f64;
+    let (pairs, a_counts, b_counts) = contingency(a, b);
+    let (a_entropy, b_entropy) = (entropy(&a_counts, n), entropy(&b_counts, n));
+    if a_entropy + b_entropy == 0.0 {
+        return 1.0;
+    }
+    let mutual_information : f64 = pairs.iter().map(|((a_label, b_label), c)| {
+        let c = *c as f64;
+        (c / n) * (c * n / (a_counts[a_label] as f64 * b_counts[b_label] as f64)).ln()
+    }).sum();
+    (mutual_information / ((a_entropy + b_entropy) / 2.0)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn adjusted_rand_index_test() {
+    let truth = [0, 0, 0, 1, 1, 1];
+    assert_eq!(adjusted_rand_index(&truth, &[5, 5, 5, 2, 2, 2]), 1.0);
+    assert_eq!(adjusted_rand_index(&truth, &truth), 1.0);
+    // value computed with scikit-learn
+    let ari = adjusted_rand_index(&truth, &[0, 0, 1, 1, 2, 2]);
+    assert!((ari - 0.24242424242424243).abs() < 1e-12, "{}", ari);
+    assert!(adjusted_rand_index(&truth, &[0, 1, 0, 1, 0, 1]) < 0.0);
+}
+
+#[test]
+fn normalized_mutual_information_test() {
+    let truth = [0, 0, 0, 1, 1, 1];
+    assert!((normalized_mutual_information(&truth, &[7, 7, 7, 3, 3, 3]) - 1.0).abs() < 1e-12);
+    // mutual information is 2ln(2)/3 while the entropies are ln(2) and ln(3)
+    let nmi = normalized_mutual_information(&truth, &[0, 0, 1, 1, 2, 2]);
+    assert!((nmi - 0.5158037429793889).abs() < 1e-12, "{}", nmi);
+    assert_eq!(normalized_mutual_information(&[1, 1, 1], &[2, 2, 2]), 1.0);
+}
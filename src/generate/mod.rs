@@ -0,0 +1,238 @@
+//! Seeded generators of synthetic datasets in any dimensionality, with the ground truth label of each point. The same
+//! seed always gives the same points, so the datasets can be used in tests and benchmarks in place of stored files.
+//! The labels of the clusters start from `0`, and the points that belong to no cluster have the label `NOISE_LABEL`.
+
+use crate::data_io::write_points;
+use crate::utils::VectorPoint;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// The ground truth label of the points that belong to no cluster
+pub const NOISE_LABEL: i64 = -1;
+
+/// The number of attempts made to place the center of a blob far enough from the others
+const CENTER_ATTEMPTS: usize = 1000;
+/// The distance kept between the centers of the blobs, in standard deviations, when there is room for it
+const CENTER_SEPARATION: f64 = 8.0;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+/// Points generated together with their ground truth labels, in the same order
+pub struct Dataset {
+    pub points: Vec<VectorPoint>,
+    pub labels: Vec<i64>
+}
+
+impl Dataset {
+    /// The number of clusters, noise excluded
+    pub fn clusters_count(&self) -> usize {
+        self.labels.iter().filter(|l| **l != NOISE_LABEL).max().map_or(0, |l| *l as usize + 1)
+    }
+
+    /// Adds `count` noise points spread uniformly in the smallest box that contains the points, labeled `NOISE_LABEL`
+    pub fn with_noise(mut self, count: usize, seed: u64) -> Dataset {
+        let dimensionality = self.points.first().map_or(0, |p| p.len());
+        let mut low = vec![f64::INFINITY; dimensionality];
+        let mut high = vec![f64::NEG_INFINITY; dimensionality];
+        for point in &self.points {
+            for (i, coord) in point.iter().enumerate() {
+                low[i] = low[i].min(*coord);
+                high[i] = high[i].max(*coord);
+            }
+        }
+        let mut rng = SeededRng::new(seed);
+        for _ in 0..count {
+            self.points.push(low.iter().zip(high.iter()).map(|(l, h)| l + rng.uniform() * (h - l)).collect());
+            self.labels.push(NOISE_LABEL);
+        }
+        self
+    }
+
+    /// Writes the points to `points_file` in the whitespace separated format of the files in `datasets/` and, if
+    /// `labels_file` is given, the labels to it, one for each line, as read by the `evaluate` command of the executable
+    pub fn save<P: AsRef<Path>, Q: AsRef<Path>>(&self, points_file: &P, labels_file: Option<&Q>) -> io::Result<()> {
+        write_points(points_file, &self.points)?;
+        if let Some(labels_file) = labels_file {
+            let mut writer = BufWriter::new(File::create(labels_file)?);
+            for label in &self.labels {
+                writeln!(writer, "{}", label)?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// A small permuted congruential generator, so that the datasets do not depend on the version of an external crate
+struct SeededRng {
+    state: u64
+}
+
+impl SeededRng {
+    fn new(seed: u64) -> SeededRng {
+        let mut rng = SeededRng{state: 0};
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// A uniform sample in `[0, 1)`
+    fn uniform(&mut self) -> f64 {
+        let bits = ((self.next_u32() as u64) << 21) ^ (self.next_u32() as u64 >> 11);
+        bits as f64 / (1_u64 << 53) as f64
+    }
+
+    /// A sample of the standard normal distribution, with the Box-Muller transform
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * self.uniform()).cos()
+    }
+
+    /// A point uniformly distributed on the sphere of radius `1`
+    fn unit_direction(&mut self, dimensionality: usize) -> VectorPoint {
+        assert!(dimensionality > 0, "a direction needs at least one dimension");
+        loop {
+            let direction : VectorPoint = (0..dimensionality).map(|_| self.normal()).collect();
+            let norm = direction.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                return direction.into_iter().map(|x| x / norm).collect();
+            }
+        }
+    }
+}
+
+/// `count` points in `clusters` gaussian blobs with standard deviation `std_dev` on each axis. The points are dealt to
+/// the blobs in turn, so the blobs have the same size up to one point. The centers are uniform in a box that grows with
+/// the number of blobs and are kept at least `8 * std_dev` apart when there is room for it.
+pub fn blobs(count: usize, dimensionality: usize, clusters: usize, std_dev: f64, seed: u64) -> Dataset {
+    assert!(clusters > 0, "at least one blob is needed");
+    assert!(dimensionality > 0, "the blobs need at least one dimension");
+    let mut rng = SeededRng::new(seed);
+    let half_side = 0.5 * CENTER_SEPARATION * std_dev * (clusters as f64).powf(1.0 / dimensionality as f64);
+    let mut centers : Vec<VectorPoint> = Vec::with_capacity(clusters);
+    while centers.len() < clusters {
+        let mut center = Vec::new();
+        for _ in 0..CENTER_ATTEMPTS {
+            center = (0..dimensionality).map(|_| (2.0 * rng.uniform() - 1.0) * half_side).collect();
+            let separated = centers.iter().all(|c| distance(c, &center) >= CENTER_SEPARATION * std_dev);
+            if separated {
+                break;
+            }
+        }
+        centers.push(center);
+    }
+    gaussian_clusters(count, &centers, &vec![std_dev; clusters], &mut rng)
+}
+
+/// `count` points on `rings` concentric spheres centered in the origin, with radii `1, 2, ..., rings`. Each coordinate is
+/// moved by a gaussian noise of standard deviation `std_dev`. Each ring gets a number of points proportional to its radius,
+/// so that the points are about as dense on all of them in two dimensions. The label of a point is the index of its ring,
+/// from the innermost one.
+pub fn rings(count: usize, dimensionality: usize, rings: usize, std_dev: f64, seed: u64) -> Dataset {
+    assert!(rings > 0, "at least one ring is needed");
+    assert!(dimensionality > 0, "the rings need at least one dimension");
+    let mut rng = SeededRng::new(seed);
+    let total_radius = (rings * (rings + 1) / 2) as f64;
+    let mut dataset = Dataset::default();
+    let mut generated = 0;
+    for ring in 0..rings {
+        let radius = (ring + 1) as f64;
+        let ring_count = if ring + 1 == rings {
+            count - generated
+        } else {
+            (count as f64 * radius / total_radius).round() as usize
+        };
+        for _ in 0..ring_count {
+            let point = rng.unit_direction(dimensionality).into_iter().map(|x| x * radius + std_dev * rng.normal()).collect();
+            dataset.points.push(point);
+            dataset.labels.push(ring as i64);
+        }
+        generated += ring_count;
+    }
+    dataset
+}
+
+/// `count` points on two interleaved half circles of radius `1`, labeled `0` and `1`, in the plane of the first two axes.
+/// Each coordinate is moved by a gaussian noise of standard deviation `std_dev`, so the points on the other axes are
+/// only noise. Panics if `dimensionality` is less than `2`.
+pub fn moons(count: usize, dimensionality: usize, std_dev: f64, seed: u64) -> Dataset {
+    assert!(dimensionality >= 2, "the moons need at least two dimensions");
+    let mut rng = SeededRng::new(seed);
+    let mut dataset = Dataset::default();
+    for i in 0..count {
+        let moon = i % 2;
+        let angle = PI * rng.uniform();
+        let mut point = vec![0.0; dimensionality];
+        if moon == 0 {
+            point[0] = angle.cos();
+            point[1] = angle.sin();
+        } else {
+            point[0] = 1.0 - angle.cos();
+            point[1] = 0.5 - angle.sin();
+        }
+        for coord in point.iter_mut() {
+            *coord += std_dev * rng.normal();
+        }
+        dataset.points.push(point);
+        dataset.labels.push(moon as i64);
+    }
+    dataset
+}
+
+/// `count` points spread uniformly in the box `[0, side)^dimensionality`, all labeled `NOISE_LABEL`
+pub fn uniform(count: usize, dimensionality: usize, side: f64, seed: u64) -> Dataset {
+    assert!(dimensionality > 0, "the points need at least one dimension");
+    let mut rng = SeededRng::new(seed);
+    Dataset{
+        points: (0..count).map(|_| (0..dimensionality).map(|_| rng.uniform() * side).collect()).collect(),
+        labels: vec![NOISE_LABEL; count]
+    }
+}
+
+/// `count` points in `clusters` gaussian blobs of the same size whose standard deviations double from one blob to the
+/// next, starting from `min_std_dev`. The centers lie on the first axis, with the blobs far enough apart that they do
+/// not overlap, so that a single value of `epsilon` can not separate all of them and keep them whole.
+pub fn varying_density(count: usize, dimensionality: usize, clusters: usize, min_std_dev: f64, seed: u64) -> Dataset {
+    assert!(clusters > 0, "at least one cluster is needed");
+    assert!(dimensionality > 0, "the clusters need at least one dimension");
+    let mut rng = SeededRng::new(seed);
+    let std_devs : Vec<f64> = (0..clusters).map(|i| min_std_dev * 2_f64.powi(i as i32)).collect();
+    let mut centers : Vec<VectorPoint> = Vec::with_capacity(clusters);
+    let mut position = 0.0;
+    for (i, std_dev) in std_devs.iter().enumerate() {
+        if i > 0 {
+            position += 0.5 * CENTER_SEPARATION * (std_devs[i - 1] + std_dev);
+        }
+        let mut center = vec![0.0; dimensionality];
+        center[0] = position;
+        centers.push(center);
+    }
+    gaussian_clusters(count, &centers, &std_devs, &mut rng)
+}
+
+/// `count` points dealt in turn to gaussian clusters with the given centers and standard deviations
+fn gaussian_clusters(count: usize, centers: &[VectorPoint], std_devs: &[f64], rng: &mut SeededRng) -> Dataset {
+    let mut dataset = Dataset{points: Vec::with_capacity(count), labels: Vec::with_capacity(count)};
+    for i in 0..count {
+        let cluster = i % centers.len();
+        dataset.points.push(centers[cluster].iter().map(|x| x + std_devs[cluster] * rng.normal()).collect());
+        dataset.labels.push(cluster as i64);
+    }
+    dataset
+}
+
+fn distance(p: &[f64], q: &[f64]) -> f64 {
+    p.iter().zip(q.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn seed_test() {
+    assert_eq!(blobs(100, 3, 4, 1.0, 7), blobs(100, 3, 4, 1.0, 7));
+    assert_ne!(blobs(100, 3, 4, 1.0, 7).points, blobs(100, 3, 4, 1.0, 8).points);
+    assert_eq!(moons(50, 2, 0.1, 1), moons(50, 2, 0.1, 1));
+    assert_ne!(uniform(50, 2, 1.0, 1).points, uniform(50, 2, 1.0, 2).points);
+}
+
+#[test]
+fn blobs_test() {
+    let dataset = blobs(1001, 5, 4, 0.5, 3);
+    assert_eq!(dataset.points.len(), 1001);
+    assert!(dataset.points.iter().all(|p| p.len() == 5));
+    assert_eq!(dataset.clusters_count(), 4);
+    assert_eq!(dataset.labels.iter().filter(|l| **l == 0).count(), 251);
+    assert_eq!(dataset.labels.iter().filter(|l| **l == 3).count(), 250);
+}
+
+#[test]
+fn rings_test() {
+    let dataset = rings(600, 3, 3, 0.0, 5);
+    assert_eq!(dataset.points.len(), 600);
+    assert_eq!(dataset.clusters_count(), 3);
+    assert_eq!(dataset.labels.iter().filter(|l| **l == 0).count(), 100);
+    assert_eq!(dataset.labels.iter().filter(|l| **l == 2).count(), 300);
+    for (point, label) in dataset.points.iter().zip(dataset.labels.iter()) {
+        let radius = point.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((radius - (*label + 1) as f64).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn moons_test() {
+    let dataset = moons(200, 4, 0.0, 11);
+    assert_eq!(dataset.clusters_count(), 2);
+    for (point, label) in dataset.points.iter().zip(dataset.labels.iter()) {
+        let center = if *label == 0 {[0.0, 0.0]} else {[1.0, 0.5]};
+        let radius = ((point[0] - center[0]).powi(2) + (point[1] - center[1]).powi(2)).sqrt();
+        assert!((radius - 1.0).abs() < 1e-9);
+        assert_eq!(&point[2..], &[0.0, 0.0]);
+    }
+}
+
+#[test]
+#[should_panic]
+fn moons_one_dimension_test() {
+    moons(10, 1, 0.1, 0);
+}
+
+#[test]
+fn zero_dimensions_test() {
+    // without the checks the rings would never find a direction and the others would make empty points
+    assert!(std::panic::catch_unwind(|| rings(10, 0, 2, 0.1, 0)).is_err());
+    assert!(std::panic::catch_unwind(|| blobs(10, 0, 2, 0.1, 0)).is_err());
+    assert!(std::panic::catch_unwind(|| uniform(10, 0, 1.0, 0)).is_err());
+    assert!(std::panic::catch_unwind(|| varying_density(10, 0, 2, 0.1, 0)).is_err());
+}
+
+#[test]
+fn uniform_and_noise_test() {
+    let dataset = uniform(300, 2, 4.0, 9);
+    assert_eq!(dataset.clusters_count(), 0);
+    assert!(dataset.points.iter().flatten().all(|x| (0.0..4.0).contains(x)));
+    let dataset = varying_density(300, 2, 3, 0.1, 2).with_noise(30, 4);
+    assert_eq!(dataset.points.len(), 330);
+    assert_eq!(dataset.clusters_count(), 3);
+    assert_eq!(dataset.labels.iter().filter(|l| **l == NOISE_LABEL).count(), 30);
+    let max_x = dataset.points[..300].iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max);
+    assert!(dataset.points[300..].iter().all(|p| p[0] <= max_x));
+}
+
+#[test]
+fn save_test() {
+    let dataset = blobs(20, 3, 2, 1.0, 1);
+    let points_file = std::env::temp_dir().join("appr_dbscan_generate_points_test.txt");
+    let labels_file = std::env::temp_dir().join("appr_dbscan_generate_labels_test.txt");
+    dataset.save(&points_file, Some(&labels_file)).unwrap();
+    assert_eq!(crate::data_io::read_vector_points_from_file(&points_file).unwrap(), dataset.points);
+    let labels : Vec<i64> = std::fs::read_to_string(&labels_file).unwrap().lines().map(|l| l.parse().unwrap()).collect();
+    assert_eq!(labels, dataset.labels);
+    std::fs::remove_file(&points_file).unwrap();
+    std::fs::remove_file(&labels_file).unwrap();
+}
@@ -0,0 +1,62 @@
+//! The separate stages of the approximate DBSCAN algorithm, exposed with the `internals` feature so that they can be
+//! timed one at a time by the benchmarks. The stages must run in the order of the functions below, each one on the 
+//! cells returned or updated by the previous ones. This module is not part of the stable API.
+
+use crate::cell::CellId;
+use crate::progress::Monitor;
+use crate::utils::*;
+use partitions::PartitionVec;
+
+pub use crate::cell::CellTable;
+
+/// The sets of connected core cells found by `compute_adjacency_lists`
+pub struct CoreCellSets {
+    part_vec: PartitionVec<CellId>,
+    merges: usize
+}
+
+impl CoreCellSets {
+    /// The number of union operations that joined two different sets
+    pub fn merges(&self) -> usize {
+        self.merges
+    }
+}
+
+/// Puts the points in the cells of the grid of side `epsilon / sqrt(D)`
+pub fn find_cells<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> CellTable<D> {
+    crate::cell::find_cells(points, params, &mut Monitor::unobserved())
+}
+
+/// Finds the neighbours of each non empty cell
+pub fn populate_neighbours<const D: usize>(cells: &mut CellTable<D>) {
+    crate::cell::populate_neighbours(cells, &mut Monitor::unobserved())
+}
+
+/// Finds the core points and the core cells and builds the range counting trees. Returns the number of core cells
+pub fn label_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, params: &DBSCANParams) -> usize {
+    crate::core_cell::label_points(cells, points, params, &mut Monitor::unobserved())
+}
+
+/// Same as `label_points`, but the core points of the sparse cells are found by measuring every point of their
+/// neighbour cells instead of reading the large ones through a kd-tree
+pub fn label_points_scanning_neighbours<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, params: &DBSCANParams) -> usize {
+    crate::core_cell::label_points_with_index(cells, points, params, usize::MAX, &mut Monitor::unobserved())
+}
+
+/// Joins the core cells that have core points at distance at most `epsilon`
+pub fn compute_adjacency_lists<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, points: &S, params: &DBSCANParams) -> CoreCellSets {
+    let (part_vec, merges) = crate::core_cell::compute_adjacency_lists(cells, points, params, &mut Monitor::unobserved());
+    CoreCellSets{part_vec, merges}
+}
+
+/// Gives a cluster index to each set of connected core cells and groups the core points in their clusters. The first 
+/// cluster is the still empty set of noise points
+pub fn find_connected_components<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &mut CellTable<D>, points: &S, sets: CoreCellSets) -> DBSCANResult<D, F> {
+    crate::cluster::find_connected_components(cells, points, sets.part_vec)
+}
+
+/// Adds the non core points to the clusters they are close to in `clusters`, as returned by `find_connected_components`,
+/// or to the noise points
+pub fn assign_border_noise_points<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(cells: &CellTable<D>, points: &S, params: &DBSCANParams, clusters: &mut DBSCANResult<D, F>) {
+    crate::cluster::assign_border_noise_points(cells, points, clusters, params, &mut Monitor::unobserved())
+}
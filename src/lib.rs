@@ -1,18 +1,32 @@
-#![feature(min_const_generics)]
 pub mod utils;
 mod tree_structure;
+mod point_index;
 mod cell;
 mod core_cell;
 mod cluster;
 pub mod dbscan;
 pub mod data_io;
+pub mod segmentation;
+pub mod plot;
+pub mod tuning;
+pub mod evaluation;
+pub mod model;
+pub mod out_of_core;
+pub mod progress;
+pub mod stats;
+pub mod generate;
+#[cfg(feature = "ndarray")]
+pub mod array;
+#[cfg(feature = "internals")]
+#[doc(hidden)]
+pub mod internals;
 
 extern crate partitions;
 extern crate rstar;
 
 use utils::*;
 use data_io::{params_from_file, read_points_from_file};
-use dbscan::approximate_dbscan;
+use dbscan::{approximate_dbscan, approximate_dbscan_labels};
 use std::path::{Path};
 
 /// Function that returns the result of the approximate DBSCAN algorithm 
@@ -21,7 +35,7 @@ use std::path::{Path};
 /// # Arguments
 /// 
 /// * `filename`: the path to the file containing the data points. The file should be formatted with one point per line and the values for each coordinate should be 
-///     separated by a white space. Only numerical coordinates values are accepted. 
+///   separated by a white space. Only numerical coordinates values are accepted. 
 /// * `epsilon`: the radius for the DBSCAN algorithm. 
 /// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
 /// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
@@ -56,8 +70,7 @@ where P: AsRef<Path>{
     params.rho = rho;
     params.min_pts = min_pts;
     let points : Vec<Point<D>> = read_points_from_file(&filename, &params);
-    let res = approximate_dbscan(points, &params);
-    res
+    approximate_dbscan(&points, &params)
 }
 
 
@@ -66,18 +79,19 @@ where P: AsRef<Path>{
 ///  
 /// # Arguments
 /// 
-/// * `points`: the vector of points to execute the algorithm on. All points must be arrays of lenght `D` 
+/// * `points`: the vector of points to execute the algorithm on. All points must be arrays of lenght `D` with coordinates of type `f32` or `f64`
 /// * `epsilon`: the radius for the DBSCAN algorithm. 
 /// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
 /// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
 /// 
-/// # Constant argument
+/// # Generic arguments
 /// 
+/// * `F`: The scalar type of the coordinates of the points, either `f32` or `f64`.
 /// * `D`: The dimensionality of each point in the data. 
 /// 
 /// # Return value
 /// 
-/// This function returns a vector of clusters, where each cluster is a vector of the points contained in it. Each point is stored as an array of f64 (``[f64;D]``).
+/// This function returns a vector of clusters, where each cluster is a vector of the points contained in it. Each point is stored as an array of `F` (``[F;D]``).
 /// The element at index `0` is the collection of all noise points, while all the other elements are the actual clusters. 
 /// 
 /// # Example
@@ -92,16 +106,15 @@ where P: AsRef<Path>{
 /// let noise_points_count = res[0].len();
 /// ```
 /// 
-pub fn do_appr_dbscan_points<const D: usize>(points: Vec<Point<D>>, epsilon: f64, rho: f64, min_pts: usize) -> DBSCANResult<D> {
+pub fn do_appr_dbscan_points<F: Float, const D: usize>(points: Vec<Point<D, F>>, epsilon: f64, rho: f64, min_pts: usize) -> DBSCANResult<D, F> {
     let params = DBSCANParams{
         dimensionality: D as u32,
         cardinality: points.len(),
-        epsilon: epsilon,
-        rho: rho,
-        min_pts: min_pts
+        epsilon,
+        rho,
+        min_pts
     };
-    let res = approximate_dbscan(points, &params);
-    res
+    approximate_dbscan(&points, &params)
 }
 
 /// Function that returns the result of the approximate DBSCAN algorithm without prior knowledge of the points dimensionality
@@ -110,7 +123,7 @@ pub fn do_appr_dbscan_points<const D: usize>(points: Vec<Point<D>>, epsilon: f64
 /// # Arguments
 /// 
 /// * `filename`: the path to the file containing the data points. The file should be formatted with one point per line and the values for each coordinate should be 
-///     separated by a white space. Only numerical coordinates values are accepted. 
+///   separated by a white space. Only numerical coordinates values are accepted. 
 /// * `epsilon`: the radius for the DBSCAN algorithm. 
 /// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
 /// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
@@ -136,13 +149,13 @@ where P: AsRef<Path>{
     let params = params_from_file(&filename);
     match params.dimensionality {
         0 => {panic!("There has been an error while reading the data: 0 dimensionality point found");},
-        1 => (array_res_to_vector_res::<1>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        2 => (array_res_to_vector_res::<2>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        3 => (array_res_to_vector_res::<3>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        4 => (array_res_to_vector_res::<4>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        5 => (array_res_to_vector_res::<5>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        6 => (array_res_to_vector_res::<6>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
-        7 => (array_res_to_vector_res::<7>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        1 => (array_res_to_vector_res::<f64, 1>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        2 => (array_res_to_vector_res::<f64, 2>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        3 => (array_res_to_vector_res::<f64, 3>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        4 => (array_res_to_vector_res::<f64, 4>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        5 => (array_res_to_vector_res::<f64, 5>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        6 => (array_res_to_vector_res::<f64, 6>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
+        7 => (array_res_to_vector_res::<f64, 7>(do_appr_dbscan_file(filename, epsilon, rho, min_pts)),params.dimensionality as usize),
         _ => {panic!("Dimensionalities over 7 are not supported")}
     }
 }
@@ -175,7 +188,7 @@ where P: AsRef<Path>{
 /// ```
 /// 
 pub fn do_appr_dbscan_auto_dimensionality_points(points: Vec<VectorPoint>, epsilon: f64, rho: f64, min_pts: usize) -> (VectorDBSCANResult, usize) {
-    if points.len() == 0 {
+    if points.is_empty() {
         return (Vec::new(),0);
     }
     let dimensionality = points[0].len();
@@ -183,33 +196,156 @@ pub fn do_appr_dbscan_auto_dimensionality_points(points: Vec<VectorPoint>, epsil
         0 => {panic!("There has been an error while reading the data: 0 dimensionality point found");},
         1 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<1>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 1>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         2 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<2>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 2>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         3 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<3>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 3>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         4 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<4>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 4>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         5 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<5>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 5>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         6 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<6>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 6>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         7 => {
             let arr_points = vector_input_to_array_input(points);
-            (array_res_to_vector_res::<7>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
+            (array_res_to_vector_res::<f64, 7>(do_appr_dbscan_points(arr_points, epsilon, rho, min_pts)), dimensionality)
         },
         _ => {panic!("Dimensionalities over 7 are not supported")}
     }
 }
 
+
+/// Function that labels each point in `points` with the cluster found for it by the approximate DBSCAN algorithm 
+/// executed with the given values of epsilon and rho.
+///  
+/// # Arguments
+/// 
+/// * `points`: the vector of points to execute the algorithm on. All points must be arrays of lenght `D` with coordinates of type `f32` or `f64`
+/// * `epsilon`: the radius for the DBSCAN algorithm. 
+/// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
+/// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
+/// 
+/// # Generic arguments
+/// 
+/// * `F`: The scalar type of the coordinates of the points, either `f32` or `f64`.
+/// * `D`: The dimensionality of each point in the data. 
+/// 
+/// # Return value
+/// 
+/// This function returns a vector of labels, where the label at index `i` refers to the `i`-th point in `points`. Each label holds 
+/// the index of the cluster of the point and its role (core, border or noise). Noise points are labeled with cluster `0`.
+/// 
+/// # Example
+/// ``` rust
+/// extern crate appr_dbscan;
+/// use appr_dbscan::do_appr_dbscan_labels_points;
+/// use appr_dbscan::utils::{DBSCANLabels, PointRole};
+/// 
+/// let points = vec![[0.0,0.0],[1.0,1.0],[0.0,1.0],[1.0,0.0],[2.0,1.0],[0.0,2.0],[2.0,1.0],[1.0,1.0]];
+/// let labels : DBSCANLabels = do_appr_dbscan_labels_points(points, 0.3, 0.1, 10);
+/// let noise_points_count = labels.iter().filter(|l| l.role == PointRole::Noise).count();
+/// ```
+/// 
+pub fn do_appr_dbscan_labels_points<F: Float, const D: usize>(points: Vec<Point<D, F>>, epsilon: f64, rho: f64, min_pts: usize) -> DBSCANLabels {
+    do_appr_dbscan_labels(&points, epsilon, rho, min_pts)
+}
+
+/// Function that labels each point in `points` with the cluster found for it by the approximate DBSCAN algorithm 
+/// executed with the given values of epsilon and rho, without copying the points.
+///  
+/// # Arguments
+/// 
+/// * `points`: the points to execute the algorithm on. They can be a slice of arrays of length `D`, a `utils::StridedPoints` 
+///   view on a flat slice of coordinates or, with the `ndarray` feature, an `ndarray::ArrayView2` with `D` columns. 
+/// * `epsilon`: the radius for the DBSCAN algorithm. 
+/// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
+/// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
+/// 
+/// # Generic arguments
+/// 
+/// * `F`: The scalar type of the coordinates of the points, either `f32` or `f64`.
+/// * `D`: The dimensionality of each point in the data. 
+/// 
+/// # Return value
+/// 
+/// This function returns a vector of labels, where the label at index `i` refers to the `i`-th point in `points`. Each label holds 
+/// the index of the cluster of the point and its role (core, border or noise). Noise points are labeled with cluster `0`.
+/// 
+/// # Example
+/// ``` rust
+/// extern crate appr_dbscan;
+/// use appr_dbscan::do_appr_dbscan_labels;
+/// use appr_dbscan::utils::{DBSCANLabels, StridedPoints};
+/// 
+/// // two coordinates and a weight for each point, the weight is skipped
+/// let data = vec![0.0,0.0,5.0, 1.0,1.0,5.0, 0.0,1.0,5.0, 1.0,0.0,5.0];
+/// let points = StridedPoints::<f64, 2>::new(&data, 3);
+/// let labels : DBSCANLabels = do_appr_dbscan_labels(&points, 0.3, 0.1, 10);
+/// assert_eq!(labels.len(), 4);
+/// ```
+/// 
+pub fn do_appr_dbscan_labels<F: Float, const D: usize, S: PointSource<F, D> + ?Sized>(points: &S, epsilon: f64, rho: f64, min_pts: usize) -> DBSCANLabels {
+    let params = DBSCANParams{
+        dimensionality: D as u32,
+        cardinality: points.len(),
+        epsilon,
+        rho,
+        min_pts
+    };
+    approximate_dbscan_labels(points, &params)
+}
+
+/// Function that labels each point in `points` with the cluster found for it by the approximate DBSCAN algorithm, 
+/// without prior knowledge of the points dimensionality.
+///  
+/// # Arguments
+/// 
+/// * `points`: the vector of points to execute the algorithm on. All points must be vectors of the same length in order to be points from the same space. 
+/// * `epsilon`: the radius for the DBSCAN algorithm. 
+/// * `rho`: the approximation factor. The smaller it is the more precise the result. Usual values are 0.1 and 0.01.
+/// * `min_pts`: the minimum number of nearby points required by the DBSCAN algorithm to declare an area as 'dense'.
+/// 
+/// # Return value
+/// 
+/// This function returns a vector of labels, where the label at index `i` refers to the `i`-th point in `points`, along with the 
+/// detected dimensionality. Noise points are labeled with cluster `0`.
+/// 
+/// # Example
+/// ``` rust
+/// extern crate appr_dbscan;
+/// use appr_dbscan::do_appr_dbscan_auto_dimensionality_labels_points;
+/// 
+/// let points = vec![vec![0.0,0.0],vec![1.0,1.0],vec![0.0,1.0],vec![1.0,0.0],vec![2.0,1.0],vec![0.0,2.0],vec![2.0,1.0],vec![1.0,1.0]];
+/// let (labels, dimensionality) = do_appr_dbscan_auto_dimensionality_labels_points(points, 0.3, 0.1, 10);
+/// assert_eq!(labels.len(), 8);
+/// ```
+/// 
+pub fn do_appr_dbscan_auto_dimensionality_labels_points(points: Vec<VectorPoint>, epsilon: f64, rho: f64, min_pts: usize) -> (DBSCANLabels, usize) {
+    if points.is_empty() {
+        return (Vec::new(),0);
+    }
+    let dimensionality = points[0].len();
+    match dimensionality {
+        0 => {panic!("There has been an error while reading the data: 0 dimensionality point found");},
+        1 => (do_appr_dbscan_labels_points::<f64, 1>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        2 => (do_appr_dbscan_labels_points::<f64, 2>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        3 => (do_appr_dbscan_labels_points::<f64, 3>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        4 => (do_appr_dbscan_labels_points::<f64, 4>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        5 => (do_appr_dbscan_labels_points::<f64, 5>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        6 => (do_appr_dbscan_labels_points::<f64, 6>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        7 => (do_appr_dbscan_labels_points::<f64, 7>(vector_input_to_array_input(points), epsilon, rho, min_pts), dimensionality),
+        _ => {panic!("Dimensionalities over 7 are not supported")}
+    }
+}
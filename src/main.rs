@@ -1,73 +1,353 @@
-#![feature(min_const_generics)]
 extern crate appr_dbscan;
-use appr_dbscan::do_appr_dbscan_auto_dimensionality_file;
-use appr_dbscan::data_io::{params_from_file, write_to_bmp_vec};
+use appr_dbscan::do_appr_dbscan_auto_dimensionality_labels_points;
+use appr_dbscan::data_io::{read_vector_points_from_file, write_to_bmp_vec, write_labels, OutputFormat, BmpWriterConfig};
+use appr_dbscan::utils::{labels_to_vector_res, DBSCANLabels, Metric, PointRole, VectorPoint};
+use appr_dbscan::plot::{write_svg, PlotConfig, Projection};
+use appr_dbscan::tuning::suggest_epsilon;
+use appr_dbscan::evaluation::{adjusted_rand_index, normalized_mutual_information};
+use appr_dbscan::generate::{self, NOISE_LABEL};
+use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::process;
 
+/// The highest dimensionality supported by the functions that detect it automatically
+const MAX_DIMENSIONALITY : usize = 7;
+const DEFAULT_RHO : f64 = 0.1;
+const DEFAULT_SAMPLE_SIZE : usize = 2000;
+const DEFAULT_CLUSTERS : usize = 3;
 
+/// Exit code for errors in the command line arguments
+const USAGE_ERROR : i32 = 2;
+/// Exit code for errors while reading the input or writing the output
+const RUNTIME_ERROR : i32 = 1;
 
-const MIN_ARGS_NUM : usize = 5;
-const MAX_ARGS_NUM : usize = 6;
+enum CliError {
+    Usage(String),
+    Runtime(String)
+}
 
+type CliResult<T> = Result<T, CliError>;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != MIN_ARGS_NUM && args.len() != MAX_ARGS_NUM {
-        println!("Numero di parametri errato");
-        print_help();
-        return;
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}", message);
+            eprintln!("Run 'appr_dbscan help' to see the available commands and flags");
+            USAGE_ERROR
+        },
+        Err(CliError::Runtime(message)) => {
+            eprintln!("error: {}", message);
+            RUNTIME_ERROR
+        }
+    };
+    process::exit(code);
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+    let (command, flags) = match args.split_first() {
+        Some((command, flags)) => (command.as_str(), flags),
+        None => {
+            print_help();
+            return Err(CliError::Usage("no command given".to_string()));
+        }
+    };
+    match command {
+        "cluster" => cluster(&Flags::parse(flags, &["input", "epsilon", "rho", "min-pts", "metric", "output", "format", "svg", "bmp"])?),
+        "suggest-eps" => suggest_eps(&Flags::parse(flags, &["input", "min-pts", "metric", "sample"])?),
+        "sweep" => sweep(&Flags::parse(flags, &["input", "epsilon", "rho", "min-pts", "metric"])?),
+        "evaluate" => evaluate(&Flags::parse(flags, &["input", "labels", "epsilon", "rho", "min-pts", "metric"])?),
+        "generate" => generate(&Flags::parse(flags, &["shape", "count", "dimensionality", "clusters", "spread", "noise", "seed", "output", "labels"])?),
+        "help" | "--help" | "-h" => {
+            print_help();
+            Ok(())
+        },
+        _ => Err(CliError::Usage(format!("unknown command {:?}", command)))
     }
-    let file_name = &args[1];
-    let epsilon = parse_float(&args[2], "epsilon");
-    let rho = parse_float(&args[3], "rho");
-    let min_pts = parse_usize(&args[4], "min_pts");
-    let print_bitmap = if args.len() == MAX_ARGS_NUM {parse_bool(&args[5])} else {false};
-    let mut params = params_from_file(file_name); 
-    params.epsilon = epsilon;
-    params.rho = rho;
-    params.min_pts = min_pts;
-    println!("Epsilon: {}, Rho: {}, MinPts: {}",epsilon, rho, min_pts);
-    println!("Dim: {}, n: {}, Apprx_rdx: {}",params.dimensionality, params.cardinality, params.epsilon*(1_f64 +params.rho));
+}
+
+/// The values of the named flags given to a command, in the form `--name value` or `--name=value`
+struct Flags {
+    values: HashMap<String, String>
+}
 
+impl Flags {
+    fn parse(args: &[String], allowed: &[&str]) -> CliResult<Flags> {
+        let mut values = HashMap::new();
+        let mut args_iter = args.iter();
+        while let Some(arg) = args_iter.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return Err(CliError::Usage(format!("unexpected argument {:?}, all arguments must be named flags", arg)))
+            };
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name, value.to_string()),
+                None => match args_iter.next() {
+                    Some(value) => (name, value.clone()),
+                    None => return Err(CliError::Usage(format!("missing value for flag --{}", name)))
+                }
+            };
+            if !allowed.contains(&name) {
+                return Err(CliError::Usage(format!("unknown flag --{} for this command", name)));
+            }
+            if values.insert(name.to_string(), value).is_some() {
+                return Err(CliError::Usage(format!("flag --{} given more than once", name)));
+            }
+        }
+        Ok(Flags{values})
+    }
+
+    fn optional(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+
+    fn required(&self, name: &str) -> CliResult<&str> {
+        self.optional(name).ok_or_else(|| CliError::Usage(format!("missing required flag --{}", name)))
+    }
 
-    let (res, dimensionality) = do_appr_dbscan_auto_dimensionality_file(file_name, epsilon, rho, min_pts);
-    if print_bitmap {
-        write_to_bmp_vec(&"./gp_srcs/out.bmp",&res, dimensionality);
+    fn parsed<T: std::str::FromStr>(&self, name: &str, default: Option<T>) -> CliResult<T>
+    where T::Err: std::fmt::Display {
+        match (self.optional(name), default) {
+            (Some(value), _) => value.parse().map_err(|e| CliError::Usage(format!("invalid value {:?} for --{}: {}", value, name, e))),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(CliError::Usage(format!("missing required flag --{}", name)))
+        }
+    }
+
+    fn positive_float(&self, name: &str, default: Option<f64>) -> CliResult<f64> {
+        let value : f64 = self.parsed(name, default)?;
+        if value.is_nan() || value <= 0.0 {
+            return Err(CliError::Usage(format!("--{} must be a positive number", name)));
+        }
+        Ok(value)
+    }
+
+    fn positive_integer(&self, name: &str, default: Option<usize>) -> CliResult<usize> {
+        let value : usize = self.parsed(name, default)?;
+        if value == 0 {
+            return Err(CliError::Usage(format!("--{} must be a positive integer", name)));
+        }
+        Ok(value)
+    }
+
+    fn metric(&self) -> CliResult<Metric> {
+        self.parsed("metric", Some(Metric::Euclidean))
     }
-    println!("Found {} clusters and {} noise points", res.len() -1 ,res[0].len());
 }
 
-fn print_help(){
-    println!("Utilizzo:");
-    println!(" - appr_dbscan_rust data_file epsilon rho min_pts <print_bitmap>");
-    println!(" - data_file deve essere un file contenente punti stampati uno per riga e con le coordinate separate da uno spazio");
-    println!(" - epsilon e rho devono essere numeri decimali positivi non nulli");
-    println!(" - min_pts deve essere un numero intero positivo non nullo");
-    println!(" - print_bitmap e' opzionale e se presente deve essere un valore booleano. Di default vale \"false\"");
-    println!("Se uno tra 'print_bitmap' e 'compare_results' deve essere messo a true allora vanno specificati entrambi.");
+/// Parses a list of values given either as `a,b,c` or as the range `start:stop:step`, with `stop` included
+fn parse_list(value: &str, name: &str) -> CliResult<Vec<f64>> {
+    let invalid = || CliError::Usage(format!("invalid value {:?} for --{}, expected a,b,c or start:stop:step", value, name));
+    let parse = |v: &str| v.trim().parse::<f64>().map_err(|_| invalid());
+    let parts : Vec<&str> = value.split(':').collect();
+    let values : Vec<f64> = match parts.len() {
+        1 => value.split(',').map(parse).collect::<CliResult<Vec<f64>>>()?,
+        3 => {
+            let (start, stop, step) = (parse(parts[0])?, parse(parts[1])?, parse(parts[2])?);
+            if step.is_nan() || step <= 0.0 || stop < start {
+                return Err(invalid());
+            }
+            // values are computed from their position to avoid accumulating rounding errors,
+            // and then rounded so that they are printed as they would be written
+            let steps = ((stop - start) / step + 1e-9).floor() as usize;
+            (0..=steps).map(|i| ((start + i as f64 * step) * 1e12).round() / 1e12).collect()
+        },
+        _ => return Err(invalid())
+    };
+    if values.iter().any(|v| v.is_nan() || *v <= 0.0) {
+        return Err(CliError::Usage(format!("--{} values must be positive", name)));
+    }
+    Ok(values)
 }
 
-fn parse_float(arg: &str, name: &str) -> f64 {
-    let float : f64 = arg.parse().unwrap_or(0.0);
-    if float <= 0.0 {
-        println!("Il valore inserito per {:?} non e' valido : {}", name, arg);
-        process::exit(1);
+/// Reads the points from `--input`, as they are in the file
+fn load_points(flags: &Flags) -> CliResult<Vec<VectorPoint>> {
+    let file_name = flags.required("input")?;
+    let points = read_vector_points_from_file(&file_name)
+        .map_err(|e| CliError::Runtime(format!("could not read {}: {}", file_name, e)))?;
+    let dimensionality = points.first().map_or(0, |p| p.len());
+    if dimensionality == 0 {
+        return Err(CliError::Runtime(format!("{} contains no points", file_name)));
     }
-    float
+    if dimensionality > MAX_DIMENSIONALITY {
+        return Err(CliError::Runtime(format!("points have {} coordinates but at most {} are supported", dimensionality, MAX_DIMENSIONALITY)));
+    }
+    Ok(points)
 }
 
-fn parse_usize(arg: &str, name: &str) -> usize {
-    let integer : usize = arg.parse().unwrap_or(0);
-    if integer <= 0 {
-        println!("Il valore inserito per {:?} non e' valido", name);
-        process::exit(1);
+/// A copy of `points` transformed for `metric`, so that the euclidean distance can be used to cluster them
+fn metric_points(points: &[VectorPoint], metric: Metric) -> Vec<VectorPoint> {
+    let mut transformed = points.to_vec();
+    for point in transformed.iter_mut() {
+        metric.transform_point(point);
     }
-    integer
+    transformed
 }
 
-fn parse_bool(arg: &str) -> bool {
-    let boolean : bool = arg.parse().unwrap_or(false);
-    boolean
+/// Runs the approximate DBSCAN algorithm on points already transformed for `metric` by `metric_points`
+fn cluster_points(points: &[VectorPoint], epsilon: f64, rho: f64, min_pts: usize, metric: Metric) -> DBSCANLabels {
+    do_appr_dbscan_auto_dimensionality_labels_points(points.to_vec(), metric.euclidean_epsilon(epsilon), rho, min_pts).0
 }
 
+fn clusters_and_noise(labels: &DBSCANLabels) -> (usize, usize) {
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+    let noise_count = labels.iter().filter(|l| l.role == PointRole::Noise).count();
+    (clusters_count, noise_count)
+}
+
+fn cluster(flags: &Flags) -> CliResult<()> {
+    let epsilon = flags.positive_float("epsilon", None)?;
+    let rho = flags.positive_float("rho", Some(DEFAULT_RHO))?;
+    let min_pts = flags.positive_integer("min-pts", None)?;
+    let metric = flags.metric()?;
+    let format : Option<OutputFormat> = flags.optional("format").map(|f| f.parse()).transpose().map_err(CliError::Usage)?;
+    let points = load_points(flags)?;
+    let dimensionality = points[0].len();
+    // the transformed points are only used to cluster, the outputs show the points as they are in the input
+    let labels = cluster_points(&metric_points(&points, metric), epsilon, rho, min_pts, metric);
+    if let Some(output) = flags.optional("output") {
+        let format = format.or_else(|| OutputFormat::from_extension(&output)).unwrap_or(OutputFormat::Csv);
+        write_labels(&output, &points, &labels, format).map_err(|e| CliError::Runtime(format!("could not write {}: {}", output, e)))?;
+    } else if format.is_some() {
+        return Err(CliError::Usage("--format requires --output".to_string()));
+    }
+    if let Some(svg) = flags.optional("svg") {
+        let config = PlotConfig{
+            projection: if dimensionality == 3 {Projection::Isometric} else {Projection::Axes(0, dimensionality.min(2) - 1)},
+            ..PlotConfig::default()
+        };
+        write_svg(&svg, &points, &labels, &config).map_err(|e| CliError::Runtime(format!("could not write {}: {}", svg, e)))?;
+    }
+    if let Some(bmp) = flags.optional("bmp") {
+        write_to_bmp_vec(&bmp, &labels_to_vector_res(&points, &labels), &BmpWriterConfig::default())
+            .map_err(|e| CliError::Runtime(format!("could not write {}: {}", bmp, e)))?;
+    }
+    let (clusters_count, noise_count) = clusters_and_noise(&labels);
+    println!("Points: {}, dimensionality: {}", points.len(), dimensionality);
+    println!("Found {} clusters and {} noise points", clusters_count, noise_count);
+    Ok(())
+}
+
+fn suggest_eps(flags: &Flags) -> CliResult<()> {
+    let min_pts = flags.positive_integer("min-pts", None)?;
+    let sample_size = flags.positive_integer("sample", Some(DEFAULT_SAMPLE_SIZE))?;
+    let metric = flags.metric()?;
+    let points = metric_points(&load_points(flags)?, metric);
+    let epsilon = suggest_epsilon(&points, min_pts, sample_size)
+        .ok_or_else(|| CliError::Runtime(format!("at least {} points are needed to suggest a value for epsilon", min_pts)))?;
+    // the suggestion is in the space of the transformed points
+    let epsilon = match metric {
+        Metric::Euclidean => epsilon,
+        Metric::Cosine => epsilon * epsilon / 2.0
+    };
+    println!("{}", epsilon);
+    Ok(())
+}
+
+fn sweep(flags: &Flags) -> CliResult<()> {
+    let epsilons = parse_list(flags.required("epsilon")?, "epsilon")?;
+    let all_min_pts = parse_list(flags.required("min-pts")?, "min-pts")?;
+    if all_min_pts.iter().any(|m| m.fract() != 0.0) {
+        return Err(CliError::Usage("--min-pts values must be integers".to_string()));
+    }
+    let rho = flags.positive_float("rho", Some(DEFAULT_RHO))?;
+    let metric = flags.metric()?;
+    let points = metric_points(&load_points(flags)?, metric);
+    println!("epsilon\tmin_pts\tclusters\tnoise");
+    for epsilon in &epsilons {
+        for min_pts in &all_min_pts {
+            let labels = cluster_points(&points, *epsilon, rho, *min_pts as usize, metric);
+            let (clusters_count, noise_count) = clusters_and_noise(&labels);
+            println!("{}\t{}\t{}\t{}", epsilon, min_pts, clusters_count, noise_count);
+        }
+    }
+    Ok(())
+}
+
+fn evaluate(flags: &Flags) -> CliResult<()> {
+    let epsilon = flags.positive_float("epsilon", None)?;
+    let rho = flags.positive_float("rho", Some(DEFAULT_RHO))?;
+    let min_pts = flags.positive_integer("min-pts", None)?;
+    let metric = flags.metric()?;
+    let labels_file = flags.required("labels")?;
+    let points = metric_points(&load_points(flags)?, metric);
+    let content = fs::read_to_string(labels_file).map_err(|e| CliError::Runtime(format!("could not read {}: {}", labels_file, e)))?;
+    let mut truth : Vec<i64> = Vec::with_capacity(points.len());
+    for (line_i, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        truth.push(line.trim().parse().map_err(|_| CliError::Runtime(format!("{} line {}: invalid label {:?}", labels_file, line_i + 1, line)))?);
+    }
+    if truth.len() != points.len() {
+        return Err(CliError::Runtime(format!("{} contains {} labels but there are {} points", labels_file, truth.len(), points.len())));
+    }
+    let labels = cluster_points(&points, epsilon, rho, min_pts, metric);
+    let predicted : Vec<usize> = labels.iter().map(|l| l.cluster_id).collect();
+    let (clusters_count, noise_count) = clusters_and_noise(&labels);
+    println!("Found {} clusters and {} noise points", clusters_count, noise_count);
+    println!("Adjusted Rand index: {:.6}", adjusted_rand_index(&truth, &predicted));
+    println!("Normalized mutual information: {:.6}", normalized_mutual_information(&truth, &predicted));
+    Ok(())
+}
+
+fn generate(flags: &Flags) -> CliResult<()> {
+    let shape = flags.required("shape")?;
+    let count = flags.positive_integer("count", None)?;
+    let dimensionality = flags.positive_integer("dimensionality", Some(2))?;
+    let clusters = flags.positive_integer("clusters", Some(DEFAULT_CLUSTERS))?;
+    let noise : usize = flags.parsed("noise", Some(0))?;
+    let seed : u64 = flags.parsed("seed", Some(0))?;
+    let output = flags.required("output")?;
+    let dataset = match shape {
+        "blobs" => generate::blobs(count, dimensionality, clusters, flags.positive_float("spread", Some(1.0))?, seed),
+        "rings" => generate::rings(count, dimensionality, clusters, flags.positive_float("spread", Some(0.05))?, seed),
+        "moons" if dimensionality >= 2 => generate::moons(count, dimensionality, flags.positive_float("spread", Some(0.05))?, seed),
+        "moons" => return Err(CliError::Usage("moons need --dimensionality of at least 2".to_string())),
+        "uniform" => generate::uniform(count, dimensionality, flags.positive_float("spread", Some(10.0))?, seed),
+        "varying-density" => generate::varying_density(count, dimensionality, clusters, flags.positive_float("spread", Some(0.25))?, seed),
+        _ => return Err(CliError::Usage(format!("unknown shape {:?}, expected blobs, rings, moons, uniform or varying-density", shape)))
+    };
+    // the noise points take a different seed than the clusters, so that they do not repeat their first coordinates
+    let dataset = dataset.with_noise(noise, seed.wrapping_add(1));
+    let labels = flags.optional("labels");
+    dataset.save(&output, labels.as_ref()).map_err(|e| CliError::Runtime(format!("could not write the dataset to {}: {}", output, e)))?;
+    let noise_count = dataset.labels.iter().filter(|l| **l == NOISE_LABEL).count();
+    println!("Generated {} points in {} clusters and {} noise points", dataset.points.len(), dataset.clusters_count(), noise_count);
+    Ok(())
+}
+
+fn print_help(){
+    println!("Usage: appr_dbscan <command> [--flag value]...");
+    println!();
+    println!("Commands:");
+    println!("  cluster      clusters the points and prints the number of clusters and noise points");
+    println!("  suggest-eps  suggests a value for epsilon from the knee of the min-pts distances of the points");
+    println!("  sweep        clusters the points with every combination of the given epsilon and min-pts values");
+    println!("  evaluate     compares the clusters found with ground truth labels");
+    println!("  generate     writes a seeded synthetic dataset, and optionally its ground truth labels");
+    println!("  help         prints this message");
+    println!();
+    println!("Flags:");
+    println!("  --input <file>      data file with one point per line and the coordinates separated by whitespace");
+    println!("  --epsilon <value>   clustering radius. sweep accepts a list a,b,c or a range start:stop:step");
+    println!("  --rho <value>       approximation factor, defaults to {}", DEFAULT_RHO);
+    println!("  --min-pts <value>   minimum number of points for density. sweep accepts a list or a range");
+    println!("  --metric <metric>   euclidean (default) or cosine");
+    println!("  --output <file>     (cluster) writes the points labeled with their cluster and role");
+    println!("                      (generate) writes the points, one for each line with whitespace separated coordinates");
+    println!("  --format <format>   (cluster) csv, tsv, jsonl or summary. Guessed from the --output extension by default");
+    println!("  --svg <file>        (cluster) draws the labeled points, projecting 3D points isometrically");
+    println!("  --bmp <file>        (cluster) draws 'row column B G R' points as a BMP image");
+    println!("  --sample <count>    (suggest-eps) maximum number of sampled points, defaults to {}", DEFAULT_SAMPLE_SIZE);
+    println!("  --labels <file>     (evaluate, generate) file with one integer ground truth label per line, {} for noise", NOISE_LABEL);
+    println!("  --shape <shape>     (generate) blobs, rings, moons, uniform or varying-density");
+    println!("  --count <count>     (generate) number of points, noise excluded");
+    println!("  --dimensionality <value>  (generate) number of coordinates of the points, defaults to 2");
+    println!("  --clusters <count>  (generate) number of blobs or rings, defaults to {}", DEFAULT_CLUSTERS);
+    println!("  --spread <value>    (generate) standard deviation of the clusters, the smallest one for varying-density,");
+    println!("                      or side of the box for uniform");
+    println!("  --noise <count>     (generate) number of uniform noise points added, defaults to 0");
+    println!("  --seed <value>      (generate) seed of the generator, defaults to 0");
+    println!();
+    println!("Exit codes: 0 on success, {} for invalid arguments, {} for input or output errors", USAGE_ERROR, RUNTIME_ERROR);
+}
@@ -0,0 +1,234 @@
+//! A fitted approximate DBSCAN model, that keeps the grid of cells built while clustering so that new points can be
+//! assigned to the clusters found. With the `serde` feature the model can be saved to a compact binary file and 
+//! reloaded later without clustering the points again.
+
+use crate::cell::{lookup_offsets, stencil_neighbours, CellId, CellTable};
+#[cfg(feature = "serde")]
+use crate::cell::populate_neighbours;
+use crate::cluster::{find_point_clusters, label_all_points};
+use crate::dbscan::cluster_cells;
+use crate::progress::Monitor;
+use crate::utils::*;
+use std::borrow::Cow;
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{self, BufReader, BufWriter, Read, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+#[cfg(feature = "serde")]
+use bincode::Options;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The result of the approximate DBSCAN algorithm on points of dimensionality `D`, together with the non empty cells
+/// of the grid and the approximate range counting trees of the core cells. The model does not keep the points.
+pub struct FittedModel<const D: usize> {
+    params: DBSCANParams,
+    clusters_count: usize,
+    labels: DBSCANLabels,
+    #[cfg_attr(feature = "serde", serde(with = "serde_cells"))]
+    cells: CellTable<D>,
+    /// The offsets used to find the neighbour cells of the points whose cell is empty, see `lookup_offsets`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    neighbour_offsets: Option<Vec<CellIndex<D>>>
+}
+
+impl <const D: usize> FittedModel<D> {
+    /// Clusters `points` with the approximate DBSCAN algorithm and keeps the grid built to find the clusters
+    pub fn fit<F: Float, S: PointSource<F, D> + ?Sized>(points: &S, params: &DBSCANParams) -> FittedModel<D> {
+        let mut monitor = Monitor::unobserved();
+        let (cells, clusters_count) = cluster_cells(points, params, &mut monitor).expect("an unobserved run can not be cancelled");
+        let labels = label_all_points(&cells, points, params, &mut monitor);
+        let neighbour_offsets = lookup_offsets(&cells);
+        FittedModel{params: *params, clusters_count, labels, cells, neighbour_offsets}
+    }
+
+    /// The parameters used to fit the model
+    pub fn params(&self) -> &DBSCANParams {
+        &self.params
+    }
+
+    /// The number of clusters found, noise excluded
+    pub fn clusters_count(&self) -> usize {
+        self.clusters_count
+    }
+
+    /// The label of each point used to fit the model, in the same order as the points
+    pub fn labels(&self) -> &DBSCANLabels {
+        &self.labels
+    }
+
+    /// The number of non empty cells of the grid
+    pub fn cells_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The labels of the model together with its parameters
+    pub fn result(&self) -> ClusteringResult {
+        ClusteringResult{params: self.params, clusters_count: self.clusters_count, labels: self.labels.clone()}
+    }
+
+    /// Finds the cluster of a new point in the same way as the border points are assigned during the clustering: the 
+    /// point belongs to the cluster of a core point at distance at most `epsilon` from it, with the approximation given by `rho`. 
+    /// Returns `NOISE_CLUSTER_INDEX` (`0`) if there is no such core point, and the lowest cluster index if the point is close 
+    /// to more than one cluster.
+    pub fn predict<F: Float>(&self, point: &Point<D, F>) -> usize {
+        let clusters_in = find_point_clusters(point, &self.neighbour_cells(point), &self.cells, &self.params, &mut Monitor::unobserved());
+        clusters_in.into_iter().min().unwrap_or(0)
+    }
+
+    /// Approximately counts the core points used to fit the model that are at distance at most `epsilon` from `point`, with
+    /// the approximation given by `rho`. It can be used as an estimate of the density of the core points around `point`,
+    /// and it is `0` exactly when `predict` would return `NOISE_CLUSTER_INDEX`.
+    pub fn approximate_core_count<F: Float>(&self, point: &Point<D, F>) -> usize {
+        self.neighbour_cells(point).iter()
+            .map(|&id| &self.cells[id])
+            .filter(|c| c.is_core)
+            .map(|c| c.core_info.root.approximate_range_counting_root(point, &self.params))
+            .sum()
+    }
+
+    /// The ids of the cells that may contain points at distance at most `epsilon` from `point`. If the cell of the point 
+    /// was empty when the model was fitted, these are its possible neighbours that are core cells: they are found by
+    /// looking up the cell of the point moved by each of the `neighbour_offsets`, or by checking every cell when
+    /// there are fewer cells than offsets.
+    fn neighbour_cells<F: Float>(&self, point: &Point<D, F>) -> Cow<'_, [CellId]> {
+        let index = get_base_cell_index(point, &self.params);
+        if let Some(cell) = self.cells.get(&index) {
+            return Cow::Borrowed(&cell.neighbour_cell_ids);
+        }
+        let neighbours = match &self.neighbour_offsets {
+            Some(offsets) => stencil_neighbours(&self.cells, &index, offsets).filter(|&id| self.cells[id].is_core).collect(),
+            None => self.cells.iter()
+                .filter(|(_, c)| c.is_core && index_distance_sq(&c.index, &index) < 4 * D)
+                .map(|(id, _)| id)
+                .collect()
+        };
+        Cow::Owned(neighbours)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Identifies the files written by `FittedModel::save`
+const MODEL_MAGIC: [u8; 4] = *b"ADBM";
+#[cfg(feature = "serde")]
+/// The version of the format of the files written by `FittedModel::save`
+const MODEL_FORMAT_VERSION: u32 = 3;
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+/// Written before the model to check that it can be read
+struct ModelHeader {
+    magic: [u8; 4],
+    format_version: u32,
+    dimensionality: u32
+}
+
+#[cfg(feature = "serde")]
+impl <const D: usize> FittedModel<D> {
+    /// Writes the model to `writer` in a compact binary form
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let header = ModelHeader{magic: MODEL_MAGIC, format_version: MODEL_FORMAT_VERSION, dimensionality: D as u32};
+        bincode_options().serialize_into(&mut writer, &header).map_err(|e| to_io_error(*e))?;
+        bincode_options().serialize_into(&mut writer, self).map_err(|e| to_io_error(*e))?;
+        writer.flush()
+    }
+
+    /// Reads a model written by `FittedModel::write_to`. Fails with `InvalidData` if the data is not a model, if the
+    /// model was fitted on points of a dimensionality different from `D` or if its parameters, labels, cells or trees
+    /// are not consistent
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<FittedModel<D>> {
+        let header : ModelHeader = bincode_options().deserialize_from(&mut reader).map_err(|e| to_io_error(*e))?;
+        if header.magic != MODEL_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the data is not an approximate DBSCAN model"));
+        }
+        if header.format_version != MODEL_FORMAT_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported model format version {}", header.format_version)));
+        }
+        if header.dimensionality != D as u32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("the model was fitted on points of dimensionality {}, expected {}", header.dimensionality, D)));
+        }
+        let mut model : FittedModel<D> = bincode_options().deserialize_from(&mut reader).map_err(|e| to_io_error(*e))?;
+        model.check_contents().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // the neighbours and the side sizes of the trees are not saved, since they can be computed again quickly
+        populate_neighbours(&mut model.cells, &mut Monitor::unobserved());
+        model.neighbour_offsets = lookup_offsets(&model.cells);
+        let base_side_size = model.params.epsilon / (model.params.dimensionality as f64).sqrt();
+        for cell in model.cells.values_mut().filter(|c| c.is_core) {
+            cell.core_info.root.check_layout().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("the tree of the cell {:?} is corrupted: {}", cell.index, e)))?;
+            cell.core_info.root.restore_side_sizes(base_side_size);
+        }
+        Ok(model)
+    }
+
+    /// Checks the parameters, the labels and the cells of a model read from a file, since each of them is used without
+    /// further checks by `predict` and `approximate_core_count`. The trees are checked separately by `read_from`
+    fn check_contents(&self) -> Result<(), String> {
+        if self.params.dimensionality != D as u32 {
+            return Err(format!("the parameters are for points of dimensionality {}, expected {}", self.params.dimensionality, D));
+        }
+        if !(self.params.epsilon.is_finite() && self.params.epsilon > 0.0) {
+            return Err(format!("epsilon must be finite and positive, found {}", self.params.epsilon));
+        }
+        if !(self.params.rho.is_finite() && self.params.rho > 0.0) {
+            return Err(format!("rho must be finite and positive, found {}", self.params.rho));
+        }
+        if let Some(label) = self.labels.iter().find(|l| l.cluster_id > self.clusters_count) {
+            return Err(format!("the label cluster {} is above the number of clusters {}", label.cluster_id, self.clusters_count));
+        }
+        for cell in self.cells.values() {
+            if cell.is_core && !(1..=self.clusters_count).contains(&cell.core_info.i_cluster) {
+                return Err(format!("the core cell {:?} is in the cluster {}, expected one from 1 to {}", cell.index, cell.core_info.i_cluster, self.clusters_count));
+            }
+            if cell.points.iter().any(|p| p.index >= self.labels.len()) {
+                return Err(format!("the cell {:?} refers to a point without a label", cell.index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the model in `file_name`. See `FittedModel::write_to`
+    pub fn save<P: AsRef<Path>>(&self, file_name: &P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(file_name)?))
+    }
+
+    /// Loads a model saved in `file_name`. See `FittedModel::read_from`
+    pub fn load<P: AsRef<Path>>(file_name: &P) -> io::Result<FittedModel<D>> {
+        FittedModel::read_from(BufReader::new(File::open(file_name)?))
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Variable length integers keep the cell indexes and the counts small
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+#[cfg(feature = "serde")]
+fn to_io_error(e: bincode::ErrorKind) -> io::Error {
+    match e {
+        bincode::ErrorKind::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+/// The cells are serialized as a sequence, since each of them already stores its index
+mod serde_cells {
+    use serde::{Serializer, Deserialize, Deserializer};
+    use serde::de::Error;
+    use crate::cell::{Cell, CellTable};
+
+    pub fn serialize<S: Serializer, const D: usize>(cells: &CellTable<D>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(cells.values())
+    }
+
+    pub fn deserialize<'de, De: Deserializer<'de>, const D: usize>(deserializer: De) -> Result<CellTable<D>, De::Error> {
+        let cells : Vec<Cell<D>> = Vec::deserialize(deserializer)?;
+        CellTable::from_cells(cells).map_err(|index| De::Error::custom(format!("the cell {:?} is repeated", index)))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,157 @@
+use super::*;
+use crate::data_io::{params_from_file, read_points_from_file};
+use crate::dbscan::approximate_dbscan_labels;
+
+fn fit_test_model() -> (Vec<Point<2>>, FittedModel<2>) {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    let model = FittedModel::fit(&points, &params);
+    (points, model)
+}
+
+#[test]
+fn fit_predict_test() {
+    let (points, model) = fit_test_model();
+    assert_eq!(model.clusters_count(), 5);
+    assert_eq!(model.labels().len(), points.len());
+    let labels = approximate_dbscan_labels(&points, model.params());
+    let noise_count = labels.iter().filter(|l| l.role == PointRole::Noise).count();
+    assert_eq!(model.labels().iter().filter(|l| l.role == PointRole::Noise).count(), noise_count);
+    // core points are always found in their own cell
+    for (point, label) in points.iter().zip(model.labels()).step_by(50) {
+        if label.role == PointRole::Core {
+            assert_eq!(model.predict(point), label.cluster_id);
+        }
+    }
+    assert_eq!(model.predict(&[1000.0, 1000.0]), 0);
+    let result = model.result();
+    assert_eq!(result.clusters_count, 5);
+    assert_eq!(&result.labels, model.labels());
+}
+
+#[test]
+fn predict_in_empty_cell_test() {
+    let params = DBSCANParams{cardinality: 4, dimensionality: 2, epsilon: 1.0, rho: 0.1, min_pts: 4};
+    let points = vec![[0.0, 0.0], [0.1, 0.0], [0.0, 0.1], [0.1, 0.1]];
+    let model = FittedModel::fit(&points, &params);
+    assert_eq!(model.clusters_count(), 1);
+    // one cell away from the only non empty cell, but still close to its points
+    let side = params.epsilon / 2_f64.sqrt();
+    assert_eq!(model.predict(&[0.9 * side, 0.0]), 1);
+    assert_eq!(model.predict(&[0.9_f32 * side as f32, 0.0]), 1);
+    assert_eq!(model.predict(&[3.0 * side, 0.0]), 0);
+    assert_eq!(model.approximate_core_count(&[0.05, 0.05]), 4);
+    assert_eq!(model.approximate_core_count(&[0.9 * side, 0.0]), 4);
+    assert_eq!(model.approximate_core_count(&[3.0 * side, 0.0]), 0);
+}
+
+#[test]
+fn empty_cell_neighbours_test() {
+    let (points, model) = fit_test_model();
+    assert!(model.neighbour_offsets.is_some());
+    let mut scanning = model.clone();
+    scanning.neighbour_offsets = None;
+    let mut empty_cells = 0;
+    // points between and around the fitted ones, with a step that is not a multiple of the side of the cells
+    let (low, high) = points.iter().fold(([f64::MAX; 2], [f64::MIN; 2]), |(l, h), p| ([l[0].min(p[0]), l[1].min(p[1])], [h[0].max(p[0]), h[1].max(p[1])]));
+    for i in 0..=40 {
+        for j in 0..=40 {
+            let point = [low[0] - 1.0 + (high[0] - low[0] + 2.0) * i as f64 / 40.0, low[1] - 1.0 + (high[1] - low[1] + 2.0) * j as f64 / 40.0];
+            let mut found = model.neighbour_cells(&point).into_owned();
+            let mut expected = scanning.neighbour_cells(&point).into_owned();
+            found.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(found, expected);
+            if model.cells.get(&get_base_cell_index(&point, model.params())).is_none() {
+                empty_cells += 1;
+            }
+        }
+    }
+    assert!(empty_cells > 0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn save_load_test() {
+    let (points, model) = fit_test_model();
+    let mut bytes = Vec::new();
+    model.write_to(&mut bytes).unwrap();
+    let loaded = FittedModel::<2>::read_from(&bytes[..]).unwrap();
+    assert_eq!(loaded.params(), model.params());
+    assert_eq!(loaded.labels(), model.labels());
+    assert_eq!(loaded.cells_count(), model.cells_count());
+    for point in points.iter().step_by(20) {
+        assert_eq!(loaded.predict(point), model.predict(point));
+        assert_eq!(loaded.approximate_core_count(point), model.approximate_core_count(point));
+    }
+
+    let err = FittedModel::<3>::read_from(&bytes[..]).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = FittedModel::<2>::read_from(&b"not a model"[..]).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(FittedModel::<2>::read_from(&bytes[..bytes.len() / 2]).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn duplicated_cells_test() {
+    let (_, model) = fit_test_model();
+    // the fields of the model in order, with the first cell written again at the end
+    let mut cells : Vec<&crate::cell::Cell<2>> = model.cells.values().collect();
+    cells.push(cells[0]);
+    let mut bytes = Vec::new();
+    let header = ModelHeader{magic: MODEL_MAGIC, format_version: MODEL_FORMAT_VERSION, dimensionality: 2};
+    bincode_options().serialize_into(&mut bytes, &header).unwrap();
+    bincode_options().serialize_into(&mut bytes, &(model.params(), model.clusters_count(), model.labels(), &cells)).unwrap();
+    let err = FittedModel::<2>::read_from(&bytes[..]).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    // the same bytes without the repeated cell are a valid model
+    cells.pop();
+    let mut bytes = Vec::new();
+    bincode_options().serialize_into(&mut bytes, &header).unwrap();
+    bincode_options().serialize_into(&mut bytes, &(model.params(), model.clusters_count(), model.labels(), &cells)).unwrap();
+    assert_eq!(FittedModel::<2>::read_from(&bytes[..]).unwrap().cells_count(), model.cells_count());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn invalid_contents_test() {
+    let (_, model) = fit_test_model();
+    let read_back = |model: &FittedModel<2>| {
+        let mut bytes = Vec::new();
+        model.write_to(&mut bytes).unwrap();
+        FittedModel::<2>::read_from(&bytes[..])
+    };
+    assert!(read_back(&model).is_ok());
+    let corruptions : Vec<fn(&mut FittedModel<2>)> = vec![
+        |m| m.params.dimensionality = 3,
+        |m| m.params.epsilon = f64::NAN,
+        |m| m.params.epsilon = -1.0,
+        |m| m.params.rho = 0.0,
+        |m| m.params.rho = f64::INFINITY,
+        |m| m.labels[0].cluster_id = m.clusters_count + 1,
+        |m| m.cells.values_mut().find(|c| c.is_core).unwrap().core_info.i_cluster = m.clusters_count + 1,
+        |m| m.cells.values_mut().find(|c| c.is_core).unwrap().core_info.i_cluster = 0,
+        |m| m.cells.values_mut().next().unwrap().points[0].index = m.labels.len()
+    ];
+    for corrupt in corruptions {
+        let mut corrupted = model.clone();
+        corrupt(&mut corrupted);
+        assert_eq!(read_back(&corrupted).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn result_serde_test() {
+    let (_, model) = fit_test_model();
+    let result = model.result();
+    let bytes = bincode::serialize(&result).unwrap();
+    let loaded : ClusteringResult = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(loaded, result);
+    let params_bytes = bincode::serialize(model.params()).unwrap();
+    assert_eq!(&bincode::deserialize::<DBSCANParams>(&params_bytes).unwrap(), model.params());
+}
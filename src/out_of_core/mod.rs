@@ -0,0 +1,520 @@
+//! Memory bounded approximate DBSCAN for datasets that do not fit in memory.
+//!
+//! The grid of cells is split in tiles of `OutOfCoreConfig::tile_side` cells per side in each of the `D` dimensions. The
+//! points are streamed once and appended to one file per tile, together with the points of the nearby cells that are
+//! needed to find the core points and the edges of the cells in the tile. Then the tiles are clustered one at a time with the same
+//! steps of the in-memory algorithm, and the edges between core cells are added to a union-find structure over the core
+//! cells that is kept for the whole run. Finally the labels are streamed back out to disk in the order of the points.
+//!
+//! Only the points of a single tile and the union-find structure over the core cells are kept in memory, so the tile
+//! side should be chosen so that the points of a tile, plus a margin of a few cells around it, fit in memory.
+
+use crate::cell::{find_cells, neighbour_reach, populate_neighbours, CellTable};
+use crate::core_cell::label_points;
+use crate::cluster::NOISE_CLUSTER_INDEX;
+use crate::progress::Monitor;
+use crate::utils::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The index of a tile of the grid: a cell with index `c` belongs to the tile `c.div_euclid(tile_side)`
+type TileIndex<const D: usize> = [i64; D];
+
+/// Label record value used for noise points
+const NO_CORE_CELL: u64 = 0;
+
+/// The first line of the labels written by `cluster_out_of_core`
+const LABELS_HEADER: &str = "cluster_id,role";
+
+/// Used to give a different name to the working directories of the runs in the same process. The directories left
+/// behind by killed runs of a process with the same id are skipped, see `WorkDir::create`
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, PartialEq, Debug)]
+/// The settings of an out of core clustering
+pub struct OutOfCoreConfig {
+    /// The directory where the temporary files are written. A new directory is created inside it for each run
+    /// and removed at the end.
+    pub work_dir: PathBuf,
+    /// The number of cells on each side of a tile. Larger tiles need more memory but duplicate fewer points
+    /// on disk, since the cells at the border of each tile are also written to the nearby tiles.
+    pub tile_side: usize,
+    /// The maximum number of temporary files kept open at the same time
+    pub max_open_files: usize
+}
+
+impl OutOfCoreConfig {
+    /// A configuration that writes the temporary files in `work_dir`, with tiles of 64 cells per side and
+    /// at most 256 open files
+    pub fn new<P: AsRef<Path>>(work_dir: &P) -> OutOfCoreConfig {
+        OutOfCoreConfig {
+            work_dir: work_dir.as_ref().to_path_buf(),
+            tile_side: 64,
+            max_open_files: 256
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Statistics about an out of core clustering
+pub struct OutOfCoreSummary {
+    /// The number of points clustered
+    pub points_count: usize,
+    /// The number of clusters found, noise excluded
+    pub clusters_count: usize,
+    /// The number of noise points
+    pub noise_count: usize,
+    /// The number of non empty tiles the points were divided into
+    pub tiles_count: usize
+}
+
+/// Function that clusters the points in `points` with the approximate DBSCAN algorithm while keeping in memory only
+/// one tile of the grid at a time. The labels are written to `labels_out` as comma separated values, with a header
+/// line `cluster_id,role` followed by one line per point in the same order as `points`. Cluster `0` holds the noise
+/// points and the other clusters are numbered from `1` in order of appearance.
+///
+/// # Arguments
+///
+/// * `points`: the points to cluster. Errors are propagated and stop the clustering
+/// * `params`: the DBSCAN algorithm parameters. `cardinality` and `dimensionality` are ignored
+/// * `config`: where to write the temporary files and how large the tiles are
+/// * `labels_out`: where to write the labels
+///
+/// # Constant argument
+///
+/// * `D`: the dimensionality of the points
+///
+/// # Return value
+///
+/// Some statistics about the clustering, or the first I/O error found
+pub fn cluster_out_of_core<const D: usize, I, W>(points: I, params: &DBSCANParams, config: &OutOfCoreConfig, labels_out: W) -> io::Result<OutOfCoreSummary>
+where I: IntoIterator<Item = io::Result<Point<D>>>, W: Write, {
+    if config.tile_side == 0 || config.max_open_files == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the tile side and the maximum number of open files must be positive"));
+    }
+    let mut params = *params;
+    params.dimensionality = D as u32;
+    let work_dir = WorkDir::create(&config.work_dir)?;
+    let grid = TileGrid::<D>{tile_side: config.tile_side as i64, reach: 2 * neighbour_reach::<D>()};
+    let (tiles, points_count) = partition_points(points, &params, &grid, &work_dir, config.max_open_files)?;
+    let mut core_cells = CoreCellUnionFind::new();
+    for (tile_id, tile) in tiles.iter().enumerate() {
+        cluster_tile(tile_id, tile, &params, &grid, &work_dir, &mut core_cells)?;
+    }
+    let (clusters_count, noise_count) = write_labels_in_order(points_count, &work_dir, &mut core_cells, config.max_open_files, labels_out)?;
+    Ok(OutOfCoreSummary{points_count, clusters_count, noise_count, tiles_count: tiles.len()})
+}
+
+/// Function that clusters the points in the data file `input_file` with `cluster_out_of_core` and writes the labels
+/// to `output_file`. The data file is read one line at a time, with the format described in `data_io::params_from_file`.
+pub fn cluster_file_out_of_core<P, Q, const D: usize>(input_file: &P, output_file: &Q, params: &DBSCANParams, config: &OutOfCoreConfig) -> io::Result<OutOfCoreSummary>
+where P: AsRef<Path>, Q: AsRef<Path>, {
+    let points = crate::data_io::stream_points_from_file::<P, D>(input_file)?;
+    let writer = BufWriter::new(File::create(output_file)?);
+    cluster_out_of_core(points, params, config, writer)
+}
+
+/// The tiling of the grid of cells
+struct TileGrid<const D: usize> {
+    tile_side: i64,
+    /// How many cells around a tile are loaded together with it. It is twice the reach of the neighbours, so that
+    /// the core points of the neighbours of the cells in the tile can be found too
+    reach: i64
+}
+
+impl <const D: usize> TileGrid<D> {
+    fn tile_of(&self, cell_index: &CellIndex<D>) -> TileIndex<D> {
+        let mut tile = [0; D];
+        for i in 0..D {
+            tile[i] = cell_index[i].div_euclid(self.tile_side);
+        }
+        tile
+    }
+
+    /// Calls `f` on all the tiles that need the points of the cell `cell_index`: the tile of the cell and the ones
+    /// that have the cell in their margin
+    fn for_each_tile_around(&self, cell_index: &CellIndex<D>, mut f: impl FnMut(TileIndex<D>)) {
+        let mut low = [0; D];
+        let mut high = [0; D];
+        for i in 0..D {
+            low[i] = (cell_index[i] - self.reach).div_euclid(self.tile_side);
+            high[i] = (cell_index[i] + self.reach).div_euclid(self.tile_side);
+        }
+        let mut tile = low;
+        loop {
+            f(tile);
+            let mut axis = 0;
+            while axis < D && tile[axis] == high[axis] {
+                tile[axis] = low[axis];
+                axis += 1;
+            }
+            if axis == D {
+                return;
+            }
+            tile[axis] += 1;
+        }
+    }
+}
+
+/// A temporary directory that is removed when dropped
+struct WorkDir {
+    path: PathBuf
+}
+
+impl WorkDir {
+    /// Creates a new directory in `parent`. A directory with the same name may be left by a run that was killed
+    /// before removing it, in a process that had the same id, so another name is tried instead of reusing its files
+    fn create(parent: &Path) -> io::Result<WorkDir> {
+        fs::create_dir_all(parent)?;
+        loop {
+            let run = RUN_COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = parent.join(format!("appr_dbscan_out_of_core_{}_{}", std::process::id(), run));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(WorkDir{path}),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
+    fn tile_points(&self, tile_id: usize) -> PathBuf {
+        self.path.join(format!("tile_{}.points", tile_id))
+    }
+
+    fn tile_labels(&self, tile_id: usize) -> PathBuf {
+        self.path.join(format!("tile_{}.labels", tile_id))
+    }
+
+    /// Holds the tile of each point, in the order of the points
+    fn point_tiles(&self) -> PathBuf {
+        self.path.join("point_tiles")
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The files kept open by `AppendFiles` and `SequentialFiles`, with the time of their last use. When a file has to be
+/// closed the least recently used one is chosen, since the points of nearby cells, and so of the same tiles, tend to
+/// come close to each other.
+struct OpenFiles<T> {
+    max_open: usize,
+    uses: u64,
+    open: HashMap<usize, (T, u64)>
+}
+
+impl<T> OpenFiles<T> {
+    fn new(max_open: usize) -> OpenFiles<T> {
+        OpenFiles{max_open, uses: 0, open: HashMap::new()}
+    }
+
+    /// The file `id`, marked as just used, if it is open
+    fn get(&mut self, id: usize) -> Option<&mut T> {
+        self.uses += 1;
+        let uses = self.uses;
+        self.open.get_mut(&id).map(|(file, last_use)| {
+            *last_use = uses;
+            file
+        })
+    }
+
+    /// Removes the least recently used file if there is no room for another one
+    fn make_room(&mut self) -> Option<(usize, T)> {
+        if self.open.len() < self.max_open {
+            return None;
+        }
+        let evicted = self.open.iter().min_by_key(|(_, (_, last_use))| *last_use).map(|(id, _)| *id)?;
+        self.open.remove(&evicted).map(|(file, _)| (evicted, file))
+    }
+
+    /// Adds the file `id`, marked as just used. There must be room for it.
+    fn insert(&mut self, id: usize, file: T) -> &mut T {
+        self.uses += 1;
+        &mut self.open.entry(id).or_insert((file, self.uses)).0
+    }
+}
+
+/// Keeps at most `max_open` files open for appending, closing the least recently used one when another one is needed.
+/// Each file is emptied the first time it is opened, so nothing already in it is read back as written by this run
+struct AppendFiles {
+    open: OpenFiles<BufWriter<File>>,
+    opened: HashSet<usize>
+}
+
+impl AppendFiles {
+    fn new(max_open: usize) -> AppendFiles {
+        AppendFiles{open: OpenFiles::new(max_open), opened: HashSet::new()}
+    }
+
+    fn get(&mut self, id: usize, path: &Path) -> io::Result<&mut BufWriter<File>> {
+        if !self.open.open.contains_key(&id) {
+            if let Some((_, mut writer)) = self.open.make_room() {
+                writer.flush()?;
+            }
+            let file = if self.opened.insert(id) {
+                OpenOptions::new().create(true).write(true).truncate(true).open(path)?
+            } else {
+                OpenOptions::new().append(true).open(path)?
+            };
+            self.open.insert(id, BufWriter::new(file));
+        }
+        Ok(self.open.get(id).unwrap())
+    }
+
+    fn close_all(&mut self) -> io::Result<()> {
+        for (_, (mut writer, _)) in self.open.open.drain() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps at most `max_open` files open for sequential reading, closing the least recently used one when another one
+/// is needed and remembering the position reached in the closed ones
+struct SequentialFiles {
+    open: OpenFiles<BufReader<File>>,
+    positions: HashMap<usize, u64>
+}
+
+impl SequentialFiles {
+    fn new(max_open: usize) -> SequentialFiles {
+        SequentialFiles{open: OpenFiles::new(max_open), positions: HashMap::new()}
+    }
+
+    fn read_exact(&mut self, id: usize, path: &Path, buf: &mut [u8]) -> io::Result<()> {
+        if let Some(reader) = self.open.get(id) {
+            return reader.read_exact(buf);
+        }
+        if let Some((evicted, mut reader)) = self.open.make_room() {
+            self.positions.insert(evicted, reader.stream_position()?);
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        reader.seek(SeekFrom::Start(self.positions.get(&id).copied().unwrap_or(0)))?;
+        self.open.insert(id, reader).read_exact(buf)
+    }
+}
+
+/// Streams the points to the files of the tiles that need them, in the order of the points. The tile of each point is also written to `WorkDir::point_tiles`. Returns the non empty tiles, in
+/// the order of their ids, and the number of points
+fn partition_points<const D: usize, I>(points: I, params: &DBSCANParams, grid: &TileGrid<D>, work_dir: &WorkDir, max_open_files: usize) -> io::Result<(Vec<TileIndex<D>>, usize)>
+where I: IntoIterator<Item = io::Result<Point<D>>>, {
+    let mut tile_ids : HashMap<TileIndex<D>, usize> = HashMap::new();
+    let mut tiles : Vec<TileIndex<D>> = Vec::new();
+    // one file is used for the tiles of the points
+    let mut files = AppendFiles::new(max_open_files.saturating_sub(1).max(1));
+    let mut point_tiles = BufWriter::new(File::create(work_dir.point_tiles())?);
+    let mut record = Vec::with_capacity(8 * D);
+    let mut points_count = 0;
+    for point in points {
+        let point = point?;
+        let cell_index = get_base_cell_index(&point, params);
+        record.clear();
+        for coordinate in &point {
+            record.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        let mut result = Ok(());
+        grid.for_each_tile_around(&cell_index, |tile| {
+            if result.is_err() {
+                return;
+            }
+            let tile_id = *tile_ids.entry(tile).or_insert_with(|| {
+                tiles.push(tile);
+                tiles.len() - 1
+            });
+            result = files.get(tile_id, &work_dir.tile_points(tile_id)).and_then(|w| w.write_all(&record));
+        });
+        result?;
+        let own_tile_id = tile_ids[&grid.tile_of(&cell_index)];
+        point_tiles.write_all(&(own_tile_id as u64).to_le_bytes())?;
+        points_count += 1;
+    }
+    files.close_all()?;
+    point_tiles.flush()?;
+    Ok((tiles, points_count))
+}
+
+/// Union-find structure over all the core cells found so far, identified by their index
+struct CoreCellUnionFind<const D: usize> {
+    ids: HashMap<CellIndex<D>, usize>,
+    parents: Vec<usize>
+}
+
+impl <const D: usize> CoreCellUnionFind<D> {
+    fn new() -> CoreCellUnionFind<D> {
+        CoreCellUnionFind{ids: HashMap::new(), parents: Vec::new()}
+    }
+
+    /// The id of the core cell `cell_index`, that is added to the structure if it is not there yet
+    fn id(&mut self, cell_index: &CellIndex<D>) -> usize {
+        let parents = &mut self.parents;
+        *self.ids.entry(*cell_index).or_insert_with(|| {
+            parents.push(parents.len());
+            parents.len() - 1
+        })
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.parents[id] != id {
+            self.parents[id] = self.parents[self.parents[id]];
+            id = self.parents[id];
+        }
+        id
+    }
+
+    fn union(&mut self, id_1: usize, id_2: usize) {
+        let root_1 = self.find(id_1);
+        let root_2 = self.find(id_2);
+        if root_1 != root_2 {
+            self.parents[root_1.max(root_2)] = root_1.min(root_2);
+        }
+    }
+}
+
+/// Reads the points written by `partition_points` for a tile
+fn read_tile_points<const D: usize>(path: &Path) -> io::Result<Vec<Point<D>>> {
+    let bytes = fs::read(path)?;
+    let points = bytes.chunks_exact(8 * D).map(|record| {
+        let mut point : Point<D> = [0.0; D];
+        for (coordinate, chunk) in point.iter_mut().zip(record.chunks_exact(8)) {
+            let mut buf = [0_u8; 8];
+            buf.copy_from_slice(chunk);
+            *coordinate = f64::from_le_bytes(buf);
+        }
+        point
+    }).collect();
+    Ok(points)
+}
+
+/// Clusters the points of a tile together with the points in its margin. The edges between the core cells of the tile
+/// and their neighbours are added to `core_cells`, and the label of each point of the tile is written to
+/// `WorkDir::tile_labels`, in the order of the points. Each label record holds the id of the core cell the point was
+/// assigned to plus one, or `NO_CORE_CELL` for noise points, followed by `1` if the point is a core point and `0` otherwise.
+fn cluster_tile<const D: usize>(tile_id: usize, tile: &TileIndex<D>, params: &DBSCANParams, grid: &TileGrid<D>, work_dir: &WorkDir, core_cells: &mut CoreCellUnionFind<D>) -> io::Result<()> {
+    let points = read_tile_points::<D>(&work_dir.tile_points(tile_id))?;
+    let mut tile_params = *params;
+    tile_params.cardinality = points.len();
+    // The margin is twice as wide as the reach of the neighbours, so the core points and the trees of the cells
+    // in the tile and of their neighbours are the same that would be found by clustering all the points at once
+    let mut monitor = Monitor::unobserved();
+    let mut cells : CellTable<D> = find_cells(&points, &tile_params, &mut monitor);
+    populate_neighbours(&mut cells, &mut monitor);
+    label_points(&mut cells, &points, &tile_params, &mut monitor);
+    add_tile_edges(tile, &cells, &points, &tile_params, grid, core_cells);
+    let mut point_status = vec![false; points.len()];
+    for cell in cells.values() {
+        for s_point in &cell.points {
+            point_status[s_point.index] = s_point.is_core;
+        }
+    }
+    let mut writer = BufWriter::new(File::create(work_dir.tile_labels(tile_id))?);
+    for (local_i, point) in points.iter().enumerate() {
+        let cell_index = get_base_cell_index(point, &tile_params);
+        if grid.tile_of(&cell_index) != *tile {
+            continue;
+        }
+        let (core_cell, is_core) = if point_status[local_i] {
+            (core_cells.id(&cell_index) as u64 + 1, true)
+        } else {
+            let cell = cells.get(&cell_index).unwrap();
+            let found = cell.neighbour_cell_ids.iter()
+                .map(|&n_id| &cells[n_id])
+                .find(|n| n.is_core && n.core_info.root.approximate_range_nonempty(point, &tile_params));
+            match found {
+                Some(neighbour) => (core_cells.id(&neighbour.index) as u64 + 1, false),
+                None => (NO_CORE_CELL, false)
+            }
+        };
+        writer.write_all(&core_cell.to_le_bytes())?;
+        writer.write_all(&[is_core as u8])?;
+    }
+    writer.flush()
+}
+
+/// Adds to `core_cells` the edges between the core cells of `tile` and their neighbour core cells
+fn add_tile_edges<const D: usize>(tile: &TileIndex<D>, cells: &CellTable<D>, points: &[Point<D>], params: &DBSCANParams, grid: &TileGrid<D>, core_cells: &mut CoreCellUnionFind<D>) {
+    for cell in cells.values().filter(|c| c.is_core && grid.tile_of(&c.index) == *tile) {
+        let cell_id = core_cells.id(&cell.index);
+        for &n_id in &cell.neighbour_cell_ids {
+            let neighbour = &cells[n_id];
+            if !neighbour.is_core || neighbour.index == cell.index {
+                continue;
+            }
+            let neighbour_id = core_cells.id(&neighbour.index);
+            if core_cells.find(cell_id) == core_cells.find(neighbour_id) {
+                continue;
+            }
+            let connected = cell.points.iter()
+                .filter(|p| p.is_core)
+                .any(|p| neighbour.core_info.root.approximate_range_nonempty(&points[p.index], params));
+            if connected {
+                core_cells.union(cell_id, neighbour_id);
+            }
+        }
+    }
+}
+
+/// Writes the labels of all the points in their original order, by reading from the label file of the tile of each
+/// point. Returns the number of clusters and the number of noise points
+fn write_labels_in_order<const D: usize, W: Write>(points_count: usize, work_dir: &WorkDir, core_cells: &mut CoreCellUnionFind<D>, max_open_files: usize, mut labels_out: W) -> io::Result<(usize, usize)> {
+    let mut point_tiles = BufReader::new(File::open(work_dir.point_tiles())?);
+    let mut label_files = SequentialFiles::new(max_open_files.saturating_sub(1).max(1));
+    let mut cluster_ids : HashMap<usize, usize> = HashMap::new();
+    let mut noise_count = 0;
+    let mut tile_buf = [0_u8; 8];
+    let mut core_cell_buf = [0_u8; 8];
+    let mut label_buf = [0_u8; 9];
+    writeln!(labels_out, "{}", LABELS_HEADER)?;
+    for _ in 0..points_count {
+        point_tiles.read_exact(&mut tile_buf)?;
+        let tile_id = u64::from_le_bytes(tile_buf) as usize;
+        label_files.read_exact(tile_id, &work_dir.tile_labels(tile_id), &mut label_buf)?;
+        core_cell_buf.copy_from_slice(&label_buf[..8]);
+        let core_cell = u64::from_le_bytes(core_cell_buf);
+        let label = if core_cell == NO_CORE_CELL {
+            noise_count += 1;
+            PointLabel{cluster_id: NOISE_CLUSTER_INDEX, role: PointRole::Noise}
+        } else {
+            let root = core_cells.find(core_cell as usize - 1);
+            let next_id = cluster_ids.len() + 1;
+            let cluster_id = *cluster_ids.entry(root).or_insert(next_id);
+            let role = if label_buf[8] == 1 { PointRole::Core } else { PointRole::Border };
+            PointLabel{cluster_id, role}
+        };
+        writeln!(labels_out, "{},{}", label.cluster_id, label.role.as_str())?;
+    }
+    labels_out.flush()?;
+    Ok((cluster_ids.len(), noise_count))
+}
+
+/// Reads the labels written by `cluster_out_of_core`. Fails with `InvalidData` if the first line is not the header
+/// written with them or if a label is not valid
+pub fn read_labels<R: Read>(reader: R) -> io::Result<DBSCANLabels> {
+    let invalid = |line_i: usize, message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_i + 1, message));
+    let mut labels = DBSCANLabels::new();
+    let mut lines = io::BufRead::lines(BufReader::new(reader));
+    if lines.next().transpose()?.as_deref() != Some(LABELS_HEADER) {
+        return Err(invalid(0, &format!("expected the header `{}`", LABELS_HEADER)));
+    }
+    for (line_i, line) in (1..).zip(lines) {
+        let line = line?;
+        let (cluster_id, role) = line.split_once(',').ok_or_else(|| invalid(line_i, "expected two values"))?;
+        let cluster_id = cluster_id.parse().map_err(|_| invalid(line_i, "invalid cluster id"))?;
+        let role = match role {
+            "core" => PointRole::Core,
+            "border" => PointRole::Border,
+            "noise" => PointRole::Noise,
+            _ => return Err(invalid(line_i, "invalid role"))
+        };
+        labels.push(PointLabel{cluster_id, role});
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests;
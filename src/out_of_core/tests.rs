@@ -0,0 +1,154 @@
+use super::*;
+use crate::data_io::{params_from_file, read_points_from_file};
+use crate::dbscan::approximate_dbscan_labels;
+use crate::evaluation::adjusted_rand_index;
+
+/// Clusters `file_name` both in memory and out of core and checks that the results are the same. Border points close
+/// to more than one cluster may be assigned to a different one, so the clusters are compared on the core points.
+fn check_same_as_in_memory<const D: usize>(file_name: &str, epsilon: f64, min_pts: usize, config: &OutOfCoreConfig) -> OutOfCoreSummary {
+    let mut params = params_from_file(&file_name);
+    params.epsilon = epsilon;
+    params.rho = 0.1;
+    params.min_pts = min_pts;
+    let points : Vec<Point<D>> = read_points_from_file(&file_name, &params);
+    let expected = approximate_dbscan_labels(&points, &params);
+    let mut output = Vec::new();
+    let summary = cluster_out_of_core(points.iter().map(|p| Ok(*p)), &params, config, &mut output).unwrap();
+    let labels = read_labels(output.as_slice()).unwrap();
+    assert_eq!(labels.len(), expected.len());
+    assert_eq!(summary.points_count, expected.len());
+    for (label, expected_label) in labels.iter().zip(expected.iter()) {
+        assert_eq!(label.role, expected_label.role);
+    }
+    let expected_clusters = expected.iter().map(|l| l.cluster_id).max().unwrap();
+    assert_eq!(summary.clusters_count, expected_clusters);
+    assert_eq!(labels.iter().map(|l| l.cluster_id).max().unwrap(), expected_clusters);
+    assert_eq!(summary.noise_count, expected.iter().filter(|l| l.role == PointRole::Noise).count());
+    let core_ids : Vec<usize> = labels.iter().filter(|l| l.role == PointRole::Core).map(|l| l.cluster_id).collect();
+    let expected_core_ids : Vec<usize> = expected.iter().filter(|l| l.role == PointRole::Core).map(|l| l.cluster_id).collect();
+    assert_eq!(adjusted_rand_index(&core_ids, &expected_core_ids), 1.0);
+    summary
+}
+
+fn small_tiles_config(tile_side: usize) -> OutOfCoreConfig {
+    let mut config = OutOfCoreConfig::new(&std::env::temp_dir());
+    config.tile_side = tile_side;
+    config.max_open_files = 3;
+    config
+}
+
+#[test]
+fn same_as_in_memory_2d_test() {
+    let summary = check_same_as_in_memory::<2>("datasets/out_test_1.txt", 0.3, 10, &small_tiles_config(4));
+    assert_eq!(summary.clusters_count, 5);
+    assert!(summary.tiles_count > 1);
+    let summary = check_same_as_in_memory::<2>("datasets/out_test_1.txt", 0.1, 20, &small_tiles_config(8));
+    assert!(summary.noise_count > 0);
+}
+
+#[test]
+fn same_as_in_memory_3d_test() {
+    let summary = check_same_as_in_memory::<3>("datasets/out_test_2.txt", 0.3, 10, &small_tiles_config(8));
+    assert_eq!(summary.clusters_count, 8);
+    assert!(summary.tiles_count > 1);
+    check_same_as_in_memory::<3>("datasets/out_test_2.txt", 0.15, 30, &OutOfCoreConfig::new(&std::env::temp_dir()));
+}
+
+#[test]
+fn tiles_around_test() {
+    let grid = TileGrid::<2>{tile_side: 4, reach: 2};
+    let mut tiles = Vec::new();
+    grid.for_each_tile_around(&[0, 5], |t| tiles.push(t));
+    assert_eq!(tiles, vec![[-1, 0], [0, 0], [-1, 1], [0, 1]]);
+    assert_eq!(grid.tile_of(&[-1, 5]), [-1, 1]);
+    assert_eq!(neighbour_reach::<1>(), 1);
+    assert_eq!(neighbour_reach::<2>(), 2);
+    assert_eq!(neighbour_reach::<3>(), 3);
+}
+
+#[test]
+fn least_recently_used_files_test() {
+    let mut open = OpenFiles::new(2);
+    assert!(open.make_room().is_none());
+    open.insert(1, "a");
+    open.insert(2, "b");
+    assert_eq!(open.get(1), Some(&mut "a"));
+    assert_eq!(open.make_room(), Some((2, "b")));
+    open.insert(3, "c");
+    assert_eq!(open.get(2), None);
+    assert_eq!(open.make_room(), Some((1, "a")));
+
+    // the files closed to make room are reopened where they were left
+    let work_dir = WorkDir::create(&std::env::temp_dir()).unwrap();
+    let mut writers = AppendFiles::new(2);
+    for i in 0..30_u8 {
+        writers.get((i % 3) as usize, &work_dir.tile_points((i % 3) as usize)).unwrap().write_all(&[i]).unwrap();
+    }
+    writers.close_all().unwrap();
+    let mut readers = SequentialFiles::new(2);
+    let mut byte = [0];
+    for i in 0..30_u8 {
+        readers.read_exact((i % 3) as usize, &work_dir.tile_points((i % 3) as usize), &mut byte).unwrap();
+        assert_eq!(byte[0], i);
+    }
+}
+
+#[test]
+fn stale_work_dir_test() {
+    let parent = std::env::temp_dir().join(format!("appr_dbscan_stale_work_dir_test_{}", std::process::id()));
+    // the directories and the tile files that a killed run with the next names would have left
+    let next_run = RUN_COUNTER.load(Ordering::Relaxed);
+    for run in next_run..next_run + 3 {
+        let stale = parent.join(format!("appr_dbscan_out_of_core_{}_{}", std::process::id(), run));
+        fs::create_dir_all(&stale).unwrap();
+        fs::write(stale.join("tile_0.points"), [100, 100, 100]).unwrap();
+    }
+    let work_dir = WorkDir::create(&parent).unwrap();
+    assert!(fs::read_dir(&work_dir.path).unwrap().next().is_none());
+    // a tile file that is already there is emptied when it is first opened
+    fs::write(work_dir.tile_points(0), [100, 100, 100]).unwrap();
+    let mut writers = AppendFiles::new(1);
+    writers.get(0, &work_dir.tile_points(0)).unwrap().write_all(&[1]).unwrap();
+    writers.get(1, &work_dir.tile_points(1)).unwrap().write_all(&[2]).unwrap();
+    writers.get(0, &work_dir.tile_points(0)).unwrap().write_all(&[3]).unwrap();
+    writers.close_all().unwrap();
+    assert_eq!(fs::read(work_dir.tile_points(0)).unwrap(), vec![1, 3]);
+    drop(work_dir);
+    fs::remove_dir_all(&parent).unwrap();
+}
+
+#[test]
+fn read_labels_test() {
+    let labels = read_labels("cluster_id,role\n1,core\n0,noise\n1,border\n".as_bytes()).unwrap();
+    assert_eq!(labels, vec![
+        PointLabel{cluster_id: 1, role: PointRole::Core},
+        PointLabel{cluster_id: 0, role: PointRole::Noise},
+        PointLabel{cluster_id: 1, role: PointRole::Border}
+    ]);
+    assert!(read_labels("cluster_id,role\n".as_bytes()).unwrap().is_empty());
+    // the first label is not taken for a header
+    for bad in ["1,core\n0,noise\n", "", "cluster,role\n1,core\n", "cluster_id,role\n1,core\n2\n", "cluster_id,role\n1,center\n"] {
+        let error = read_labels(bad.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", bad);
+    }
+    let error = read_labels("1,core\n".as_bytes()).unwrap_err();
+    assert!(error.to_string().starts_with("line 1:"), "{}", error);
+    let error = read_labels("cluster_id,role\n1,core\nx,core\n".as_bytes()).unwrap_err();
+    assert!(error.to_string().starts_with("line 3:"), "{}", error);
+}
+
+#[test]
+fn file_out_of_core_test() {
+    let mut params = params_from_file(&"datasets/out_test_small.txt");
+    params.epsilon = 0.3;
+    params.rho = 0.1;
+    params.min_pts = 3;
+    let output_file = std::env::temp_dir().join("appr_dbscan_out_of_core_test.csv");
+    let config = OutOfCoreConfig::new(&std::env::temp_dir());
+    let summary = cluster_file_out_of_core::<_, _, 2>(&"datasets/out_test_small.txt", &output_file, &params, &config).unwrap();
+    assert_eq!(summary.points_count, params.cardinality);
+    let labels = read_labels(File::open(&output_file).unwrap()).unwrap();
+    assert_eq!(labels.len(), params.cardinality);
+    let error = cluster_file_out_of_core::<_, _, 3>(&"datasets/out_test_small.txt", &output_file, &params, &config).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
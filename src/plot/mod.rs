@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::path::{Path};
+use crate::utils::{DBSCANLabels, PointRole, get_cell_index};
+use crate::data_io::PALETTE_ARR;
+
+/// Color used to draw noise points
+const NOISE_COLOR : &str = "#999999";
+/// Color used to draw the grid cells
+const GRID_COLOR : &str = "#cccccc";
+/// Space left empty around the plotted points, in pixels
+const MARGIN : f64 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// How points with more than two coordinates are projected on the plane of the plot
+pub enum Projection {
+    /// Uses the coordinates at the two given indexes as horizontal and vertical axis
+    Axes(usize, usize),
+    /// Isometric view of the first three coordinates
+    Isometric
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Configuration of the SVG scatter plot drawn by `render_svg`
+pub struct PlotConfig {
+    /// Width of the image in pixels
+    pub width: f64,
+    /// Height of the image in pixels
+    pub height: f64,
+    /// Radius of the glyph of each point, in pixels
+    pub point_radius: f64,
+    /// How the points are projected on the plane of the plot
+    pub projection: Projection,
+    /// If set, the non empty cells of the grid with side `epsilon/sqrt(D)` used by the approximate DBSCAN algorithm
+    /// are drawn below the points. Only supported with `Projection::Axes`.
+    pub grid_epsilon: Option<f64>
+}
+
+impl Default for PlotConfig {
+    fn default() -> PlotConfig {
+        PlotConfig {
+            width: 800.0,
+            height: 800.0,
+            point_radius: 2.0,
+            projection: Projection::Axes(0, 1),
+            grid_epsilon: None
+        }
+    }
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Projects `point` on the plane, with the vertical axis pointing upwards
+fn project(point: &[f64], projection: Projection) -> Option<(f64, f64)> {
+    match projection {
+        Projection::Axes(h, v) => Some((*point.get(h)?, *point.get(v)?)),
+        Projection::Isometric => {
+            let (x, y, z) = (*point.first()?, *point.get(1)?, *point.get(2)?);
+            let cos_30 = 3_f64.sqrt() / 2.0;
+            Some(((x - y) * cos_30, z - (x + y) / 2.0))
+        }
+    }
+}
+
+/// Renders the points in `points` as an SVG scatter plot, using the labels in `labels` to choose how to draw them.
+/// Clusters are drawn with the same high contrast colors used for the BMP images, noise points are grey. Core points are 
+/// drawn as circles and border points as squares. Both axis are scaled by the same factor so that distances are preserved.
+/// 
+/// # Errors
+/// 
+/// Returns an error of kind `InvalidInput` if the number of points and labels differ, if the projection needs coordinates that 
+/// the points do not have or if the grid is requested with an isometric projection.
+pub fn render_svg<Q: AsRef<[f64]>>(points: &[Q], labels: &DBSCANLabels, config: &PlotConfig) -> io::Result<String> {
+    if points.len() != labels.len() {
+        return Err(invalid_input(format!("{} points were given but there are {} labels", points.len(), labels.len())));
+    }
+    if config.grid_epsilon.is_some() && config.projection == Projection::Isometric {
+        return Err(invalid_input("the grid can only be drawn when projecting on two axis".to_string()));
+    }
+    let mut projected : Vec<(f64, f64)> = Vec::with_capacity(points.len());
+    for point in points {
+        let point = point.as_ref();
+        match project(point, config.projection) {
+            Some(p) => projected.push(p),
+            None => return Err(invalid_input(format!("can not use projection {:?} on points with {} coordinates", config.projection, point.len())))
+        }
+    }
+    // cells are collected as (horizontal index, vertical index) of the grid projected on the plot plane
+    let mut grid_cells : Vec<(i64, i64)> = Vec::new();
+    let mut side_size = 0.0;
+    if let (Some(epsilon), Projection::Axes(h, v)) = (config.grid_epsilon, config.projection) {
+        let dimensionality = points.first().map_or(1, |p| p.as_ref().len());
+        side_size = epsilon / (dimensionality as f64).sqrt();
+        let cells : HashSet<(i64, i64)> = points.iter().map(|p| {
+            let p = p.as_ref();
+            (get_cell_index(&[p[h]], side_size)[0], get_cell_index(&[p[v]], side_size)[0])
+        }).collect();
+        grid_cells = cells.into_iter().collect();
+        grid_cells.sort_unstable();
+    }
+
+    let mut min = (f64::INFINITY, f64::INFINITY);
+    let mut max = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for &(x, y) in &projected {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    for &(i, j) in &grid_cells {
+        min = (min.0.min((i as f64 - 0.5) * side_size), min.1.min((j as f64 - 0.5) * side_size));
+        max = (max.0.max((i as f64 + 0.5) * side_size), max.1.max((j as f64 + 0.5) * side_size));
+    }
+    let span = ((max.0 - min.0).max(max.1 - min.1)).max(f64::EPSILON);
+    let scale = ((config.width - 2.0 * MARGIN).min(config.height - 2.0 * MARGIN) / span).max(0.0);
+    let to_svg = |x: f64, y: f64| (MARGIN + (x - min.0) * scale, config.height - MARGIN - (y - min.1) * scale);
+
+    let mut svg = String::new();
+    // writing to a String never fails
+    let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">", w = config.width, h = config.height);
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+    if !grid_cells.is_empty() {
+        let _ = writeln!(svg, "<g id=\"grid\" fill=\"none\" stroke=\"{}\" stroke-width=\"1\">", GRID_COLOR);
+        let cell_size = side_size * scale;
+        for &(i, j) in &grid_cells {
+            let (x, y) = to_svg((i as f64 - 0.5) * side_size, (j as f64 + 0.5) * side_size);
+            let _ = writeln!(svg, "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"/>", x, y, cell_size, cell_size);
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+    // the indexes of the points of each cluster, so that each group is written without going through all the points
+    let mut groups : Vec<Vec<usize>> = vec![Vec::new(); clusters_count + 1];
+    for (i, label) in labels.iter().enumerate() {
+        groups[label.cluster_id].push(i);
+    }
+    for (i_cluster, group) in groups.iter().enumerate() {
+        let color = if i_cluster == 0 {
+            NOISE_COLOR.to_string()
+        } else {
+            let rgb = PALETTE_ARR[i_cluster % PALETTE_ARR.len()];
+            format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+        };
+        let group_id = if i_cluster == 0 { "noise".to_string() } else { format!("cluster-{}", i_cluster) };
+        let _ = writeln!(svg, "<g id=\"{}\" fill=\"{}\">", group_id, color);
+        for &i in group {
+            let (x, y) = to_svg(projected[i].0, projected[i].1);
+            let label = &labels[i];
+            let r = config.point_radius;
+            match label.role {
+                PointRole::Border => {
+                    let _ = writeln!(svg, "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"/>", x - r, y - r, 2.0 * r, 2.0 * r);
+                },
+                PointRole::Core | PointRole::Noise => {
+                    let _ = writeln!(svg, "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\"/>", x, y, r);
+                }
+            }
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+    let _ = writeln!(svg, "</svg>");
+    Ok(svg)
+}
+
+/// Same as `render_svg` but writes the plot to `file_name`
+pub fn write_svg<P, Q>(file_name: &P, points: &[Q], labels: &DBSCANLabels, config: &PlotConfig) -> io::Result<()>
+where P: AsRef<Path>, Q: AsRef<[f64]>, {
+    let svg = render_svg(points, labels, config)?;
+    std::fs::write(file_name, svg)
+}
+
+#[cfg(test)]
+mod tests;
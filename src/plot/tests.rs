@@ -0,0 +1,54 @@
+use super::*;
+use crate::utils::PointLabel;
+
+fn labeled_points() -> (Vec<[f64; 3]>, DBSCANLabels) {
+    let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 1.0], [4.0, 4.0, 2.0], [2.0, 2.0, 0.5]];
+    let labels = vec![
+        PointLabel{cluster_id: 1, role: PointRole::Core},
+        PointLabel{cluster_id: 1, role: PointRole::Border},
+        PointLabel{cluster_id: 2, role: PointRole::Core},
+        PointLabel{cluster_id: 0, role: PointRole::Noise},
+    ];
+    (points, labels)
+}
+
+#[test]
+fn render_svg_test() {
+    let (points, labels) = labeled_points();
+    let config = PlotConfig{width: 100.0, height: 60.0, ..PlotConfig::default()};
+    let svg = render_svg(&points, &labels, &config).unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"60\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<circle").count(), 3);
+    assert_eq!(svg.matches("<rect").count(), 2);
+    assert!(svg.contains("<g id=\"noise\" fill=\"#999999\">"));
+    let rgb = PALETTE_ARR[2];
+    assert!(svg.contains(&format!("<g id=\"cluster-2\" fill=\"#{:02x}{:02x}{:02x}\">", rgb[0], rgb[1], rgb[2])));
+    // the plot area is 40x40 pixels: the first point is in the bottom left corner and the third in the top right one
+    assert!(svg.contains("<circle cx=\"10.00\" cy=\"50.00\" r=\"2.00\"/>"));
+    assert!(svg.contains("<circle cx=\"50.00\" cy=\"10.00\" r=\"2.00\"/>"));
+}
+
+#[test]
+fn render_svg_projections_test() {
+    let (points, labels) = labeled_points();
+    let config = PlotConfig{projection: Projection::Axes(0, 2), ..PlotConfig::default()};
+    assert!(render_svg(&points, &labels, &config).is_ok());
+    let config = PlotConfig{projection: Projection::Isometric, ..PlotConfig::default()};
+    assert!(render_svg(&points, &labels, &config).is_ok());
+    let config = PlotConfig{projection: Projection::Axes(0, 3), ..PlotConfig::default()};
+    assert!(render_svg(&points, &labels, &config).is_err());
+    let config = PlotConfig{projection: Projection::Isometric, grid_epsilon: Some(1.0), ..PlotConfig::default()};
+    assert!(render_svg(&points, &labels, &config).is_err());
+    assert!(render_svg(&points[1..], &labels, &PlotConfig::default()).is_err());
+}
+
+#[test]
+fn render_svg_grid_test() {
+    let points = vec![[0.0, 0.0], [0.1, 0.1], [3.0, 0.0]];
+    let labels = vec![PointLabel{cluster_id: 0, role: PointRole::Noise}; 3];
+    let config = PlotConfig{grid_epsilon: Some(2_f64.sqrt()), ..PlotConfig::default()};
+    let svg = render_svg(&points, &labels, &config).unwrap();
+    let grid = &svg[svg.find("<g id=\"grid\"").unwrap()..svg.find("<g id=\"noise\"").unwrap()];
+    assert_eq!(grid.matches("<rect").count(), 2);
+}
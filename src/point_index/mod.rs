@@ -0,0 +1,130 @@
+//! A small kd-tree over the points of a single cell, used to count the points of a neighbour cell that are in range of
+//! a point without measuring all of them. The nodes that are entirely in range are counted whole and the ones entirely
+//! out of range are skipped, so only the leaves crossed by the border of the ball are read one point at a time.
+
+use crate::utils::*;
+use std::cmp::Ordering;
+
+/// The nodes with at most this many points are not split. Measuring the points of a leaf in batches with
+/// `count_within_sq` costs about as much as testing the boxes of a few nodes, so smaller leaves only make the
+/// tree deeper without reading fewer coordinates.
+pub(crate) const LEAF_POINTS: usize = 32;
+
+#[derive(Clone, Debug)]
+/// A node of a `PointIndex`, with the smallest box that contains its points
+struct IndexNode<F: Float, const D: usize> {
+    /// The lowest coordinate of the points of the node on each axis
+    low: Point<D, F>,
+    /// The highest coordinate of the points of the node on each axis
+    high: Point<D, F>,
+    /// The position of the first point of the node in `PointIndex::axes`
+    start: u32,
+    /// The position after the last point of the node in `PointIndex::axes`
+    end: u32,
+    /// The position in the arena of the first node after the sub-tree of this node
+    next: u32
+}
+
+#[derive(Clone, Debug)]
+/// A kd-tree over the points of a cell. The nodes are stored in a single vector in depth first order, like the ones of
+/// `TreeStructure`, and the points of each node are contiguous in the coordinates.
+pub(crate) struct PointIndex<F: Float, const D: usize> {
+    /// The coordinates of the points one axis at a time, in the order of the leaves
+    axes: [Vec<F>; D],
+    /// All the nodes of the tree, the root first. A node that is not a leaf is followed by its two children
+    nodes: Vec<IndexNode<F, D>>
+}
+
+impl <F: Float, const D: usize> PointIndex<F, D> {
+    /// Builds the tree on `points`, usually the ones of a cell. Each node is split at the median of the axis where its
+    /// points are spread the most, so the tree is balanced and takes `O(n log n)` time to build.
+    pub fn build<I: IntoIterator<Item = Point<D, F>>>(points: I) -> PointIndex<F, D> {
+        let mut cell_points : Vec<Point<D, F>> = points.into_iter().collect();
+        let mut nodes = Vec::new();
+        if !cell_points.is_empty() {
+            build_node(&mut cell_points, 0, &mut nodes);
+        }
+        let axes = std::array::from_fn(|i| cell_points.iter().map(|p| p[i]).collect());
+        PointIndex{axes, nodes}
+    }
+
+    /// Counts the points at squared distance at most `epsilon_sq` from `q`, stopping as soon as `limit` points are found:
+    /// the result is exact if it is less than `limit`. The boxes of the nodes are measured with the same operations used
+    /// for the points, so a node is counted whole or skipped only when each of its points would be.
+    pub fn count_within_sq(&self, q: &Point<D, F>, epsilon_sq: F, limit: usize) -> usize {
+        let mut count = 0;
+        let mut i = 0;
+        while i < self.nodes.len() && count < limit {
+            let node = &self.nodes[i];
+            let (near_sq, far_sq) = box_distance_bounds_sq(q, &node.low, &node.high);
+            let is_leaf = node.next as usize == i + 1;
+            if far_sq <= epsilon_sq {
+                count += (node.end - node.start) as usize;
+            } else if near_sq <= epsilon_sq && is_leaf {
+                count += count_within_sq(&self.axes, node.start as usize..node.end as usize, q, epsilon_sq);
+            } else if near_sq <= epsilon_sq {
+                // visits the children, that directly follow the node
+                i += 1;
+                continue;
+            }
+            i = node.next as usize;
+        }
+        count
+    }
+}
+
+/// Appends the node with `points`, that start at `offset` in the coordinates of the tree, and its sub-tree to `nodes`
+fn build_node<F: Float, const D: usize>(points: &mut [Point<D, F>], offset: usize, nodes: &mut Vec<IndexNode<F, D>>) {
+    let mut low = points[0];
+    let mut high = points[0];
+    for point in points.iter() {
+        for i in 0..D {
+            if point[i] < low[i] {
+                low[i] = point[i];
+            }
+            if point[i] > high[i] {
+                high[i] = point[i];
+            }
+        }
+    }
+    let position = nodes.len();
+    nodes.push(IndexNode{low, high, start: offset as u32, end: (offset + points.len()) as u32, next: 0});
+    if points.len() > LEAF_POINTS {
+        let mut axis = 0;
+        for i in 1..D {
+            if high[i] - low[i] > high[axis] - low[axis] {
+                axis = i;
+            }
+        }
+        let middle = points.len() / 2;
+        points.select_nth_unstable_by(middle, |a, b| a[axis].partial_cmp(&b[axis]).unwrap_or(Ordering::Equal));
+        let (left, right) = points.split_at_mut(middle);
+        build_node(left, offset, nodes);
+        build_node(right, offset + middle, nodes);
+    }
+    nodes[position].next = nodes.len() as u32;
+}
+
+/// The squared distances of `q` from the closest and the farthest point of the box from `low` to `high`. They are
+/// computed axis by axis as in `count_within_sq`, and since rounding never changes the order of the differences and
+/// of their squares, no point in the box can be measured closer than the first value or farther than the second one.
+fn box_distance_bounds_sq<F: Float, const D: usize>(q: &Point<D, F>, low: &Point<D, F>, high: &Point<D, F>) -> (F, F) {
+    let zero = F::default();
+    let mut near_sq = F::default();
+    let mut far_sq = F::default();
+    for i in 0..D {
+        let to_low = low[i] - q[i];
+        let to_high = high[i] - q[i];
+        let (low_sq, high_sq) = (to_low * to_low, to_high * to_high);
+        if to_low > zero {
+            near_sq += low_sq;
+        } else if to_high < zero {
+            near_sq += high_sq;
+        }
+        far_sq += if low_sq > high_sq { low_sq } else { high_sq };
+    }
+    (near_sq, far_sq)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,73 @@
+use super::*;
+use crate::generate::{blobs, uniform};
+use proptest::prelude::*;
+
+/// Checks the counts of the tree on `points` against the points measured one at a time, with and without a limit
+fn check_counts<F: Float, const D: usize>(points: &[Point<D, F>], q: Point<D, F>, epsilon: F) -> Result<(), TestCaseError> {
+    let index = PointIndex::build(points.iter().copied());
+    let epsilon_sq = epsilon * epsilon;
+    let expected = points.iter().filter(|p| squared_euclidean_distance(p, &q) <= epsilon_sq).count();
+    prop_assert_eq!(index.count_within_sq(&q, epsilon_sq, usize::MAX), expected);
+    for limit in [1, 5, 40] {
+        let count = index.count_within_sq(&q, epsilon_sq, limit);
+        prop_assert!(count == expected || (count >= limit && expected >= limit), "{} of {} with limit {}", count, expected, limit);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn count_2d_prop(points in prop::collection::vec(prop::array::uniform2(-2.0..2.0), 0..300), q in prop::array::uniform2(-3.0..3.0), epsilon in 0.1..3.0) {
+        check_counts(&points, q, epsilon)?;
+    }
+
+    #[test]
+    fn count_3d_f32_prop(points in prop::collection::vec(prop::array::uniform3(-2.0_f32..2.0), 0..300), q in prop::array::uniform3(-3.0_f32..3.0), epsilon in 0.1_f32..3.0) {
+        check_counts(&points, q, epsilon)?;
+    }
+
+    #[test]
+    fn count_7d_prop(points in prop::collection::vec(prop::array::uniform7(-2.0..2.0), 0..300), q in prop::array::uniform7(-3.0..3.0), epsilon in 0.5..5.0) {
+        check_counts(&points, q, epsilon)?;
+    }
+}
+
+#[test]
+fn layout_test() {
+    let points : Vec<Point<3>> = vector_input_to_array_input(uniform(1000, 3, 1.0, 1).points);
+    let index = PointIndex::build(points.iter().copied());
+    assert_eq!(index.nodes[0].end, 1000);
+    assert_eq!(index.nodes[0].next as usize, index.nodes.len());
+    for (i, node) in index.nodes.iter().enumerate() {
+        let len = (node.end - node.start) as usize;
+        let is_leaf = node.next as usize == i + 1;
+        assert_eq!(is_leaf, len <= LEAF_POINTS);
+        if !is_leaf {
+            // the children split the points of the node in two halves
+            let left = &index.nodes[i + 1];
+            let right = &index.nodes[left.next as usize];
+            assert_eq!((left.start, left.end, right.start, right.end), (node.start, node.start + len as u32 / 2, node.start + len as u32 / 2, node.end));
+            assert_eq!(right.next, node.next);
+        }
+        for j in node.start as usize..node.end as usize {
+            assert!((0..3).all(|a| node.low[a] <= index.axes[a][j] && index.axes[a][j] <= node.high[a]));
+        }
+    }
+    assert_eq!(PointIndex::<f64, 2>::build(Vec::new()).count_within_sq(&[0.0, 0.0], 1.0, 10), 0);
+}
+
+#[test]
+fn boundary_test() {
+    // points exactly at distance epsilon, and nodes whose box touches the ball, are counted
+    let mut points : Vec<Point<2>> = (0..100).map(|i| [1.0, i as f64 / 100.0]).collect();
+    points.extend((0..100).map(|i| [i as f64 / 100.0 - 2.0, 0.0]));
+    let index = PointIndex::build(points.iter().copied());
+    assert_eq!(index.count_within_sq(&[0.0, 0.0], 1.0, usize::MAX), 1);
+    assert_eq!(index.count_within_sq(&[1.0, 0.0], 1.0, usize::MAX), 100);
+    assert_eq!(index.count_within_sq(&[-1.0, 0.0], 1.0, usize::MAX), 100);
+    let dataset = blobs(2000, 2, 1, 1.0, 3);
+    let points : Vec<Point<2>> = vector_input_to_array_input(dataset.points);
+    let index = PointIndex::build(points.iter().copied());
+    assert_eq!(index.count_within_sq(&[0.0, 0.0], 1e6, usize::MAX), 2000);
+    assert_eq!(index.count_within_sq(&[0.0, 0.0], 1e6, 7), 2000);
+}
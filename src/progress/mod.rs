@@ -0,0 +1,250 @@
+//! Progress reporting and cancellation for long runs of the approximate DBSCAN algorithm.
+//!
+//! A `ProgressObserver` receives a `ProgressEvent` when each stage of the algorithm starts and finishes, and periodically
+//! while the stage runs. A `CancellationToken` can be shared with another thread, for example a user interface, to stop
+//! a run: the token is checked between the cells, so the run stops shortly after `CancellationToken::cancel` is called.
+
+use crate::stats::RunStats;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How many progress events are sent at most for each stage, other than the start and the finish
+const PROGRESS_EVENTS_PER_STAGE: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+/// The stages of the approximate DBSCAN algorithm, in the order in which they run
+pub enum Stage {
+    /// The points are assigned to the cells of the grid. The total is the number of points and the final count
+    /// is the number of non empty cells
+    FindCells,
+    /// The neighbours of each cell are found. The total is the number of cells and the final count is the
+    /// number of neighbour pairs, each cell being a neighbour of itself
+    PopulateNeighbours,
+    /// The core points and the core cells are found. The total is the number of cells and the final count is the
+    /// number of core cells
+    LabelPoints,
+    /// The core cells with core points close enough are connected. The total is the number of cells and the final
+    /// count is the number of connections that merged two groups of core cells
+    ComputeAdjacency,
+    /// The groups of connected core cells become the clusters. The total is the number of core cells and the final
+    /// count is the number of clusters
+    FindComponents,
+    /// The non core points are assigned to a cluster or to the noise. The total is the number of cells and the final
+    /// count is the number of noise points
+    AssignBorderPoints
+}
+
+impl Stage {
+    /// All the stages, in the order in which they run
+    pub const ALL: [Stage; 6] = [Stage::FindCells, Stage::PopulateNeighbours, Stage::LabelPoints, Stage::ComputeAdjacency, Stage::FindComponents, Stage::AssignBorderPoints];
+
+    /// Lowercase name of the stage, with words separated by spaces
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::FindCells => "find cells",
+            Stage::PopulateNeighbours => "populate neighbours",
+            Stage::LabelPoints => "label points",
+            Stage::ComputeAdjacency => "compute adjacency",
+            Stage::FindComponents => "find components",
+            Stage::AssignBorderPoints => "assign border points"
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// An event sent to a `ProgressObserver`. See `Stage` for the meaning of the totals and of the counts of each stage
+pub enum ProgressEvent {
+    /// A stage started and will process `total` elements
+    StageStarted { stage: Stage, total: usize },
+    /// A stage processed `done` elements out of `total`
+    StageProgress { stage: Stage, done: usize, total: usize },
+    /// A stage finished in `elapsed` time and produced `count` elements
+    StageFinished { stage: Stage, count: usize, elapsed: Duration }
+}
+
+/// Receives the progress of a run of the approximate DBSCAN algorithm. It is implemented for all the closures
+/// that take a `ProgressEvent`
+pub trait ProgressObserver {
+    /// Called for each event of the run, on the thread that runs the algorithm
+    fn on_event(&mut self, event: ProgressEvent);
+}
+
+impl <T: FnMut(ProgressEvent)> ProgressObserver for T {
+    fn on_event(&mut self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+/// A flag shared between the clones of the token that stops the runs it is given to once set
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    /// A new token that is not cancelled
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Asks the runs that use this token, or one of its clones, to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Tells if `cancel` was called on this token or on one of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The error returned by a run stopped through a `CancellationToken`
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the clustering was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Follows a run of the algorithm, sending the events to the observer, checking the cancellation token and collecting
+/// the `RunStats`. The stages call `Monitor::step` for each element they process and stop early if it returns `false`,
+/// then `Monitor::finish` tells the caller that the run was cancelled.
+pub(crate) struct Monitor<'a> {
+    observer: Option<&'a mut dyn ProgressObserver>,
+    token: Option<&'a CancellationToken>,
+    stage: Stage,
+    started: Instant,
+    done: usize,
+    total: usize,
+    next_report: usize,
+    cancelled: bool,
+    stats: RunStats,
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::span::EnteredSpan>
+}
+
+impl <'a> Monitor<'a> {
+    /// A monitor that sends no events and can not be cancelled
+    pub(crate) fn unobserved() -> Monitor<'static> {
+        Monitor::with(None, None)
+    }
+
+    pub(crate) fn new(observer: &'a mut dyn ProgressObserver, token: &'a CancellationToken) -> Monitor<'a> {
+        Monitor::with(Some(observer), Some(token))
+    }
+
+    fn with(observer: Option<&'a mut dyn ProgressObserver>, token: Option<&'a CancellationToken>) -> Monitor<'a> {
+        Monitor{
+            observer,
+            token,
+            stage: Stage::FindCells,
+            started: Instant::now(),
+            done: 0,
+            total: 0,
+            next_report: 0,
+            cancelled: false,
+            stats: RunStats::default(),
+            #[cfg(feature = "tracing")]
+            span: None
+        }
+    }
+
+    /// Starts `stage`, that will process `total` elements
+    pub(crate) fn start(&mut self, stage: Stage, total: usize) {
+        self.stage = stage;
+        self.started = Instant::now();
+        self.done = 0;
+        self.total = total;
+        self.next_report = Self::report_interval(total);
+        #[cfg(feature = "tracing")]
+        {
+            self.span = None;
+            self.span = Some(tracing::info_span!("stage", name = stage.as_str(), total).entered());
+        }
+        self.send(ProgressEvent::StageStarted{stage, total});
+    }
+
+    /// Records that one more element of the current stage was processed. Returns `false` if the run was cancelled
+    pub(crate) fn step(&mut self) -> bool {
+        if let Some(token) = self.token {
+            if token.is_cancelled() {
+                self.cancelled = true;
+                return false;
+            }
+        }
+        self.done += 1;
+        if self.done >= self.next_report && self.done < self.total {
+            self.next_report += Self::report_interval(self.total);
+            let (stage, done, total) = (self.stage, self.done, self.total);
+            self.send(ProgressEvent::StageProgress{stage, done, total});
+        }
+        true
+    }
+
+    /// Ends the current stage, that produced `count` elements. Fails if the stage was stopped because the run was cancelled
+    pub(crate) fn finish(&mut self, count: usize) -> Result<(), Cancelled> {
+        if self.cancelled {
+            return Err(Cancelled);
+        }
+        let (stage, elapsed) = (self.stage, self.started.elapsed());
+        match stage {
+            Stage::FindCells => self.stats.cells_count = count,
+            Stage::PopulateNeighbours if self.stats.cells_count > 0 => {
+                self.stats.average_neighbours = count as f64 / self.stats.cells_count as f64;
+            },
+            Stage::LabelPoints => self.stats.core_cells_count = count,
+            _ => {}
+        }
+        self.stats.stage_times.push((stage, elapsed));
+        #[cfg(feature = "tracing")]
+        {
+            tracing::debug!(stage = stage.as_str(), count, elapsed_ms = elapsed.as_secs_f64() * 1000.0, "stage finished");
+            self.span = None;
+        }
+        self.send(ProgressEvent::StageFinished{stage, count, elapsed});
+        Ok(())
+    }
+
+    /// Records that an approximate range counting query was made
+    pub(crate) fn count_range_query(&mut self) {
+        self.stats.range_queries_count += 1;
+    }
+
+    /// Records the total number of nodes of the trees of the core cells
+    pub(crate) fn set_tree_nodes_count(&mut self, count: usize) {
+        self.stats.tree_nodes_count = count;
+    }
+
+    /// The statistics collected so far
+    pub(crate) fn into_stats(self) -> RunStats {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            cells = self.stats.cells_count,
+            core_cells = self.stats.core_cells_count,
+            average_neighbours = self.stats.average_neighbours,
+            tree_nodes = self.stats.tree_nodes_count,
+            range_queries = self.stats.range_queries_count,
+            "run finished"
+        );
+        self.stats
+    }
+
+    fn report_interval(total: usize) -> usize {
+        (total / PROGRESS_EVENTS_PER_STAGE).max(1)
+    }
+
+    fn send(&mut self, event: ProgressEvent) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
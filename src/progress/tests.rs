@@ -0,0 +1,64 @@
+use super::*;
+use crate::data_io::{params_from_file, read_points_from_file};
+use crate::dbscan::{approximate_dbscan, approximate_dbscan_observed, approximate_dbscan_labels_observed};
+use crate::utils::Point;
+
+fn test_points() -> (Vec<Point<2>>, crate::utils::DBSCANParams) {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    (points, params)
+}
+
+#[test]
+fn stage_events_test() {
+    let (points, params) = test_points();
+    let mut events = Vec::new();
+    let res = approximate_dbscan_observed(&points, &params, &mut |e| events.push(e), &CancellationToken::new()).unwrap();
+    assert_eq!(res.len(), approximate_dbscan(&points, &params).len());
+    let started : Vec<Stage> = events.iter().filter_map(|e| match e {
+        ProgressEvent::StageStarted{stage, ..} => Some(*stage),
+        _ => None
+    }).collect();
+    assert_eq!(started, Stage::ALL.to_vec());
+    let finished : Vec<(Stage, usize)> = events.iter().filter_map(|e| match e {
+        ProgressEvent::StageFinished{stage, count, ..} => Some((*stage, *count)),
+        _ => None
+    }).collect();
+    assert_eq!(finished.len(), Stage::ALL.len());
+    assert_eq!(finished[4], (Stage::FindComponents, res.len() - 1));
+    assert_eq!(finished[5], (Stage::AssignBorderPoints, res[0].len()));
+    match events[0] {
+        ProgressEvent::StageStarted{stage: Stage::FindCells, total} => assert_eq!(total, points.len()),
+        _ => panic!("unexpected first event {:?}", events[0])
+    }
+    for event in &events {
+        if let ProgressEvent::StageProgress{done, total, ..} = event {
+            assert!(done < total);
+        }
+    }
+    let progress_count = events.iter().filter(|e| matches!(e, ProgressEvent::StageProgress{stage: Stage::FindCells, ..})).count();
+    assert!(progress_count > 0 && progress_count < PROGRESS_EVENTS_PER_STAGE);
+}
+
+#[test]
+fn cancellation_test() {
+    let (points, params) = test_points();
+    let token = CancellationToken::new();
+    let observer_token = token.clone();
+    let mut last_stage = None;
+    let res = approximate_dbscan_labels_observed(&points, &params, &mut |e| {
+        if let ProgressEvent::StageProgress{stage, ..} = e {
+            last_stage = Some(stage);
+            observer_token.cancel();
+        }
+    }, &token);
+    assert_eq!(res, Err(Cancelled));
+    assert_eq!(last_stage, Some(Stage::FindCells));
+    assert!(token.is_cancelled());
+    let cancelled = CancellationToken::new();
+    cancelled.cancel();
+    assert!(approximate_dbscan_observed(&points, &params, &mut |_| {}, &cancelled).is_err());
+}
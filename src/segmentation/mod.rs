@@ -0,0 +1,107 @@
+use std::io;
+use std::path::{Path};
+use crate::utils::{DBSCANParams, DBSCANLabels, Point};
+use crate::dbscan::approximate_dbscan_labels;
+use crate::data_io::{RgbImage, PALETTE_ARR, read_image, write_bmp};
+
+/// The parameters needed to segment an image with the approximate DBSCAN algorithm.
+/// Each pixel becomes a point `row column B G R` where the position is multiplied by `position_weight`
+/// and the color by `color_weight`, so that the two can be balanced against each other.
+pub struct SegmentationParams{
+    /// The clustering radius
+    pub epsilon: f64,
+    /// The approximation factor
+    pub rho: f64,
+    /// The minimum number of points for density
+    pub min_pts: usize,
+    /// The weight of the row and column of each pixel
+    pub position_weight: f64,
+    /// The weight of the color channels of each pixel
+    pub color_weight: f64
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// How the pixels of each segment are colored in the segmented image
+pub enum SegmentColoring{
+    /// High contrast colors, one for each segment
+    Palette,
+    /// The mean color of the pixels in each segment
+    MeanColor
+}
+
+/// Transforms each pixel of `image` in a 5 dimensional point `row column B G R`, with rows counted from the top one.
+/// Position and color are multiplied respectively by `position_weight` and `color_weight`.
+/// The points are listed row by row, in the same order as the pixels of the image.
+pub fn image_to_points(image: &RgbImage, position_weight: f64, color_weight: f64) -> Vec<Point<5>> {
+    let mut points = Vec::with_capacity(image.pixels.len());
+    for row in 0..image.height {
+        for column in 0..image.width {
+            let color = image.get_pixel(row, column);
+            points.push([
+                row as f64 * position_weight,
+                column as f64 * position_weight,
+                color[2] as f64 * color_weight,
+                color[1] as f64 * color_weight,
+                color[0] as f64 * color_weight
+            ]);
+        }
+    }
+    points
+}
+
+/// Runs the approximate DBSCAN algorithm on the pixels of `image` and returns the label of each pixel,
+/// in the same order as `image.pixels`.
+pub fn segment_image(image: &RgbImage, params: &SegmentationParams) -> DBSCANLabels {
+    let points = image_to_points(image, params.position_weight, params.color_weight);
+    let dbscan_params = DBSCANParams{
+        cardinality: points.len(),
+        dimensionality: 5,
+        epsilon: params.epsilon,
+        rho: params.rho,
+        min_pts: params.min_pts
+    };
+    approximate_dbscan_labels(&points, &dbscan_params)
+}
+
+/// Builds the segmented version of `image` given the labels of its pixels. Noise pixels are colored with 
+/// the first color of the palette (black) whatever the chosen coloring.
+pub fn segmented_image(image: &RgbImage, labels: &DBSCANLabels, coloring: SegmentColoring) -> RgbImage {
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+    let mut colors : Vec<[u8; 3]> = (0..=clusters_count).map(|i| PALETTE_ARR[i % PALETTE_ARR.len()]).collect();
+    if coloring == SegmentColoring::MeanColor {
+        let mut sums = vec![[0_u64; 3]; clusters_count + 1];
+        let mut counts = vec![0_u64; clusters_count + 1];
+        for (pixel, label) in image.pixels.iter().zip(labels.iter()) {
+            for channel in 0..3 {
+                sums[label.cluster_id][channel] += pixel[channel] as u64;
+            }
+            counts[label.cluster_id] += 1;
+        }
+        for i_cluster in 1..=clusters_count {
+            for channel in 0..3 {
+                if let Some(mean) = sums[i_cluster][channel].checked_div(counts[i_cluster]) {
+                    colors[i_cluster][channel] = mean as u8;
+                }
+            }
+        }
+    }
+    let mut segmented = image.clone();
+    for (pixel, label) in segmented.pixels.iter_mut().zip(labels.iter()) {
+        *pixel = colors[label.cluster_id];
+    }
+    segmented
+}
+
+/// Reads the image in `input_file`, segments it with the approximate DBSCAN algorithm and writes the segmented image
+/// to `output_file` as a 24 bit BMP image. See `data_io::read_image` for the supported input formats.
+/// Returns the number of segments found.
+pub fn segment_image_file<P, Q>(input_file: &P, output_file: &Q, params: &SegmentationParams, coloring: SegmentColoring) -> io::Result<usize>
+where P: AsRef<Path>, Q: AsRef<Path>, {
+    let image = read_image(input_file)?;
+    let labels = segment_image(&image, params);
+    write_bmp(output_file, &segmented_image(&image, &labels, coloring))?;
+    Ok(labels.iter().map(|l| l.cluster_id).max().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests;
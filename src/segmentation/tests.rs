@@ -0,0 +1,65 @@
+use super::*;
+use crate::utils::PointRole;
+
+/// 9x6 image with a red left half and a blue right half
+fn two_colors_image() -> RgbImage {
+    let mut image = RgbImage::new(9, 6, [255, 0, 0]);
+    for row in 0..image.height {
+        for column in 5..image.width {
+            image.set_pixel(row, column, [0, 0, 255]);
+        }
+    }
+    image
+}
+
+#[test]
+fn image_to_points_test() {
+    let image = two_colors_image();
+    let points = image_to_points(&image, 2.0, 0.5);
+    assert_eq!(points.len(), 54);
+    assert_eq!(points[0], [0.0, 0.0, 0.0, 0.0, 127.5]);
+    assert_eq!(points[10], [2.0, 2.0, 0.0, 0.0, 127.5]);
+    assert_eq!(points[8], [0.0, 16.0, 127.5, 0.0, 0.0]);
+}
+
+#[test]
+fn segment_image_test() {
+    let image = two_colors_image();
+    let params = SegmentationParams{
+        epsilon: 1.5,
+        rho: 0.1,
+        min_pts: 4,
+        position_weight: 1.0,
+        color_weight: 1.0
+    };
+    let labels = segment_image(&image, &params);
+    assert_eq!(labels.len(), image.pixels.len());
+    assert!(labels.iter().all(|l| l.role != PointRole::Noise));
+    assert_eq!(labels.iter().map(|l| l.cluster_id).max(), Some(2));
+    assert_ne!(labels[0].cluster_id, labels[8].cluster_id);
+    let segmented = segmented_image(&image, &labels, SegmentColoring::MeanColor);
+    assert_eq!(segmented, image);
+    let segmented = segmented_image(&image, &labels, SegmentColoring::Palette);
+    assert_ne!(segmented.get_pixel(0, 0), segmented.get_pixel(0, 8));
+    assert_eq!(segmented.get_pixel(0, 0), segmented.get_pixel(5, 4));
+}
+
+#[test]
+fn segment_image_file_test() {
+    let image = two_colors_image();
+    let input_file = std::env::temp_dir().join("appr_dbscan_segment_in.bmp");
+    let output_file = std::env::temp_dir().join("appr_dbscan_segment_out.bmp");
+    write_bmp(&input_file, &image).unwrap();
+    let params = SegmentationParams{
+        epsilon: 1.5,
+        rho: 0.1,
+        min_pts: 4,
+        position_weight: 1.0,
+        color_weight: 1.0
+    };
+    let segments = segment_image_file(&input_file, &output_file, &params, SegmentColoring::MeanColor).unwrap();
+    assert_eq!(segments, 2);
+    assert_eq!(crate::data_io::read_bmp(&output_file).unwrap(), image);
+    std::fs::remove_file(&input_file).unwrap();
+    std::fs::remove_file(&output_file).unwrap();
+}
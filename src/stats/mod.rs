@@ -0,0 +1,38 @@
+//! Statistics about a run of the approximate DBSCAN algorithm, useful to understand where the time goes and how
+//! large the structures built are. With the `tracing` feature the start and the end of each stage are also
+//! reported through the `tracing` crate, as spans named `stage` and events at the `DEBUG` level.
+
+use crate::progress::Stage;
+use std::time::Duration;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+/// The statistics collected during a run of the approximate DBSCAN algorithm
+pub struct RunStats {
+    /// The number of non empty cells of the grid
+    pub cells_count: usize,
+    /// The number of core cells
+    pub core_cells_count: usize,
+    /// The average number of neighbours of the non empty cells, each cell being a neighbour of itself
+    pub average_neighbours: f64,
+    /// The total number of nodes of the approximate range counting trees built on the core cells
+    pub tree_nodes_count: usize,
+    /// The number of approximate range counting queries made on the trees
+    pub range_queries_count: usize,
+    /// The time taken by each stage, in the order in which the stages ran
+    pub stage_times: Vec<(Stage, Duration)>
+}
+
+impl RunStats {
+    /// The time taken by `stage`, or `None` if the stage did not run
+    pub fn stage_time(&self, stage: Stage) -> Option<Duration> {
+        self.stage_times.iter().find(|(s, _)| *s == stage).map(|(_, time)| *time)
+    }
+
+    /// The time taken by all the stages
+    pub fn total_time(&self) -> Duration {
+        self.stage_times.iter().map(|(_, time)| *time).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests;
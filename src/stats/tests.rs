@@ -0,0 +1,30 @@
+use super::*;
+use crate::data_io::{params_from_file, read_points_from_file};
+use crate::dbscan::{approximate_dbscan_labels_with_stats, approximate_dbscan_with_stats};
+use crate::utils::{Point, PointRole};
+
+#[test]
+fn run_stats_test() {
+    let mut params = params_from_file(&"datasets/out_test_1.txt");
+    let points : Vec<Point<2>> = read_points_from_file(&"datasets/out_test_1.txt", &params);
+    params.epsilon = 0.3;
+    params.min_pts = 10;
+    params.rho = 0.1;
+    let (res, stats) = approximate_dbscan_with_stats(&points, &params);
+    assert_eq!(res.len(), 5 + 1);
+    assert!(stats.cells_count > 0);
+    assert!(stats.core_cells_count > 0 && stats.core_cells_count <= stats.cells_count);
+    // each cell is a neighbour of itself and in two dimensions there are at most 21 neighbours
+    assert!(stats.average_neighbours >= 1.0 && stats.average_neighbours <= 21.0);
+    assert!(stats.tree_nodes_count > stats.core_cells_count);
+    assert!(stats.range_queries_count > 0);
+    assert_eq!(stats.stage_times.iter().map(|(s, _)| *s).collect::<Vec<Stage>>(), Stage::ALL.to_vec());
+    assert!(stats.stage_time(Stage::LabelPoints).unwrap() <= stats.total_time());
+
+    let (labels, labels_stats) = approximate_dbscan_labels_with_stats(&points, &params);
+    assert_eq!(labels.iter().filter(|l| l.role == PointRole::Noise).count(), res[0].len());
+    assert_eq!(labels_stats.cells_count, stats.cells_count);
+    assert_eq!(labels_stats.core_cells_count, stats.core_cells_count);
+    assert_eq!(labels_stats.tree_nodes_count, stats.tree_nodes_count);
+    assert_eq!(RunStats::default().stage_time(Stage::FindCells), None);
+}
@@ -2,137 +2,245 @@ use std::collections::HashMap;
 use crate::utils::*;
 
 #[derive(Clone)]
-/// Tree structure that divides the space in nested cells to perform approximate range counting
-/// Each member of this structure is a node in the tree
-pub struct TreeStructure<const D: usize>{
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A node of a `TreeStructure`, that represents a sub-cell of a core cell
+struct TreeNode<const D: usize> {
     /// The index of the cell represented by this node
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_array"))]
     cell_index: CellIndex<D>,
-    /// The size of the cell 
-    side_size: f64,
     /// The depth inside the tree where this node lays
-    level: i32,
+    level: u32,
     /// The number of points cointained in the cell
     cnt: usize,
-    /// The collection of nested sub-cells (bounded by 2^D at max, with D constant)
-    children: HashMap<CellIndex<D>, TreeStructure<D>>,
+    /// The position in the arena of the first node after the sub-tree of this node
+    end: u32
 }
 
-impl <const D: usize> TreeStructure<D> {
-    pub fn new(cell_index: &CellIndex<D>, level: i32, side_size: f64) -> TreeStructure<D> {
-        let structure = TreeStructure {
-            cell_index: cell_index.clone(),
-            level: level,
-            cnt: 0,
-            side_size: side_size,
-            // mettere sempre 2^D come dimensione assicura che non ci siano riallocazione ma occupa 
-            // troppo spazio. sembra che funzioni piu' velocemente senza dare una capacity
-            children: HashMap::new()
-        };
-        structure
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Tree structure that divides the space in nested cells to perform approximate range counting.
+/// The nodes are stored in a single vector in depth first order, and each node knows where its sub-tree ends,
+/// so the tree can be visited without recursion and without following pointers.
+pub struct TreeStructure<const D: usize>{
+    /// All the nodes of the tree, the root first. The children of a node follow it, each one followed by its own sub-tree
+    nodes: Vec<TreeNode<D>>,
+    /// The size of the cell of the root. The cells at each level are half the size of the ones at the level above.
+    /// It is not serialized since it only depends on the parameters, see `restore_side_sizes`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    side_size: f64,
+    /// The deepest level visited by the queries: its nodes are counted whole when they intersect the ball
+    last_level: u32
+}
+
+/// The number of levels of the tree, the root included, needed to reach the approximation given by `rho`
+fn levels_count(rho: f64) -> u32 {
+    let levels_count_f = 1.0 + (1.0/rho).log(2.0).ceil();
+    if levels_count_f < 1.0 {
+        1
+    } else {
+        levels_count_f as u32
     }
+}
 
+impl <const D: usize> TreeStructure<D> {
     pub fn new_empty() -> TreeStructure<D>{
         TreeStructure{
-            cell_index: [0;D],
-            level: 0,
-            cnt: 0,
+            nodes: Vec::new(),
             side_size: 0.0,
-            children: HashMap::with_capacity(0)
+            last_level: 0
         }
     }
 
     /// Generates a tree starting from the points given in input. To function correctly the points in input
     /// must be all and only the core points in a given cell of the approximated DBSCAN algorithm with side size
-    /// equal to `epsilon/sqrt(D)`. This is assumed true during the construction.
-    pub fn build_structure(points: Vec<Point<D>>, params: &DBSCANParams) -> TreeStructure<D> {
+    /// equal to `epsilon/sqrt(D)`. This is assumed true during the construction. The tree only stores cell indexes
+    /// and counts, so it is the same for any scalar type `F` of the points.
+    pub fn build_structure<F: Float, I: IntoIterator<Item = Point<D, F>>>(points: I, params: &DBSCANParams) -> TreeStructure<D> {
         let base_side_size = params.epsilon/(params.dimensionality as  f64 ).sqrt();
-        let levels_count_f = 1.0 + (1.0/params.rho).log(2.0).ceil();
-        let levels_count = if levels_count_f < 1.0 {
-            1
-        } else {
-            levels_count_f as i32
-        };
-        // The approximated DBSCAN algorithm needs one instance of this structure for every core cell. 
-        // This gives that all the points in input are contained in the cell of side size `epsilon/sqrt(D)`. 
-        // All the points can then be added to the root and we proceed directly to divide the core cell in its sub-cells
-        let mut root = TreeStructure::new(&get_base_cell_index(&points[0], params),0,base_side_size);
-        root.cnt = points.len();
-        
-        for point in &points {
+        // The queries never go below the level `levels_count - 1`, so the nodes are built only up to it
+        let last_level = levels_count(params.rho) - 1;
+        // The approximated DBSCAN algorithm needs one instance of this structure for every core cell.
+        // This gives that all the points in input are contained in the cell of side size `epsilon/sqrt(D)`.
+        // All the points can then be added to the root and we proceed directly to divide the core cell in its sub-cells.
+        // The nodes are first collected in creation order, and then rearranged in depth first order
+        let mut nodes : Vec<TreeNode<D>> = Vec::new();
+        let mut parents : Vec<u32> = Vec::new();
+        let mut node_ids : HashMap<(u32, CellIndex<D>), u32> = HashMap::new();
+        for point in points {
+            if nodes.is_empty() {
+                nodes.push(TreeNode{cell_index: get_base_cell_index(&point, params), level: 0, cnt: 0, end: 0});
+                parents.push(0);
+            }
+            nodes[0].cnt += 1;
             let mut curr_side_size = base_side_size;
-            let mut prev_child = &mut root;
+            let mut parent = 0;
             //il livello 0 è occupato dalla radice
-            for i in 1..=levels_count {
-                curr_side_size = curr_side_size / 2.0;
-                let index_arr = get_cell_index(point, curr_side_size);
-                let curr_child : &mut TreeStructure<D> =
-                    prev_child.children.entry(index_arr.clone())
-                    .or_insert(TreeStructure::new(&index_arr, i, curr_side_size));
-                curr_child.cnt += 1;
-                prev_child = curr_child;
+            for level in 1..=last_level {
+                curr_side_size /= 2.0;
+                let index_arr = get_cell_index(&point, curr_side_size);
+                let child = *node_ids.entry((parent, index_arr)).or_insert_with(|| {
+                    nodes.push(TreeNode{cell_index: index_arr, level, cnt: 0, end: 0});
+                    parents.push(parent);
+                    (nodes.len() - 1) as u32
+                });
+                nodes[child as usize].cnt += 1;
+                parent = child;
             }
         }
-        root
+        TreeStructure{nodes: depth_first_order(nodes, &parents), side_size: base_side_size, last_level}
+    }
+
+    /// Sets the side size of the root to `side_size`. The side sizes of the other nodes are found by halving it at each level.
+    /// Used to restore the side size that is not serialized.
+    #[cfg(feature = "serde")]
+    pub fn restore_side_sizes(&mut self, side_size: f64) {
+        self.side_size = side_size;
     }
 
-    /// Performs the approximated range counting on the tree given the point in input. It stops as soon as the counting
-    /// is non zero, so the result is not actually the exact count but rather 0 if there is no point in the tree
-    /// in the vicinity of `q`, and a value that is less or equal to the number of points in the vicinity of `q` otherwise.
-    /// The points in the vicinity are found for certain if they are at a distance less than equal to `epsilon` from `q` and 
-    /// are excluded for certain if their distance from `q` is greater than `epsilon(1 + rho)`. All the points in between are 
-    /// counted in an arbitrary way, depending on what is more efficient. 
-    pub fn approximate_range_counting_root(&self, q: &Point<D>, params: &DBSCANParams) -> usize{
-        self.approximate_range_counting(q,params)
+    /// Checks that the nodes are laid out as `build_structure` lays them out, so that the queries always move forward
+    /// and stop: each node ends after itself and inside the sub-tree of its parent, its children are one level deeper
+    /// than it, and the root is the only node at level `0`. Used on deserialized trees, whose nodes can not be trusted.
+    #[cfg(feature = "serde")]
+    pub fn check_layout(&self) -> Result<(), String> {
+        // the nodes whose sub-tree contains the current one, from the root down
+        let mut ancestors : Vec<usize> = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let end = node.end as usize;
+            if end <= i || end > self.nodes.len() {
+                return Err(format!("the node {} ends at {}, out of the range {}..={}", i, end, i + 1, self.nodes.len()));
+            }
+            while ancestors.last().is_some_and(|&a| self.nodes[a].end as usize <= i) {
+                ancestors.pop();
+            }
+            match ancestors.last() {
+                None if i > 0 => return Err(format!("the node {} is outside of the sub-tree of the root", i)),
+                None if node.level != 0 => return Err(format!("the root is at level {}", node.level)),
+                Some(&parent) if end > self.nodes[parent].end as usize => {
+                    return Err(format!("the node {} ends after its parent {}", i, parent));
+                },
+                Some(&parent) if node.level != self.nodes[parent].level + 1 => {
+                    return Err(format!("the node {} is at level {} under a node at level {}", i, node.level, self.nodes[parent].level));
+                },
+                _ => {}
+            }
+            ancestors.push(i);
+        }
+        Ok(())
     }
 
-    fn approximate_range_counting(&self, q: &Point<D>, params: &DBSCANParams) -> usize {
+    /// The number of nodes of the tree, the root included
+    pub fn nodes_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Performs the approximated range counting on the tree given the point in input. The result is the number of points
+    /// in the vicinity of `q`: the points are counted for certain if they are at a distance less than equal to `epsilon` from `q` and
+    /// are excluded for certain if their distance from `q` is greater than `epsilon(1 + rho)`. All the points in between are
+    /// counted in an arbitrary way, depending on what is more efficient. Use `approximate_range_nonempty` to only know if
+    /// the count is non zero.
+    pub fn approximate_range_counting_root<F: Float>(&self, q: &Point<D, F>, params: &DBSCANParams) -> usize{
         let mut ans : usize = 0;
-        let levels_count_f = 1.0 + (1.0/params.rho).log(2.0).ceil();
-        let levels_count = if levels_count_f < 1.0 {
-            1
-        } else {
-            levels_count_f as i32
-        };
-        let intersection_type = determine_intersection(q, params, &self.cell_index, self.side_size);
-        match intersection_type {
-            IntersectionType::Disjoint => {},
-            IntersectionType::FullyCovered => {
-                ans += self.cnt;
-            },
-            IntersectionType::Intersecting => {
-                if self.level < (levels_count - 1) {
-                    for child in self.children.values() {
-                        ans += child.approximate_range_counting(q, params);
-                        /*if ans > 0 {
-                            return ans;
-                        }*/
+        let mut i = 0;
+        while i < self.nodes.len() {
+            let node = &self.nodes[i];
+            let side_size = self.side_size / f64::powi(2.0, node.level as i32);
+            let intersection_type = determine_intersection(q, params, &node.cell_index, side_size);
+            match intersection_type {
+                IntersectionType::Disjoint => {
+                    i = node.end as usize;
+                },
+                IntersectionType::FullyCovered => {
+                    ans += node.cnt;
+                    i = node.end as usize;
+                },
+                IntersectionType::Intersecting => {
+                    if node.level < self.last_level {
+                        // visits the children, that directly follow the node
+                        i += 1;
+                    } else {
+                        ans += node.cnt;
+                        i = node.end as usize;
                     }
-                } else {
-                    ans += self.cnt;
                 }
             }
         }
         ans
     }
 
-    
-
-    /*fn print_tree_rec(&self) {
-        println!("--- node ---");
-        println!("> Level: {}",self.level);
-        println!("> cell_index: {:?}",self.cell_index);
-        println!("> cnt: {}",self.cnt);
-        for child in self.children.values() {
-            child.print_tree_rec();
+    /// Tells if the approximated range counting for `q` would be non zero, that is if there is at least a point in the vicinity
+    /// of `q` with the same approximation of `approximate_range_counting_root`. It stops at the first node that is found to be
+    /// in the vicinity, without counting the rest of the tree.
+    pub fn approximate_range_nonempty<F: Float>(&self, q: &Point<D, F>, params: &DBSCANParams) -> bool {
+        let mut i = 0;
+        while i < self.nodes.len() {
+            let node = &self.nodes[i];
+            let side_size = self.side_size / f64::powi(2.0, node.level as i32);
+            match determine_intersection(q, params, &node.cell_index, side_size) {
+                IntersectionType::Disjoint => {
+                    i = node.end as usize;
+                },
+                IntersectionType::FullyCovered => {
+                    return true;
+                },
+                IntersectionType::Intersecting => {
+                    if node.level < self.last_level {
+                        i += 1;
+                    } else {
+                        return true;
+                    }
+                }
+            }
         }
+        false
     }
-
-    pub fn print_tree(&self){
-        println!("----- TREE -----");
-        self.print_tree_rec();
-    }*/
 }
 
+/// Rearranges the nodes, given in creation order with the parent of each one, so that each node is followed by its
+/// sub-tree, and sets where each sub-tree ends. The root must be the first node.
+fn depth_first_order<const D: usize>(nodes: Vec<TreeNode<D>>, parents: &[u32]) -> Vec<TreeNode<D>> {
+    if nodes.is_empty() {
+        return nodes;
+    }
+    // children of each node, in creation order, as ranges of `children`
+    let mut children_start = vec![0_usize; nodes.len() + 1];
+    for &parent in &parents[1..] {
+        children_start[parent as usize + 1] += 1;
+    }
+    for i in 0..nodes.len() {
+        children_start[i + 1] += children_start[i];
+    }
+    let mut children = vec![0_u32; nodes.len() - 1];
+    let mut next_child = children_start.clone();
+    for (child, &parent) in parents.iter().enumerate().skip(1) {
+        children[next_child[parent as usize]] = child as u32;
+        next_child[parent as usize] += 1;
+    }
+    let mut old_nodes : Vec<Option<TreeNode<D>>> = nodes.into_iter().map(Some).collect();
+    let mut ordered : Vec<TreeNode<D>> = Vec::with_capacity(old_nodes.len());
+    // the nodes still to visit, by their position in `old_nodes`
+    let mut stack : Vec<u32> = vec![0];
+    // the positions in `ordered` of the nodes whose sub-tree is not complete yet, from the root down
+    let mut open : Vec<usize> = Vec::new();
+    while let Some(old_i) = stack.pop() {
+        let node = old_nodes[old_i as usize].take().unwrap();
+        // the sub-trees of the nodes deeper than or as deep as this one are complete
+        while let Some(&open_i) = open.last() {
+            if ordered[open_i].level < node.level {
+                break;
+            }
+            ordered[open_i].end = ordered.len() as u32;
+            open.pop();
+        }
+        open.push(ordered.len());
+        ordered.push(node);
+        let range = children_start[old_i as usize]..children_start[old_i as usize + 1];
+        stack.extend(children[range].iter().rev());
+    }
+    for open_i in open {
+        ordered[open_i].end = ordered.len() as u32;
+    }
+    ordered
+}
 
 #[cfg(test)]
-mod tests;
\ No newline at end of file
+mod tests;
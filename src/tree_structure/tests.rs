@@ -1,4 +1,5 @@
 use super::*;
+use proptest::prelude::*;
 
 #[test]
 fn counting_test(){
@@ -29,4 +30,126 @@ fn counting_test(){
     assert_eq!(root1.approximate_range_counting_root(&[2.5*l,2.5*l], &params),0);
     assert_eq!(root1.approximate_range_counting_root(&[2.2*l,2.2*l], &params),0);
     assert_eq!(root1.approximate_range_counting_root(&[2.11*l,2.11*l], &params),0);
-}
\ No newline at end of file
+    assert!(root1.approximate_range_nonempty(&central, &params));
+    assert!(!root1.approximate_range_nonempty(&q2, &params));
+    assert!(!TreeStructure::<2>::new_empty().approximate_range_nonempty(&central, &params));
+}
+#[test]
+fn counting_dimensionalities_test(){
+    fn check<const D: usize>() {
+        let params = DBSCANParams {
+            cardinality: 2,
+            dimensionality: D as u32,
+            epsilon: 1.0,
+            rho: 0.1,
+            min_pts: 0
+        };
+        let l = params.epsilon / (params.dimensionality as f64).sqrt();
+        let root = TreeStructure::<D>::build_structure(vec![[0.1 * l; D], [-0.1 * l; D]], &params);
+        assert_eq!(root.approximate_range_counting_root(&[0.0; D], &params), 2);
+        assert_eq!(root.approximate_range_counting_root(&[10.0 * l; D], &params), 0);
+    }
+    check::<1>();
+    check::<3>();
+    check::<5>();
+    check::<7>();
+}
+
+#[test]
+fn depth_first_layout_test() {
+    let params = DBSCANParams {
+        cardinality: 3,
+        dimensionality: 2,
+        epsilon: 1.0,
+        rho: 0.25,
+        min_pts: 0
+    };
+    let l = params.epsilon / (params.dimensionality as f64).sqrt();
+    let tree = TreeStructure::build_structure(vec![[-0.3 * l, -0.3 * l], [0.3 * l, 0.3 * l], [0.32 * l, 0.32 * l]], &params);
+    assert_eq!(levels_count(params.rho), 3);
+    assert_eq!(tree.last_level, 2);
+    // the root, then two children with one child each
+    assert_eq!(tree.nodes_count(), 5);
+    assert_eq!(tree.nodes[0].end, 5);
+    assert_eq!(tree.nodes[0].cnt, 3);
+    for (i, node) in tree.nodes.iter().enumerate().skip(1) {
+        assert!(node.end as usize > i);
+        let subtree = &tree.nodes[i + 1..node.end as usize];
+        assert!(subtree.iter().all(|n| n.level > node.level));
+        let children_cnt : usize = subtree.iter().filter(|n| n.level == node.level + 1).map(|n| n.cnt).sum();
+        assert!(node.level == tree.last_level || children_cnt == node.cnt);
+    }
+    assert_eq!(TreeStructure::<2>::new_empty().approximate_range_counting_root(&[0.0, 0.0], &params), 0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn check_layout_test() {
+    let params = DBSCANParams {
+        cardinality: 3,
+        dimensionality: 2,
+        epsilon: 1.0,
+        rho: 0.25,
+        min_pts: 0
+    };
+    let l = params.epsilon / (params.dimensionality as f64).sqrt();
+    let tree = TreeStructure::build_structure(vec![[-0.3 * l, -0.3 * l], [0.3 * l, 0.3 * l], [0.32 * l, 0.32 * l]], &params);
+    assert_eq!(tree.check_layout(), Ok(()));
+    assert_eq!(TreeStructure::<2>::new_empty().check_layout(), Ok(()));
+    // ends that would make the queries stay in place, go back or read past the nodes
+    for (i, end) in [(0, 0), (2, 1), (2, 2), (0, 6), (4, 6)] {
+        let mut corrupted = tree.clone();
+        corrupted.nodes[i].end = end;
+        assert!(corrupted.check_layout().is_err(), "node {} ending at {}", i, end);
+    }
+    // a child that ends after its parent, and a node that is not one level below its parent
+    let mut corrupted = tree.clone();
+    corrupted.nodes[2].end = 4;
+    assert!(corrupted.check_layout().is_err());
+    let mut corrupted = tree.clone();
+    corrupted.nodes[2].level = 1;
+    assert!(corrupted.check_layout().is_err());
+    let mut corrupted = tree;
+    corrupted.nodes[0].end = 3;
+    assert!(corrupted.check_layout().is_err());
+}
+
+/// Checks that the count for `q` includes all the points within `epsilon` from it and none of the ones
+/// farther than `epsilon(1 + rho)`, and that the emptiness query agrees with it
+fn check_against_brute_force<const D: usize>(points: Vec<[f64; D]>, q: [f64; D], epsilon: f64, rho: f64) -> Result<(), TestCaseError> {
+    let params = DBSCANParams {
+        cardinality: points.len(),
+        dimensionality: D as u32,
+        epsilon,
+        rho,
+        min_pts: 0
+    };
+    // the points must all be in the same base cell, the one centered in the origin
+    let l = epsilon / (D as f64).sqrt();
+    let points : Vec<Point<D>> = points.iter().map(|p| p.map(|x| x * l)).collect();
+    let q = q.map(|x| x * l);
+    let tree = TreeStructure::build_structure(points.iter().copied(), &params);
+    let count = tree.approximate_range_counting_root(&q, &params);
+    let within = points.iter().filter(|p| euclidean_distance(p, &q) <= epsilon).count();
+    let within_approx = points.iter().filter(|p| euclidean_distance(p, &q) <= epsilon * (1.0 + rho)).count();
+    prop_assert!(within <= count && count <= within_approx, "{} not in {}..={}", count, within, within_approx);
+    prop_assert_eq!(tree.approximate_range_nonempty(&q, &params), count != 0);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn counting_2d_prop(points in prop::collection::vec(prop::array::uniform2(-0.499..0.499), 1..50), q in prop::array::uniform2(-3.0..3.0), epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_brute_force(points, q, epsilon, rho)?;
+    }
+
+    #[test]
+    fn counting_3d_prop(points in prop::collection::vec(prop::array::uniform3(-0.499..0.499), 1..50), q in prop::array::uniform3(-3.0..3.0), epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_brute_force(points, q, epsilon, rho)?;
+    }
+
+    #[test]
+    fn counting_5d_prop(points in prop::collection::vec(prop::array::uniform5(-0.499..0.499), 1..50), q in prop::array::uniform5(-3.0..3.0), epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_brute_force(points, q, epsilon, rho)?;
+    }
+}
@@ -0,0 +1,59 @@
+
+/// Euclidean distance between two points of the same dimensionality represented as slices
+fn slice_distance(p: &[f64], q: &[f64]) -> f64 {
+    p.iter().zip(q.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt()
+}
+
+/// Computes, for at most `sample_size` points evenly spread in `points`, the distance to the `k`-th closest point in `points`, 
+/// counting the point itself as the first one. With `k` equal to `min_pts` this is the smallest `epsilon` that makes the 
+/// sampled point a core point. The distances are returned sorted from the smallest one. 
+/// Points with less than `k` points in the whole dataset get no distance.
+pub fn k_distances<Q: AsRef<[f64]>>(points: &[Q], k: usize, sample_size: usize) -> Vec<f64> {
+    if k == 0 || points.len() < k || sample_size == 0 {
+        return Vec::new();
+    }
+    let step = points.len().div_ceil(sample_size);
+    let mut distances = Vec::with_capacity(sample_size);
+    // the k closest distances found so far, sorted
+    let mut closest : Vec<f64> = Vec::with_capacity(k + 1);
+    for sampled in points.iter().step_by(step) {
+        closest.clear();
+        for point in points {
+            let dist = slice_distance(sampled.as_ref(), point.as_ref());
+            if closest.len() < k || dist < closest[k - 1] {
+                let at = closest.partition_point(|d| *d <= dist);
+                closest.insert(at, dist);
+                closest.truncate(k);
+            }
+        }
+        distances.push(closest[k - 1]);
+    }
+    distances.sort_by(|a, b| a.total_cmp(b));
+    distances
+}
+
+/// Suggests a value of `epsilon` for the given value of `min_pts` by looking for the knee of the sorted `min_pts`-distances 
+/// of the points (see `k_distances`). The knee is the distance that lies farthest below the line that joins the smallest and 
+/// the largest distances. At most `sample_size` points are sampled to keep the computation fast on big datasets.
+/// Returns `None` if there are less than `min_pts` points.
+pub fn suggest_epsilon<Q: AsRef<[f64]>>(points: &[Q], min_pts: usize, sample_size: usize) -> Option<f64> {
+    let distances = k_distances(points, min_pts, sample_size);
+    let (first, last) = (*distances.first()?, *distances.last()?);
+    if distances.len() < 3 || last <= first {
+        return Some(last);
+    }
+    let max_i = (distances.len() - 1) as f64;
+    let mut knee = last;
+    let mut max_gap = f64::NEG_INFINITY;
+    for (i, dist) in distances.iter().enumerate() {
+        let gap = i as f64 / max_i - (dist - first) / (last - first);
+        if gap > max_gap {
+            max_gap = gap;
+            knee = *dist;
+        }
+    }
+    Some(knee)
+}
+
+#[cfg(test)]
+mod tests;
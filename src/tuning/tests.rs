@@ -0,0 +1,26 @@
+use super::*;
+
+#[test]
+fn k_distances_test() {
+    let points = vec![[0.0], [1.0], [3.0], [6.0]];
+    assert_eq!(k_distances(&points, 1, 10), vec![0.0; 4]);
+    assert_eq!(k_distances(&points, 2, 10), vec![1.0, 1.0, 2.0, 3.0]);
+    assert_eq!(k_distances(&points, 3, 10), vec![2.0, 3.0, 3.0, 5.0]);
+    assert_eq!(k_distances(&points, 2, 2), vec![1.0, 2.0]);
+    assert!(k_distances(&points, 5, 10).is_empty());
+}
+
+#[test]
+fn suggest_epsilon_test() {
+    // two dense groups of points at distance 0.1 from each other and a few far away outliers
+    let mut points : Vec<[f64; 2]> = Vec::new();
+    for i in 0..50 {
+        points.push([i as f64 * 0.1, 0.0]);
+        points.push([i as f64 * 0.1, 100.0]);
+    }
+    points.push([-50.0, 50.0]);
+    points.push([50.0, -50.0]);
+    let epsilon = suggest_epsilon(&points, 3, 1000).unwrap();
+    assert!((0.1..1.0).contains(&epsilon), "{}", epsilon);
+    assert_eq!(suggest_epsilon(&points[..2], 3, 1000), None);
+}
@@ -1,4 +1,132 @@
 use rstar::{Point as RPoint};
+use std::fmt::Debug;
+use std::ops::{Add, Sub, Mul, Div, AddAssign, Range};
+
+/// The scalar types that can be used for the coordinates of the points to cluster. It is implemented for `f32` and `f64`,
+/// so that `f32` data can be clustered without converting it first, and can not be implemented outside of this crate.
+/// The clustering parameters and the cell indexes are always computed with `f64` precision.
+pub trait Float: Copy + PartialOrd + Debug + Default + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + AddAssign + sealed::CountWithinSq {
+    /// Converts a `f64` value to this type, rounding it if needed
+    fn from_f64(value: f64) -> Self;
+    /// Converts this value to `f64`
+    fn to_f64(self) -> f64;
+    /// The square root of this value
+    fn sqrt(self) -> Self;
+}
+
+/// The distance kernels of each scalar type. The trait is public so that it can bound `Float`, but it can not be named
+/// outside of this crate, which keeps the kernels out of the public API and `Float` implemented only here.
+mod sealed {
+    use super::Point;
+    use std::ops::Range;
+
+    /// The implementation of `count_within_sq` for a scalar type
+    pub trait CountWithinSq: Sized {
+        fn count_within_sq<const D: usize>(axes: &[Vec<Self>; D], range: Range<usize>, q: &Point<D, Self>, epsilon_sq: Self) -> usize;
+    }
+}
+
+/// Counts the points in `range` at squared distance at most `epsilon_sq` from `q`. The points are given one axis at a time:
+/// `axes[i][j]` is the coordinate on axis `i` of the point `j`, and all the axes have the same length. With the `simd`
+/// feature the `f32` and `f64` points are processed in batches as wide as the vector registers, the kernel being chosen
+/// by the implementation of `sealed::CountWithinSq` for `F`.
+pub(crate) fn count_within_sq<F: Float, const D: usize>(axes: &[Vec<F>; D], range: Range<usize>, q: &Point<D, F>, epsilon_sq: F) -> usize {
+    F::count_within_sq(axes, range, q, epsilon_sq)
+}
+
+/// Counts the points in `range` that are at squared distance at most `epsilon_sq` from `q`, one at a time.
+/// See `count_within_sq`
+fn count_within_sq_scalar<F: Float, const D: usize>(axes: &[Vec<F>; D], range: Range<usize>, q: &Point<D, F>, epsilon_sq: F) -> usize {
+    range.filter(|&j| {
+        let mut sum = F::default();
+        for i in 0..D {
+            let diff = axes[i][j] - q[i];
+            sum += diff * diff;
+        }
+        sum <= epsilon_sq
+    }).count()
+}
+
+#[cfg(feature = "simd")]
+/// The batched versions of `count_within_sq`. Each lane sums the squares of the differences in the same order
+/// as `count_within_sq_scalar`, so the results are exactly the same.
+mod simd {
+    use super::{count_within_sq_scalar, Point};
+    use std::ops::Range;
+    use wide::{f32x8, f64x4, CmpLe};
+
+    macro_rules! count_within_sq_batched {
+        ($name:ident, $float:ty, $simd:ty, $lanes:expr) => {
+            pub(super) fn $name<const D: usize>(axes: &[Vec<$float>; D], range: Range<usize>, q: &Point<D, $float>, epsilon_sq: $float) -> usize {
+                let batches = range.len() / $lanes;
+                let epsilon_sq_v = <$simd>::splat(epsilon_sq);
+                let mut count = 0;
+                for b in 0..batches {
+                    let first = range.start + b * $lanes;
+                    let mut sum = <$simd>::splat(0.0);
+                    for i in 0..D {
+                        let mut lanes = [0.0; $lanes];
+                        lanes.copy_from_slice(&axes[i][first..first + $lanes]);
+                        let diff = <$simd>::new(lanes) - <$simd>::splat(q[i]);
+                        sum += diff * diff;
+                    }
+                    count += sum.cmp_le(epsilon_sq_v).move_mask().count_ones() as usize;
+                }
+                count + count_within_sq_scalar(axes, range.start + batches * $lanes..range.end, q, epsilon_sq)
+            }
+        };
+    }
+
+    count_within_sq_batched!(count_within_sq_f64, f64, f64x4, 4);
+    count_within_sq_batched!(count_within_sq_f32, f32, f32x8, 8);
+}
+
+impl sealed::CountWithinSq for f32 {
+    fn count_within_sq<const D: usize>(axes: &[Vec<f32>; D], range: Range<usize>, q: &Point<D, f32>, epsilon_sq: f32) -> usize {
+        #[cfg(feature = "simd")]
+        return simd::count_within_sq_f32(axes, range, q, epsilon_sq);
+        #[cfg(not(feature = "simd"))]
+        return count_within_sq_scalar(axes, range, q, epsilon_sq);
+    }
+}
+
+impl sealed::CountWithinSq for f64 {
+    fn count_within_sq<const D: usize>(axes: &[Vec<f64>; D], range: Range<usize>, q: &Point<D, f64>, epsilon_sq: f64) -> usize {
+        #[cfg(feature = "simd")]
+        return simd::count_within_sq_f64(axes, range, q, epsilon_sq);
+        #[cfg(not(feature = "simd"))]
+        return count_within_sq_scalar(axes, range, q, epsilon_sq);
+    }
+}
+
+impl Float for f32 {
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn sqrt(self) -> f32 {
+        f32::sqrt(self)
+    }
+}
+
+impl Float for f64 {
+    fn from_f64(value: f64) -> f64 {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn sqrt(self) -> f64 {
+        f64::sqrt(self)
+    }
+}
 
 #[derive(Clone,Copy,PartialEq,Debug)]
 /// Mock struct to use RTrees with const generics
@@ -38,6 +166,8 @@ impl <const D:usize> RPoint for CellIndexPoint<D>{
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The parameters needed to run the approximate DBSCAN algorithm
 pub struct DBSCANParams{
     /// The number of points to cluster
@@ -52,6 +182,41 @@ pub struct DBSCANParams{
     pub min_pts: usize
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The role of a point inside the clustering found by the approximate DBSCAN algorithm
+pub enum PointRole{
+    /// The point has at least `min_pts` points at distance at most `epsilon`
+    Core,
+    /// The point is not a core point but it is close enough to the core points of at least one cluster
+    Border,
+    /// The point does not belong to any cluster
+    Noise
+}
+
+impl PointRole {
+    /// Lowercase name of the role, as used by the output writers in `data_io`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PointRole::Core => "core",
+            PointRole::Border => "border",
+            PointRole::Noise => "noise"
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The label assigned by the approximate DBSCAN algorithm to a single point
+pub struct PointLabel{
+    /// The index of the cluster of the point. It is the same index that the cluster has in a `DBSCANResult`,
+    /// so noise points have index `0`. Border points that belong to more than one cluster are labeled with
+    /// one of them.
+    pub cluster_id: usize,
+    /// The role of the point inside its cluster
+    pub role: PointRole
+}
+
 #[derive(PartialEq, Debug)]
 /// See documentation for the function `utils::determine_intersection`
 pub enum IntersectionType{
@@ -60,78 +225,126 @@ pub enum IntersectionType{
     Intersecting
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The distance functions that can be used to cluster points. The approximate DBSCAN algorithm works in the euclidean 
+/// space, so the points are first transformed with `Metric::transform_point` and the radius with `Metric::euclidean_epsilon`
+pub enum Metric {
+    /// The euclidean distance
+    Euclidean,
+    /// The cosine distance `1 - cos(p, q)`, equivalent to the euclidean distance between the normalized points. 
+    /// Points with all zero coordinates are left unchanged.
+    Cosine
+}
+
+impl Metric {
+    /// Transforms `point` so that the euclidean distance between transformed points grows with this metric
+    pub fn transform_point(&self, point: &mut [f64]) {
+        match self {
+            Metric::Euclidean => {},
+            Metric::Cosine => {
+                let norm = point.iter().map(|x| x * x).sum::<f64>().sqrt();
+                if norm > 0.0 {
+                    for x in point.iter_mut() {
+                        *x /= norm;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The radius between transformed points that is equivalent to `epsilon` in this metric
+    pub fn euclidean_epsilon(&self, epsilon: f64) -> f64 {
+        match self {
+            Metric::Euclidean => epsilon,
+            // for unit vectors |p - q|^2 = 2 - 2cos(p, q)
+            Metric::Cosine => (2.0 * epsilon).sqrt()
+        }
+    }
+}
+
+impl std::str::FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Metric, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "euclidean" => Ok(Metric::Euclidean),
+            "cosine" => Ok(Metric::Cosine),
+            _ => Err(format!("unknown metric {:?}, expected one of euclidean, cosine", s))
+        }
+    }
+}
+
 // Computes the euclidean distance between two points in a `D` dimensional space
-pub fn euclidean_distance<const D: usize>(p: &Point<D>, q: &Point<D>) -> f64 {
-    let mut sum : f64 = 0.0;
+pub fn euclidean_distance<F: Float, const D: usize>(p: &Point<D, F>, q: &Point<D, F>) -> F {
+    squared_euclidean_distance(p, q).sqrt()
+}
+
+/// Computes the square of the euclidean distance between two points in a `D` dimensional space. Comparing it with the
+/// square of a radius avoids computing the square root
+pub fn squared_euclidean_distance<F: Float, const D: usize>(p: &Point<D, F>, q: &Point<D, F>) -> F {
+    let mut sum = F::default();
     for i in 0..D{
-        sum += (p[i]-q[i]).powf(2_f64);
+        let diff = p[i] - q[i];
+        sum += diff * diff;
     }
-    sum.sqrt()
+    sum
 }
 
 /// Determines the type of intersection between a cell and an approximated ball.
-/// The cell is determined by its center and the side of its size.
+/// The cell is determined by its center and the side of its size. The distances are computed with `f64` precision
+/// whatever the scalar type of `q`.
 /// Returns: 
 ///  * IntersectionType::FullyCovered if the cell is completely contained in a ball with center `q` and radius `epsilon(1 + rho)`;
 ///  * IntersectionType::Disjoint if the cell is completely outside of a ball with center `q` and radius `epsilon`;
 ///  * IntersectionType::Intersecting otherwise;
-pub fn determine_intersection<const D: usize>(q: &Point<D>, params: &DBSCANParams, index_c: &CellIndex<D>, side_size:f64) -> IntersectionType{
-    let n_corners = (2_usize.pow(D as u32)) as usize;
-    let mut cell_center : CellCenter<D> = [0.0;D];
-    for i in 0..D {
-        cell_center[i] = index_c[i] as f64 * side_size;
-    }
-    let corners = get_corners(&cell_center, side_size);
+/// 
+/// The farthest and the closest points of the cell from `q` are found one axis at a time, so this takes `O(D)` time.
+pub fn determine_intersection<F: Float, const D: usize>(q: &Point<D, F>, params: &DBSCANParams, index_c: &CellIndex<D>, side_size:f64) -> IntersectionType{
+    let (min_dist_sq, max_dist_sq) = cell_distance_bounds_sq(q, index_c, side_size);
     let appr_dist = (1.0 + params.rho) * params.epsilon;
-    let mut appr_in_count : usize = 0;
-    let mut out_count : usize = 0;
-    for corner in corners {
-        let dist = euclidean_distance(q, &corner);
-        if dist <= appr_dist {
-            appr_in_count += 1;
-        }
-        if dist >= params.epsilon {
-            out_count += 1;
-        }
-    }
-    if appr_in_count == n_corners{
-        return IntersectionType::FullyCovered
-    } else if out_count == n_corners{
-        return IntersectionType::Disjoint
-    }
-    IntersectionType::Intersecting
-}
-
-/// Gets the coordinates of all the corners (2^D) of a cell given its center points and its side size.
-fn get_corners<const D: usize>(cell_center: &CellCenter<D>, side_size: f64) -> Vec<Point<D>>{
-    let dist = side_size/2.0;
-    //Ho 2^d combinazioni. Posso pensare ogni combinazione come un numero binario di d cifre.
-    //Immagino di sostituire lo 0 con -dist e l'1 con +dist. Allora posso partire da cell_center
-    //e fare la sua somma con ogni numero binario per trovare tutti i vertici
-    let top = 2_usize.pow(D as u32);
-    let mut corners = Vec::with_capacity(top);
-    for bin_rep in 0..top {
-        let mut new_corner = cell_center.clone();
-        for bit_i in 0..D {
-            let mask = 1 << bit_i;
-            if bin_rep & mask == 0 {
-                new_corner[bit_i] -= dist;
-            } else {
-                new_corner[bit_i] += dist;
-            }
-        }
-        corners.push(new_corner);
+    if f64::sqrt(max_dist_sq) <= appr_dist {
+        IntersectionType::FullyCovered
+    } else if f64::sqrt(min_dist_sq) >= params.epsilon {
+        IntersectionType::Disjoint
+    } else {
+        IntersectionType::Intersecting
     }
-    corners
+}
+
+/// Computes the squared distances of `q` from the closest and from the farthest point of the cell with index `index_c` 
+/// and side size `side_size`. The distances are computed with `f64` precision
+pub(crate) fn cell_distance_bounds_sq<F: Float, const D: usize>(q: &Point<D, F>, index_c: &CellIndex<D>, side_size:f64) -> (f64, f64) {
+    let half_side = side_size / 2.0;
+    let mut min_dist_sq = 0.0;
+    let mut max_dist_sq = 0.0;
+    for i in 0..D {
+        let q_i = q[i].to_f64();
+        let center = index_c[i] as f64 * side_size;
+        let low = center - half_side;
+        let high = center + half_side;
+        let near = if q_i < low {
+            low - q_i
+        } else if q_i > high {
+            q_i - high
+        } else {
+            0.0
+        };
+        let far = (q_i - low).abs().max((q_i - high).abs());
+        min_dist_sq += near * near;
+        max_dist_sq += far * far;
+    }
+    (min_dist_sq, max_dist_sq)
 }
 
 /// Gets the indexes of the intervals of the axes in the `D` dimensional space where lies a Cell with side 
 /// size equal to `side_size` that contains point `p`
-pub fn get_cell_index<const D: usize>(p: &Point<D>, side_size: f64) -> CellIndex<D>{
+pub fn get_cell_index<F: Float, const D: usize>(p: &Point<D, F>, side_size: f64) -> CellIndex<D>{
     let mut new_index = [0;D];
     let half_size = side_size/2.0;
+    let p = p.map(F::to_f64);
     for i in 0..p.len() {
-        if p[i] >= (-1.0 * half_size) && p[i] < half_size {
+        if p[i] >= -half_size && p[i] < half_size {
             new_index[i] =  0;
         } else if p[i] > 0.0 {
             new_index[i] = ((p[i] - half_size) / side_size).ceil() as i64;
@@ -144,7 +357,7 @@ pub fn get_cell_index<const D: usize>(p: &Point<D>, side_size: f64) -> CellIndex
 
 /// Gets the indexes of the intervals of the axes in the `D` dimensional space where lies a Cell with side 
 /// size equal to `epsilon/sqrt(D)` that contains point `p`
-pub fn get_base_cell_index<const D: usize>(p: &Point<D>, params: &DBSCANParams) ->CellIndex<D>{
+pub fn get_base_cell_index<F: Float, const D: usize>(p: &Point<D, F>, params: &DBSCANParams) ->CellIndex<D>{
     get_cell_index(p, params.epsilon/(params.dimensionality as f64).sqrt())
 }
 
@@ -162,13 +375,134 @@ pub fn index_distance_sq<const D: usize>(i_1 : &CellIndex<D>, i_2: &CellIndex<D>
 pub type CellIndex<const D: usize> = [i64;D];
 /// Type that represent the point in the `D` dimensional space that lays at the center of a cell
 pub type CellCenter<const D: usize> = Point<D>;
-/// Type that represents a point with dimensionality D
-pub type Point<const D: usize> = [f64;D];
+/// Type that represents a point with dimensionality D and coordinates of type `F` (`f64` by default)
+pub type Point<const D: usize, F = f64> = [F;D];
 /// Collection of points in the same cluster
-pub type Cluster <const D: usize> = Vec<Point<D>>;
+pub type Cluster <const D: usize, F = f64> = Vec<Point<D, F>>;
 /// Collection of all the cluster found by the DBSCAN algorithm. Its first element
 /// will be the collection of noise points.
-pub type DBSCANResult <const D: usize> = Vec<Cluster<D>>;
+pub type DBSCANResult <const D: usize, F = f64> = Vec<Cluster<D, F>>;
+
+/// A collection of points that the approximate DBSCAN algorithm can read without copying it. The algorithm
+/// only keeps the indexes of the points, and reads their coordinates from the collection whenever it needs them.
+pub trait PointSource<F: Float, const D: usize> {
+    /// The number of points in the collection
+    fn len(&self) -> usize;
+    /// The coordinates of the point at index `i`. Panics if `i` is out of bounds
+    fn point(&self, i: usize) -> Point<D, F>;
+
+    /// Returns `true` if the collection contains no points
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl <F: Float, const D: usize> PointSource<F, D> for [Point<D, F>] {
+    fn len(&self) -> usize {
+        <[Point<D, F>]>::len(self)
+    }
+
+    fn point(&self, i: usize) -> Point<D, F> {
+        self[i]
+    }
+}
+
+impl <F: Float, const D: usize> PointSource<F, D> for Vec<Point<D, F>> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn point(&self, i: usize) -> Point<D, F> {
+        self[i]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Points of dimensionality `D` stored one after the other in a flat slice. The point at index `i` starts at
+/// `data[i * stride]`, so rows with extra trailing values can be read by using a stride greater than `D`.
+pub struct StridedPoints<'a, F, const D: usize> {
+    data: &'a [F],
+    stride: usize
+}
+
+impl <'a, F: Float, const D: usize> StridedPoints<'a, F, D> {
+    /// Wraps `data` without copying it. Panics if `stride` is less than `D`.
+    pub fn new(data: &'a [F], stride: usize) -> StridedPoints<'a, F, D> {
+        if stride < D || stride == 0 {
+            panic!("DBSCAN: the stride of the points must be at least {}, but it is {}", D.max(1), stride);
+        }
+        StridedPoints{data, stride}
+    }
+}
+
+impl <'a, F: Float, const D: usize> PointSource<F, D> for StridedPoints<'a, F, D> {
+    fn len(&self) -> usize {
+        if self.data.len() < D {
+            0
+        } else {
+            // the last point does not need the padding that follows the other ones
+            (self.data.len() - D) / self.stride + 1
+        }
+    }
+
+    fn point(&self, i: usize) -> Point<D, F> {
+        let mut point = [F::default(); D];
+        point.copy_from_slice(&self.data[i * self.stride .. i * self.stride + D]);
+        point
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Each row of the view is a point. The view must have exactly `D` columns.
+impl <'a, F: Float, const D: usize> PointSource<F, D> for ndarray::ArrayView2<'a, F> {
+    fn len(&self) -> usize {
+        check_columns::<F, D>(self);
+        self.nrows()
+    }
+
+    fn point(&self, i: usize) -> Point<D, F> {
+        // checked here too since a shorter row would silently be padded with zeros
+        check_columns::<F, D>(self);
+        let row = self.row(i);
+        let mut point = [F::default(); D];
+        for (coord, value) in point.iter_mut().zip(row.iter()) {
+            *coord = *value;
+        }
+        point
+    }
+}
+
+#[cfg(feature = "ndarray")]
+/// Panics if the rows of `view` do not have `D` columns
+fn check_columns<F, const D: usize>(view: &ndarray::ArrayView2<'_, F>) {
+    if view.ncols() != D {
+        panic!("DBSCAN: expected all points to have {} components, but the array has {} columns", D, view.ncols());
+    }
+}
+
+/// Labels of all the points given in input to the approximate DBSCAN algorithm. The label at index `i`
+/// refers to the `i`-th input point.
+pub type DBSCANLabels = Vec<PointLabel>;
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The labels found by the approximate DBSCAN algorithm together with the parameters used to find them, 
+/// so that they can be stored and reloaded as a whole
+pub struct ClusteringResult {
+    /// The parameters used for the clustering
+    pub params: DBSCANParams,
+    /// The number of clusters found, noise excluded
+    pub clusters_count: usize,
+    /// The label of each point, in the same order as the input
+    pub labels: DBSCANLabels
+}
+
+impl ClusteringResult {
+    pub fn new(params: DBSCANParams, labels: DBSCANLabels) -> ClusteringResult {
+        let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+        ClusteringResult{params, clusters_count, labels}
+    }
+}
 
 /// Point defined as a vector instead of as an array like in `utils::Point`.
 /// Used for when dimensionality is not previously known.
@@ -184,18 +518,16 @@ pub type VectorDBSCANResult = Vec<VectorCluster>;
 /// Translates a vector of points represented as vectors in a vector of points represented ad fixed length arrays.
 /// Panics if the points do not all have the same length.
 pub fn vector_input_to_array_input<const D: usize>(v_in: Vec<VectorPoint>) -> Vec<Point<D>> {
-    if v_in.len() == 0 {
+    if v_in.is_empty() {
         panic!("Received an unexpected 0 length vector. This should not have happened");
     }
     let mut arr_in = Vec::with_capacity(v_in.len());
-    for i in 1..v_in.len() {
-        if v_in[i].len() != D {
-            panic!("DBSCAN: expected all points to have {} components, but point {} has {} components instead",D, i, v_in[i].len());
+    for (i, v_point) in v_in.iter().enumerate() {
+        if v_point.len() != D {
+            panic!("DBSCAN: expected all points to have {} components, but point {} has {} components instead",D, i, v_point.len());
         }
         let mut arr_point = [0.0;D];
-        for j in 0..D {
-            arr_point[j] = v_in[i][j];
-        }
+        arr_point.copy_from_slice(v_point);
         arr_in.push(arr_point);
     }
     arr_in
@@ -203,17 +535,70 @@ pub fn vector_input_to_array_input<const D: usize>(v_in: Vec<VectorPoint>) -> Ve
 
 /// Transforms a vector of clusters containing points represented as arrays into a vector of clusters
 /// where each point is represented as a vector.
-pub fn array_res_to_vector_res<const D: usize>(a_res: DBSCANResult<D>) -> VectorDBSCANResult {
+pub fn array_res_to_vector_res<F: Float, const D: usize>(a_res: DBSCANResult<D, F>) -> VectorDBSCANResult {
     let mut v_res : VectorDBSCANResult = Vec::with_capacity(a_res.len());
-    for i in 0..a_res.len() {
-        let mut v_cluster = Vec::with_capacity(a_res[i].len());
-        for a_point in &a_res[i] {
-            v_cluster.push(a_point.to_vec());
+    for a_cluster in &a_res {
+        let mut v_cluster = Vec::with_capacity(a_cluster.len());
+        for a_point in a_cluster {
+            v_cluster.push(a_point.iter().map(|x| x.to_f64()).collect());
         }
         v_res.push(v_cluster);
     }
     v_res
 }
 
+/// Groups the points in `points` in clusters following their labels in `labels`. The result has the same layout
+/// as the one returned by the approximate DBSCAN algorithm, with the noise points at index `0`.
+/// Border points appear only in the cluster they were labeled with.
+pub fn labels_to_vector_res<P: AsRef<[f64]>>(points: &[P], labels: &DBSCANLabels) -> VectorDBSCANResult {
+    let clusters_count = labels.iter().map(|l| l.cluster_id).max().unwrap_or(0);
+    let mut v_res : VectorDBSCANResult = vec![Vec::new(); clusters_count + 1];
+    for (point, label) in points.iter().zip(labels.iter()) {
+        v_res[label.cluster_id].push(point.as_ref().to_vec());
+    }
+    v_res
+}
+
+#[cfg(feature = "serde")]
+/// Serialization of arrays of any length, since `serde` only supports arrays with up to 32 elements of a given length.
+/// To be used with `#[serde(with = "crate::utils::serde_array")]`
+pub(crate) mod serde_array {
+    use std::fmt;
+    use std::marker::PhantomData;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+    use serde::ser::SerializeTuple;
+    use serde::de::{Visitor, SeqAccess, Error};
+
+    pub fn serialize<S: Serializer, T: Serialize, const D: usize>(array: &[T; D], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(D)?;
+        for element in array {
+            tuple.serialize_element(element)?;
+        }
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, De: Deserializer<'de>, T: Deserialize<'de> + Copy + Default, const D: usize>(deserializer: De) -> Result<[T; D], De::Error> {
+        deserializer.deserialize_tuple(D, ArrayVisitor(PhantomData))
+    }
+
+    struct ArrayVisitor<T, const D: usize>(PhantomData<T>);
+
+    impl <'de, T: Deserialize<'de> + Copy + Default, const D: usize> Visitor<'de> for ArrayVisitor<T, D> {
+        type Value = [T; D];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "an array of length {}", D)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; D], A::Error> {
+            let mut array = [T::default(); D];
+            for (i, element) in array.iter_mut().enumerate() {
+                *element = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            Ok(array)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests;
\ No newline at end of file
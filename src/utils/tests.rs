@@ -1,10 +1,115 @@
 use super::*;
+use proptest::prelude::*;
+
+/// Gets the coordinates of all the corners (2^D) of a cell given its center points and its side size.
+fn get_corners<const D: usize>(cell_center: &CellCenter<D>, side_size: f64) -> Vec<Point<D>>{
+    let dist = side_size/2.0;
+    let top = 2_usize.pow(D as u32);
+    let mut corners = Vec::with_capacity(top);
+    for bin_rep in 0..top {
+        let mut new_corner = *cell_center;
+        for (bit_i, coord) in new_corner.iter_mut().enumerate() {
+            let mask = 1 << bit_i;
+            if bin_rep & mask == 0 {
+                *coord -= dist;
+            } else {
+                *coord += dist;
+            }
+        }
+        corners.push(new_corner);
+    }
+    corners
+}
+
+/// The previous implementation of `determine_intersection`, that measures the distance of `q` from each corner of the cell.
+/// Kept as a reference for the closed form one
+fn determine_intersection_corners<const D: usize>(q: &Point<D>, params: &DBSCANParams, index_c: &CellIndex<D>, side_size:f64) -> IntersectionType{
+    let n_corners = 2_usize.pow(D as u32);
+    let mut cell_center : CellCenter<D> = [0.0;D];
+    for i in 0..D {
+        cell_center[i] = index_c[i] as f64 * side_size;
+    }
+    let corners = get_corners(&cell_center, side_size);
+    let appr_dist = (1.0 + params.rho) * params.epsilon;
+    let mut appr_in_count : usize = 0;
+    let mut out_count : usize = 0;
+    for corner in corners {
+        let dist = euclidean_distance(q, &corner);
+        if dist <= appr_dist {
+            appr_in_count += 1;
+        }
+        if dist >= params.epsilon {
+            out_count += 1;
+        }
+    }
+    if appr_in_count == n_corners{
+        return IntersectionType::FullyCovered
+    } else if out_count == n_corners{
+        return IntersectionType::Disjoint
+    }
+    IntersectionType::Intersecting
+}
+
+/// The distance between `q` and the closest point of the cell
+fn cell_distance<const D: usize>(q: &Point<D>, index_c: &CellIndex<D>, side_size: f64) -> f64 {
+    let mut closest = [0.0; D];
+    for i in 0..D {
+        let center = index_c[i] as f64 * side_size;
+        closest[i] = q[i].max(center - side_size / 2.0).min(center + side_size / 2.0);
+    }
+    euclidean_distance(q, &closest)
+}
+
+/// Checks that the closed form `determine_intersection` agrees with the corners based one. The only allowed difference
+/// is a cell that has all its corners outside of the ball of radius `epsilon` but is still reached by the ball,
+/// for example through the middle of a face: the corners based implementation wrongly reports it as disjoint.
+fn check_against_corners<const D: usize>(q: Point<D>, index_c: CellIndex<D>, side_size: f64, epsilon: f64, rho: f64) -> Result<(), TestCaseError> {
+    let params = DBSCANParams{cardinality: 1, dimensionality: D as u32, epsilon, rho, min_pts: 1};
+    let closed_form = determine_intersection(&q, &params, &index_c, side_size);
+    let corners = determine_intersection_corners(&q, &params, &index_c, side_size);
+    if corners == IntersectionType::Disjoint && closed_form == IntersectionType::Intersecting {
+        prop_assert!(cell_distance(&q, &index_c, side_size) < epsilon);
+    } else {
+        prop_assert_eq!(closed_form, corners);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn determine_intersection_1d_prop(q in prop::array::uniform1(-4.0..4.0), index_c in prop::array::uniform1(-4_i64..4), side_size in 0.05..2.0, epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_corners(q, index_c, side_size, epsilon, rho)?;
+    }
+
+    #[test]
+    fn determine_intersection_2d_prop(q in prop::array::uniform2(-4.0..4.0), index_c in prop::array::uniform2(-4_i64..4), side_size in 0.05..2.0, epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_corners(q, index_c, side_size, epsilon, rho)?;
+    }
+
+    #[test]
+    fn determine_intersection_3d_prop(q in prop::array::uniform3(-4.0..4.0), index_c in prop::array::uniform3(-4_i64..4), side_size in 0.05..2.0, epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_corners(q, index_c, side_size, epsilon, rho)?;
+    }
+
+    #[test]
+    fn determine_intersection_5d_prop(q in prop::array::uniform5(-4.0..4.0), index_c in prop::array::uniform5(-4_i64..4), side_size in 0.05..2.0, epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_corners(q, index_c, side_size, epsilon, rho)?;
+    }
+
+    #[test]
+    fn determine_intersection_7d_prop(q in prop::array::uniform7(-4.0..4.0), index_c in prop::array::uniform7(-4_i64..4), side_size in 0.05..2.0, epsilon in 0.1..3.0, rho in 0.01..1.0) {
+        check_against_corners(q, index_c, side_size, epsilon, rho)?;
+    }
+}
 
 #[test]
 fn euclidean_distance_test() {
     let p = [0.0,0.0];
     let q = [2.0,2.0];
     assert_eq!(euclidean_distance(&p,&q), 2_f64 * 2_f64.sqrt());
+    let p : Point<2, f32> = [0.0,0.0];
+    let q : Point<2, f32> = [2.0,2.0];
+    assert_eq!(euclidean_distance(&p,&q), 2_f32 * 2_f32.sqrt());
 }
 
 #[test]
@@ -114,6 +219,18 @@ fn determine_intersection_test() {
     assert_eq!(intersection, expected_type);
 }
 
+#[test]
+fn determine_intersection_face_test() {
+    // a ball that reaches the middle of a face of a 7 dimensional cell without containing any of its corners
+    let params = DBSCANParams{cardinality: 1, dimensionality: 7, epsilon: 1.0, rho: 0.1, min_pts: 1};
+    let side_size = params.epsilon / 7_f64.sqrt();
+    let q = [side_size / 2.0 + 0.95, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+    assert_eq!(determine_intersection_corners(&q, &params, &[0; 7], side_size), IntersectionType::Disjoint);
+    assert_eq!(determine_intersection(&q, &params, &[0; 7], side_size), IntersectionType::Intersecting);
+    let q_f32 = q.map(|x| x as f32);
+    assert_eq!(determine_intersection(&q_f32, &params, &[0; 7], side_size), IntersectionType::Intersecting);
+}
+
 /*#[test]
 fn get_neighbours_test(){
     let cell_index = [0,0];
@@ -129,7 +246,7 @@ fn get_cell_index_test() {
     let index = get_cell_index(&point, l);
     assert_eq!(index[0], 0);
     assert_eq!(index[1], 1);
-    let point = [1.5 * l, -1.0 * l];
+    let point = [1.5 * l, -l];
     let index = get_cell_index(&point, l);
     assert_eq!(index[0], 1);
     assert_eq!(index[1], -1);
@@ -145,5 +262,110 @@ fn get_cell_index_test() {
     let index = get_cell_index(&point, l);
     assert_eq!(index[0], 0);
     assert_eq!(index[1], 0);
+    let point : Point<2, f32> = [-1.5 * 15.0, 2.5 * 15.0];
+    let index = get_cell_index(&point, l);
+    assert_eq!(index[0], -2);
+    assert_eq!(index[1], 2);
     
-}
\ No newline at end of file
+}
+#[test]
+fn metric_test() {
+    assert_eq!("Cosine".parse::<Metric>(), Ok(Metric::Cosine));
+    assert!("manhattan".parse::<Metric>().is_err());
+    let mut p = [3.0, 4.0];
+    Metric::Euclidean.transform_point(&mut p);
+    assert_eq!(p, [3.0, 4.0]);
+    assert_eq!(Metric::Euclidean.euclidean_epsilon(0.5), 0.5);
+    Metric::Cosine.transform_point(&mut p);
+    assert_eq!(p, [0.6, 0.8]);
+    let mut zero = [0.0, 0.0];
+    Metric::Cosine.transform_point(&mut zero);
+    assert_eq!(zero, [0.0, 0.0]);
+    // orthogonal vectors have cosine distance 1
+    let mut q = [0.0, 7.0];
+    let mut r = [2.0, 0.0];
+    Metric::Cosine.transform_point(&mut q);
+    Metric::Cosine.transform_point(&mut r);
+    assert!((euclidean_distance(&q, &r) - Metric::Cosine.euclidean_epsilon(1.0)).abs() < 1e-12);
+}
+
+#[test]
+fn strided_points_test() {
+    let data = [1.0, 2.0, 0.0, 3.0, 4.0, 0.0, 5.0, 6.0];
+    let points = StridedPoints::<f64, 2>::new(&data, 3);
+    assert_eq!(points.len(), 3);
+    assert_eq!(points.point(0), [1.0, 2.0]);
+    assert_eq!(points.point(2), [5.0, 6.0]);
+    let points = StridedPoints::<f64, 2>::new(&data[..1], 2);
+    assert!(points.is_empty());
+    let slice : &[Point<2>] = &[[1.0, 2.0], [3.0, 4.0]];
+    assert_eq!(PointSource::len(slice), 2);
+    assert_eq!(slice.point(1), [3.0, 4.0]);
+}
+
+#[test]
+#[should_panic]
+fn strided_points_short_stride_test() {
+    StridedPoints::<f32, 3>::new(&[0.0; 6], 2);
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn array_view_points_test() {
+    let array = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+    let view = array.view();
+    assert_eq!(PointSource::<f64, 2>::len(&view), 3);
+    assert_eq!(PointSource::<f64, 2>::point(&view, 1), [3.0, 4.0]);
+    let transposed = array.t();
+    assert_eq!(PointSource::<f64, 3>::point(&transposed, 1), [2.0, 4.0, 6.0]);
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+#[should_panic(expected = "the array has 2 columns")]
+fn array_view_short_rows_test() {
+    let array = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]);
+    PointSource::<f64, 3>::point(&array.view(), 0);
+}
+
+/// Checks `count_within_sq` against the squared distances of the points computed one at a time
+fn check_count_within_sq<F: Float, const D: usize>(points: &[Point<D, F>], q: Point<D, F>, epsilon: F) -> Result<(), TestCaseError> {
+    let axes : [Vec<F>; D] = std::array::from_fn(|i| points.iter().map(|p| p[i]).collect());
+    let epsilon_sq = epsilon * epsilon;
+    let expected = points.iter().filter(|p| squared_euclidean_distance(p, &q) <= epsilon_sq).count();
+    prop_assert_eq!(count_within_sq(&axes, 0..points.len(), &q, epsilon_sq), expected);
+    // a range that starts in the middle of a batch
+    let start = points.len() / 3;
+    let expected = points[start..].iter().filter(|p| squared_euclidean_distance(p, &q) <= epsilon_sq).count();
+    prop_assert_eq!(count_within_sq(&axes, start..points.len(), &q, epsilon_sq), expected);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn count_within_sq_2d_prop(points in prop::collection::vec(prop::array::uniform2(-2.0..2.0), 0..40), q in prop::array::uniform2(-2.0..2.0), epsilon in 0.1..2.0) {
+        check_count_within_sq(&points, q, epsilon)?;
+    }
+
+    #[test]
+    fn count_within_sq_3d_f32_prop(points in prop::collection::vec(prop::array::uniform3(-2.0_f32..2.0), 0..40), q in prop::array::uniform3(-2.0_f32..2.0), epsilon in 0.1_f32..2.0) {
+        check_count_within_sq(&points, q, epsilon)?;
+    }
+
+    #[test]
+    fn count_within_sq_7d_prop(points in prop::collection::vec(prop::array::uniform7(-2.0..2.0), 0..40), q in prop::array::uniform7(-2.0..2.0), epsilon in 0.5..4.0) {
+        check_count_within_sq(&points, q, epsilon)?;
+    }
+}
+
+#[test]
+fn count_within_sq_boundary_test() {
+    // points exactly at distance epsilon are counted, in the batches and in the remainder
+    let axes = [vec![1.0, -1.0, 0.0, 0.0, 0.5, 3.0, 1.0, 0.0, 1.0], vec![0.0, 0.0, 1.0, -1.0, 0.5, 3.0, 0.0, 1.0, 1.0]];
+    assert_eq!(count_within_sq(&axes, 0..9, &[0.0, 0.0], 1.0), 7);
+    assert_eq!(count_within_sq(&axes, 2..7, &[0.0, 0.0], 1.0), 4);
+    let axes = [vec![1.0_f32; 17], vec![0.0_f32; 17]];
+    assert_eq!(count_within_sq(&axes, 0..17, &[0.0, 0.0], 1.0), 17);
+    let empty : [Vec<f64>; 3] = Default::default();
+    assert_eq!(count_within_sq(&empty, 0..0, &[0.0; 3], 1.0), 0);
+}
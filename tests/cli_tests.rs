@@ -0,0 +1,70 @@
+use std::process::Command;
+
+fn run_cli(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_appr_dbscan")).args(args).output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn cluster_command_test() {
+    let out_file = std::env::temp_dir().join("appr_dbscan_cli_cluster_test.tsv");
+    let (code, stdout) = run_cli(&["cluster", "--input", "./datasets/out_test_1.txt", "--epsilon", "0.3", "--rho=0.1", "--min-pts", "10", "--output", out_file.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Found 5 clusters and 0 noise points"));
+    let written = std::fs::read_to_string(&out_file).unwrap();
+    assert_eq!(written.lines().next(), Some("x0\tx1\tcluster_id\trole"));
+    assert_eq!(written.lines().count(), 20001);
+    std::fs::remove_file(&out_file).unwrap();
+}
+
+#[test]
+fn sweep_command_test() {
+    let (code, stdout) = run_cli(&["sweep", "--input", "./datasets/out20000.txt", "--epsilon", "0.1:0.3:0.1", "--min-pts", "15"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.lines().count(), 4);
+    assert!(stdout.lines().any(|l| l == "0.3\t15\t4\t0"));
+}
+
+#[test]
+fn exit_codes_test() {
+    assert_eq!(run_cli(&[]).0, 2);
+    assert_eq!(run_cli(&["help"]).0, 0);
+    assert_eq!(run_cli(&["frobnicate"]).0, 2);
+    assert_eq!(run_cli(&["cluster", "--input", "./datasets/out_test_1.txt", "--min-pts", "10"]).0, 2);
+    assert_eq!(run_cli(&["cluster", "--input", "./datasets/out_test_1.txt", "--epsilon", "0", "--min-pts", "10"]).0, 2);
+    assert_eq!(run_cli(&["cluster", "--input", "./datasets/out_test_1.txt", "--epsilon", "0.3", "--min-pts", "10", "--metric", "hamming"]).0, 2);
+    assert_eq!(run_cli(&["suggest-eps", "--input", "./datasets/missing.txt", "--min-pts", "10"]).0, 1);
+}
+
+#[test]
+fn generate_command_test() {
+    let points_file = std::env::temp_dir().join("appr_dbscan_cli_generate_points.txt");
+    let labels_file = std::env::temp_dir().join("appr_dbscan_cli_generate_labels.txt");
+    let (code, stdout) = run_cli(&["generate", "--shape", "blobs", "--count", "2000", "--dimensionality", "3", "--clusters", "4", "--spread", "0.5", "--noise", "20", "--seed", "7", "--output", points_file.to_str().unwrap(), "--labels", labels_file.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.trim(), "Generated 2020 points in 4 clusters and 20 noise points");
+    let points = std::fs::read_to_string(&points_file).unwrap();
+    assert_eq!(points.lines().count(), 2020);
+    assert!(points.lines().all(|l| l.split_whitespace().count() == 3));
+    let (code, stdout) = run_cli(&["evaluate", "--input", points_file.to_str().unwrap(), "--labels", labels_file.to_str().unwrap(), "--epsilon", "0.5", "--min-pts", "10"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Found 4 clusters"), "{}", stdout);
+    assert_eq!(run_cli(&["generate", "--shape", "spirals", "--count", "10", "--output", points_file.to_str().unwrap()]).0, 2);
+    assert_eq!(run_cli(&["generate", "--shape", "moons", "--count", "10", "--dimensionality", "1", "--output", points_file.to_str().unwrap()]).0, 2);
+    std::fs::remove_file(&points_file).unwrap();
+    std::fs::remove_file(&labels_file).unwrap();
+}
+
+#[test]
+fn cosine_output_test() {
+    // the points are clustered on the unit sphere but written as they are in the input
+    let out_file = std::env::temp_dir().join("appr_dbscan_cli_cosine_test.csv");
+    let (code, _) = run_cli(&["cluster", "--input", "./datasets/out_test_small.txt", "--epsilon", "0.1", "--min-pts", "5", "--metric", "cosine", "--output", out_file.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    let input = std::fs::read_to_string("./datasets/out_test_small.txt").unwrap();
+    let written = std::fs::read_to_string(&out_file).unwrap();
+    let input_point : Vec<f64> = input.lines().next().unwrap().split_whitespace().map(|c| c.parse().unwrap()).collect();
+    let written_point : Vec<f64> = written.lines().nth(1).unwrap().split(',').take(input_point.len()).map(|c| c.parse().unwrap()).collect();
+    assert_eq!(written_point, input_point);
+    std::fs::remove_file(&out_file).unwrap();
+}
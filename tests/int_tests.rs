@@ -9,18 +9,21 @@ fn out_3_test_1() {
     assert_eq!(res[0].len(),1476);
 }
 
+// The noise counts of the next two tests changed when `determine_intersection` started detecting the balls that reach a 
+// cell through a face without containing any of its corners: a few points at distance between `epsilon` and 
+// `epsilon(1 + rho)` from a core point are now border points, which the approximation allows.
 #[test]
 fn out_3_test_2() {
     let res : DBSCANResult<3> = do_appr_dbscan_file("./datasets/out_3.txt", 1.25, 0.7, 15);
     assert_eq!(res.len() - 1, 18);
-    assert_eq!(res[0].len(),2038);
+    assert_eq!(res[0].len(),2032);
 }
 
 #[test]
 fn out_3_test_3() {
     let res : DBSCANResult<3> = do_appr_dbscan_file("./datasets/out_3.txt", 1.25, 0.001, 15);
     assert_eq!(res.len() - 1, 33);
-    assert_eq!(res[0].len(),3162);
+    assert_eq!(res[0].len(),3161);
 }
 
 
@@ -44,4 +47,44 @@ fn out20000_test_3() {
     let (res,_) = do_appr_dbscan_auto_dimensionality_file("./datasets/out20000.txt", 0.05, 0.0001, 15);
     assert_eq!(res.len() - 1, 8);
     assert_eq!(res[0].len(),305);
-}
\ No newline at end of file
+}
+#[test]
+fn const_generic_dimensionalities_test() {
+    use appr_dbscan::evaluation::adjusted_rand_index;
+    use appr_dbscan::generate::blobs;
+    use appr_dbscan::utils::{vector_input_to_array_input, Point};
+    fn check<const D: usize>() {
+        let dataset = blobs(400, D, 2, 1.0, 0);
+        let points : Vec<Point<D>> = vector_input_to_array_input(dataset.points);
+        let res : DBSCANResult<D> = appr_dbscan::do_appr_dbscan_points(points.clone(), 2.5, 0.1, 5);
+        assert_eq!(res.len() - 1, 2);
+        let labels = appr_dbscan::do_appr_dbscan_labels_points(points, 2.5, 0.1, 5);
+        let predicted : Vec<usize> = labels.iter().map(|l| l.cluster_id).collect();
+        let ari = adjusted_rand_index(&dataset.labels, &predicted);
+        assert!(ari > 0.95, "{} dimensions: {}", D, ari);
+        let index = appr_dbscan::utils::CellIndexPoint::<D>{index: [1; D]};
+        assert_eq!(rstar::Point::nth(&index, D - 1), 1);
+    }
+    check::<1>();
+    check::<2>();
+    check::<3>();
+    check::<4>();
+    check::<5>();
+    check::<6>();
+    check::<7>();
+}
+
+#[test]
+fn generated_datasets_test() {
+    use appr_dbscan::do_appr_dbscan_auto_dimensionality_labels_points;
+    use appr_dbscan::evaluation::adjusted_rand_index;
+    use appr_dbscan::generate::{blobs, moons, rings, Dataset};
+    fn ari(dataset: Dataset, epsilon: f64, min_pts: usize) -> f64 {
+        let (labels, _) = do_appr_dbscan_auto_dimensionality_labels_points(dataset.points, epsilon, 0.1, min_pts);
+        let predicted : Vec<usize> = labels.iter().map(|l| l.cluster_id).collect();
+        adjusted_rand_index(&dataset.labels, &predicted)
+    }
+    assert!(ari(blobs(3000, 3, 5, 0.5, 1), 0.5, 10) > 0.95);
+    assert!(ari(moons(2000, 2, 0.05, 2), 0.15, 10) > 0.95);
+    assert!(ari(rings(3000, 2, 3, 0.05, 3), 0.2, 10) > 0.95);
+}